@@ -1,3 +1,14 @@
+//! - <https://musicbrainz.org/doc/Cover_Art_Archive/API>
+
+use std::collections::HashMap;
+
+use musicbrainz::{request_client::ProgramInfo, Id as Mbid, Release, ReleaseGroup};
+use serde::Deserialize;
+
+pub const API_ROOT: &str = "https://coverartarchive.org/";
+
+/// The ID of an image in the archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Id(u64);
 impl Id {
     pub fn into_inner(self) -> u64 {
@@ -14,3 +25,143 @@ impl From<Id> for u64 {
         value.into_inner()
     }
 }
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // older listings have string IDs
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw { Number(u64), String(String) }
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(id) => Ok(Self(id)),
+            Raw::String(id) => id.parse().map(Self).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// Listings link over plain HTTP, which some consumers (e.g. Discord) won't load.
+fn upgrade_to_https(url: &str) -> String {
+    match url.strip_prefix("http://") {
+        Some(rest) => format!("https://{rest}"),
+        None => url.to_owned(),
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Image {
+    pub id: Id,
+    /// The original upload, which can be very large.
+    pub image: String,
+    /// Keyed by the size of their longest side (e.g. `250`, `500` and `1200`), as well as legacy `small` and `large` aliases.
+    #[serde(default)]
+    pub thumbnails: HashMap<String, String>,
+    pub front: bool,
+    pub back: bool,
+    /// E.g. `Front`, `Back` or `Booklet`.
+    #[serde(default)]
+    pub types: Vec<String>,
+    #[serde(default)]
+    pub approved: bool,
+}
+impl Image {
+    /// The smallest thumbnail at least `size` pixels on its longest side, or the original if none are that large.
+    /// The URLs are stable, since they're derived from the IDs of the release and image.
+    pub fn url_at(&self, size: u16) -> String {
+        let thumbnail = self.thumbnails.iter()
+            .filter_map(|(key, url)| Some((key.parse::<u16>().ok()?, url)))
+            .filter(|(thumbnail, _)| *thumbnail >= size)
+            .min_by_key(|(thumbnail, _)| *thumbnail);
+        upgrade_to_https(thumbnail.map_or(&self.image, |(_, url)| url))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Listing {
+    pub images: Vec<Image>,
+    /// The MusicBrainz URL of the release the images are of; for release groups, the release its artwork was chosen from.
+    pub release: String,
+}
+impl Listing {
+    pub fn front(&self) -> Option<&Image> {
+        self.images.iter().find(|image| image.front)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ListingError {
+    #[error("network failure: {0}")]
+    NetworkFailure(#[from] reqwest::Error),
+    #[error("could not deserialize response: {0}")]
+    Deserialization(#[from] serde_json::Error),
+    #[error("error {0}: {1}")]
+    Other(reqwest::StatusCode, String),
+}
+
+pub struct Client {
+    net: reqwest::Client,
+    limiter: shared::ratelimit::RateLimiter,
+}
+impl Client {
+    pub fn new<PS: AsRef<str>>(program: &ProgramInfo<PS>) -> Self {
+        let net = reqwest::ClientBuilder::new()
+            .user_agent(program.to_user_agent())
+            .build().expect("could not build network client");
+        Self { net, limiter: shared::ratelimit::RateLimiter::new(Default::default()) }
+    }
+
+    /// Returns `None` if there's no artwork for the entity.
+    async fn listing(&self, path: String) -> Result<Option<Listing>, ListingError> {
+        let response = self.limiter.send_idempotent(self.net.get(format!("{API_ROOT}{path}"))).await?;
+
+        use reqwest::StatusCode;
+        let code = response.status();
+        let body = response.text().await?;
+        match code {
+            StatusCode::OK => Ok(Some(serde_json::from_str(&body)?)),
+            StatusCode::NOT_FOUND => Ok(None),
+            code => Err(ListingError::Other(code, body)),
+        }
+    }
+
+    /// - <https://musicbrainz.org/doc/Cover_Art_Archive/API#/release/{mbid}/>
+    pub async fn release(&self, id: Mbid<Release>) -> Result<Option<Listing>, ListingError> {
+        self.listing(format!("release/{id}")).await
+    }
+
+    /// The artwork chosen to represent the release group, which is that of one of its releases.
+    /// - <https://musicbrainz.org/doc/Cover_Art_Archive/API#/release-group/{mbid}/>
+    pub async fn release_group(&self, id: Mbid<ReleaseGroup>) -> Result<Option<Listing>, ListingError> {
+        self.listing(format!("release-group/{id}")).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LISTING: &str = r#"{
+        "images":[
+            {"approved":true,"back":true,"comment":"","edit":1,"front":false,"id":"1234","image":"http://coverartarchive.org/release/f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1/1234.jpg",
+             "thumbnails":{},"types":["Back"]},
+            {"approved":true,"back":false,"comment":"","edit":2,"front":true,"id":5678,"image":"http://coverartarchive.org/release/f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1/5678.jpg",
+             "thumbnails":{
+                "250":"http://coverartarchive.org/release/f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1/5678-250.jpg",
+                "500":"http://coverartarchive.org/release/f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1/5678-500.jpg",
+                "1200":"http://coverartarchive.org/release/f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1/5678-1200.jpg",
+                "small":"http://coverartarchive.org/release/f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1/5678-250.jpg",
+                "large":"http://coverartarchive.org/release/f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1/5678-500.jpg"
+             },"types":["Front"]}
+        ],
+        "release":"https://musicbrainz.org/release/f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1"
+    }"#;
+
+    #[test]
+    fn picks_front_thumbnail() {
+        let listing = serde_json::from_str::<Listing>(LISTING).unwrap();
+        let front = listing.front().unwrap();
+        assert_eq!(front.id, Id(5678));
+        assert_eq!(front.url_at(300), "https://coverartarchive.org/release/f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1/5678-500.jpg");
+        assert_eq!(front.url_at(1200), "https://coverartarchive.org/release/f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1/5678-1200.jpg");
+        assert_eq!(front.url_at(2000), "https://coverartarchive.org/release/f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1/5678.jpg");
+        assert_eq!(listing.images[0].id, Id(1234));
+    }
+}
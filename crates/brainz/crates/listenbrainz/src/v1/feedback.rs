@@ -0,0 +1,150 @@
+//! Loving and hating recordings.
+//! - <https://listenbrainz.readthedocs.io/en/latest/users/api/recordings.html#feedback-api>
+
+use musicbrainz::{Id, Recording};
+use serde::{Deserialize, Serialize};
+
+/// How the user feels about a recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeedbackScore {
+    Love,
+    Hate,
+    /// Clears any earlier love or hate.
+    Remove,
+}
+impl FeedbackScore {
+    pub const fn to_i8(self) -> i8 {
+        match self {
+            Self::Love => 1,
+            Self::Hate => -1,
+            Self::Remove => 0,
+        }
+    }
+    pub const fn from_i8(score: i8) -> Option<Self> {
+        match score {
+            1 => Some(Self::Love),
+            -1 => Some(Self::Hate),
+            0 => Some(Self::Remove),
+            _ => None,
+        }
+    }
+}
+impl Serialize for FeedbackScore {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i8(self.to_i8())
+    }
+}
+impl<'de> Deserialize<'de> for FeedbackScore {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let score = i8::deserialize(deserializer)?;
+        Self::from_i8(score).ok_or_else(|| serde::de::Error::custom(format!("unknown feedback score: {score}")))
+    }
+}
+
+/// Which recording feedback is about; recordings without an MBID can be referred to by the MessyBrainz ID of one of their listens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedbackRecording<'a> {
+    Mbid(Id<Recording>),
+    Msid(&'a str),
+}
+impl From<Id<Recording>> for FeedbackRecording<'_> {
+    fn from(id: Id<Recording>) -> Self {
+        Self::Mbid(id)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct RawFeedbackBody<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recording_mbid: Option<Id<Recording>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recording_msid: Option<&'a str>,
+    score: FeedbackScore,
+}
+impl<'a> RawFeedbackBody<'a> {
+    pub fn new(recording: FeedbackRecording<'a>, score: FeedbackScore) -> Self {
+        match recording {
+            FeedbackRecording::Mbid(id) => Self { recording_mbid: Some(id), recording_msid: None, score },
+            FeedbackRecording::Msid(id) => Self { recording_mbid: None, recording_msid: Some(id), score },
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FeedbackSubmissionError {
+    #[error("network failure: {0}")]
+    NetworkFailure(#[from] reqwest::Error),
+    #[error("ratelimited")]
+    Ratelimited,
+    #[error(transparent)]
+    InvalidToken(#[from] super::error::InvalidTokenError),
+    #[error("error {0}: {1}")]
+    Other(reqwest::StatusCode, String)
+}
+
+/// Parameters for retrieving the feedback a user has given.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FeedbackQuery {
+    /// Only return feedback with this score.
+    pub score: Option<FeedbackScore>,
+    /// The number of entries to return; clamped to [`MAX_ITEMS_PER_GET`](crate::constants::MAX_ITEMS_PER_GET).
+    pub count: Option<u16>,
+    pub offset: Option<u32>,
+}
+impl FeedbackQuery {
+    pub(crate) fn to_query(self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::with_capacity(3);
+        if let Some(score) = self.score { query.push(("score", score.to_i8().to_string())) }
+        if let Some(count) = self.count { query.push(("count", count.min(crate::constants::MAX_ITEMS_PER_GET).to_string())) }
+        if let Some(offset) = self.offset { query.push(("offset", offset.to_string())) }
+        query
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Feedback {
+    /// Seconds since the Unix epoch.
+    pub created: i64,
+    #[serde(default)]
+    pub recording_mbid: Option<Id<Recording>>,
+    #[serde(default)]
+    pub recording_msid: Option<String>,
+    pub score: FeedbackScore,
+}
+
+/// Unlike most read endpoints, this isn't wrapped in a `payload`.
+#[derive(Debug, Deserialize)]
+pub struct FeedbackPage {
+    pub count: usize,
+    pub offset: u32,
+    /// How much feedback matching the query there is in total.
+    pub total_count: u64,
+    pub feedback: Vec<Feedback>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_body() {
+        let id = serde_json::from_str::<Id<Recording>>(r#""b1a9c0e9-d987-4042-ae91-78d6a3267d69""#).unwrap();
+        let love = serde_json::to_string(&RawFeedbackBody::new(id.into(), FeedbackScore::Love)).unwrap();
+        assert_eq!(love, r#"{"recording_mbid":"b1a9c0e9-d987-4042-ae91-78d6a3267d69","score":1}"#);
+        let remove = serde_json::to_string(&RawFeedbackBody::new(FeedbackRecording::Msid("some-msid"), FeedbackScore::Remove)).unwrap();
+        assert_eq!(remove, r#"{"recording_msid":"some-msid","score":0}"#);
+    }
+
+    #[test]
+    fn deserializes_page() {
+        let body = r#"{"count":2,"offset":0,"total_count":2,"feedback":[
+            {"created":1700000000,"recording_mbid":"b1a9c0e9-d987-4042-ae91-78d6a3267d69","recording_msid":null,"score":1,"user_id":"someone"},
+            {"created":1700000100,"recording_mbid":null,"recording_msid":"some-msid","score":-1,"user_id":"someone"}
+        ]}"#;
+        let page = serde_json::from_str::<FeedbackPage>(body).unwrap();
+        assert_eq!(page.feedback[0].score, FeedbackScore::Love);
+        assert!(page.feedback[0].recording_mbid.is_some());
+        assert_eq!(page.feedback[1].score, FeedbackScore::Hate);
+        assert!(serde_json::from_str::<FeedbackScore>("2").is_err());
+    }
+}
@@ -0,0 +1,91 @@
+//! - <https://listenbrainz.readthedocs.io/en/latest/users/api/core.html#get--1-user-(user_name)-listens>
+//! - <https://listenbrainz.readthedocs.io/en/latest/users/api/core.html#get--1-user-(user_name)-playing-now>
+//! - <https://listenbrainz.readthedocs.io/en/latest/users/api/core.html#get--1-user-(user_name)-listen-count>
+
+use serde::Deserialize;
+
+/// Parameters for retrieving the listens of a user.
+/// Listens are returned from newest to oldest.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ListensQuery {
+    /// Only return listens after this time (exclusive).
+    pub min_ts: Option<chrono::DateTime<chrono::Utc>>,
+    /// Only return listens before this time (exclusive).
+    pub max_ts: Option<chrono::DateTime<chrono::Utc>>,
+    /// The number of listens to return; clamped to [`MAX_ITEMS_PER_GET`](crate::constants::MAX_ITEMS_PER_GET).
+    pub count: Option<u16>,
+}
+impl ListensQuery {
+    pub(crate) fn to_query(self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::with_capacity(3);
+        if let Some(min_ts) = self.min_ts { query.push(("min_ts", min_ts.timestamp().to_string())) }
+        if let Some(max_ts) = self.max_ts { query.push(("max_ts", max_ts.timestamp().to_string())) }
+        if let Some(count) = self.count { query.push(("count", count.min(crate::constants::MAX_ITEMS_PER_GET).to_string())) }
+        query
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ListenTrackMetadata {
+    pub artist_name: String,
+    pub track_name: String,
+    pub release_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Listen {
+    /// Seconds since the Unix epoch.
+    pub listened_at: i64,
+    /// The MessyBrainz ID of the listen, assigned by ListenBrainz.
+    pub recording_msid: Option<String>,
+    pub track_metadata: ListenTrackMetadata,
+}
+impl Listen {
+    pub fn listened_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp(self.listened_at, 0)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListensPage {
+    pub count: usize,
+    /// Seconds since the Unix epoch.
+    pub latest_listen_ts: Option<i64>,
+    pub listens: Vec<Listen>,
+}
+
+/// What the user is listening to right now; there are no timestamps for these.
+#[derive(Debug, Deserialize)]
+pub(crate) struct PlayingNowPage {
+    pub listens: Vec<PlayingNowListen>,
+}
+#[derive(Debug, Deserialize)]
+pub(crate) struct PlayingNowListen {
+    pub track_metadata: ListenTrackMetadata,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ListenCount {
+    pub count: u64,
+}
+
+/// Most read endpoints wrap what they return in a `payload` object.
+#[derive(Debug, Deserialize)]
+pub(crate) struct RawPayloadResponse<T> {
+    pub payload: T,
+}
+
+/// An error from any of the endpoints that read a user's data.
+#[derive(Debug, thiserror::Error)]
+pub enum ListenRetrievalError {
+    #[error("network failure: {0}")]
+    NetworkFailure(#[from] reqwest::Error),
+    #[error("could not deserialize response: {0}")]
+    Deserialization(#[from] serde_json::Error),
+    #[error("user not found")]
+    UserNotFound,
+    #[error("ratelimited")]
+    Ratelimited,
+    #[error("error {0}: {1}")]
+    Other(reqwest::StatusCode, String)
+}
@@ -2,6 +2,9 @@ use maybe_owned_string::MaybeOwnedStringDeserializeToOwned;
 use serde::{Deserialize, Serialize};
 
 pub mod submit_listens;
+pub mod listens;
+pub mod stats;
+pub mod feedback;
 pub mod error;
 
 pub const API_ROOT: &str = "https://api.listenbrainz.org/1/";
@@ -86,9 +89,10 @@ struct InternalSharedListenPack<'a> {
 
 
 // TODO: add authorization type-state like lastfm
-// TODO: ratelimit middleware?
 pub struct Client<PS: AsRef<str>> {
     net: reqwest::Client,
+    /// Follows the `X-RateLimit-*` headers, so requests wait instead of being rejected.
+    limiter: shared::ratelimit::RateLimiter,
     program: musicbrainz::request_client::ProgramInfo<PS>,
     token: Option<UserToken>,
 }
@@ -116,6 +120,7 @@ impl<PS: AsRef<str>> Client<PS> {
     pub fn new(program: musicbrainz::request_client::ProgramInfo<PS>, token: Option<UserToken>) -> Self {
         Self {
             net: Self::mk_net(&program, token.as_ref()),
+            limiter: shared::ratelimit::RateLimiter::new(Default::default()),
             program,
             token
         }
@@ -127,9 +132,7 @@ impl<PS: AsRef<str>> Client<PS> {
             payload: payloads
         }.to_json();
 
-        // TODO: Make use of the defined payload limits in the constants file.
-        
-        let response = self.net.post(format!("{}/submit-listens", API_ROOT)).body(body).send().await?;
+        let response = self.limiter.send(self.net.post(format!("{}/submit-listens", API_ROOT)).body(body)).await?;
         Ok((response.status(), response.text().await?))
     }
 
@@ -152,6 +155,172 @@ impl<PS: AsRef<str>> Client<PS> {
         }
     }
 
+    /// Submits historical listens with the `import` listen type, split across as many requests as needed.
+    /// Returns the number of listens that were submitted.
+    pub async fn import_listens<'a>(&self, listens: impl IntoIterator<Item = submit_listens::ImportedListen<'a>>) -> Result<usize, submit_listens::ListenSubmissionError> {
+        let mut payloads = Vec::new();
+        for listen in listens {
+            if listen.listened_at < super::constants::LISTEN_MINIMUM_DATE {
+                return Err(error::ListenDateTooHistoric)?;
+            }
+            payloads.push(submit_listens::ListeningPayload {
+                listened_at: Some(listen.listened_at.timestamp() as u32),
+                metadata: submit_listens::ListeningPayloadTrackMetadata {
+                    basic: listen.track,
+                    additional_info: listen.extra.map(|info| info.into_raw())
+                }
+            });
+        }
+
+        use reqwest::StatusCode;
+        use submit_listens::ListenSubmissionError;
+        for chunk in payloads.chunks(super::constants::MAX_LISTENS_PER_REQUEST as usize) {
+            let (code, body) = self.submit_listen_payloads(submit_listens::ListenType::Import, chunk).await?;
+            match code {
+                StatusCode::OK => continue,
+                StatusCode::TOO_MANY_REQUESTS => return Err(ListenSubmissionError::Ratelimited),
+                StatusCode::UNAUTHORIZED => return Err(error::InvalidTokenError)?,
+                code => return Err(ListenSubmissionError::Other(code, body))
+            }
+        }
+
+        Ok(payloads.len())
+    }
+
+    /// Retrieves what's in the `payload` of a response; `None` if there's no content (e.g. statistics that haven't been computed yet).
+    async fn get_payload<T: serde::de::DeserializeOwned>(&self, path: &str, query: &[(&'static str, String)]) -> Result<Option<T>, listens::ListenRetrievalError> {
+        let request = self.net.get(format!("{API_ROOT}{path}")).query(query);
+        let response = self.limiter.send_idempotent(request).await?;
+
+        use reqwest::StatusCode;
+        use listens::ListenRetrievalError;
+        let code = response.status();
+        let body = response.text().await?;
+        match code {
+            StatusCode::OK => Ok(Some(serde_json::from_str::<listens::RawPayloadResponse<T>>(&body)?.payload)),
+            StatusCode::NO_CONTENT => Ok(None),
+            StatusCode::NOT_FOUND => Err(ListenRetrievalError::UserNotFound),
+            StatusCode::TOO_MANY_REQUESTS => Err(ListenRetrievalError::Ratelimited),
+            code => Err(ListenRetrievalError::Other(code, body))
+        }
+    }
+
+    /// Like [`Self::get_payload`], for endpoints that always have content.
+    async fn get_required_payload<T: serde::de::DeserializeOwned>(&self, path: &str, query: &[(&'static str, String)]) -> Result<T, listens::ListenRetrievalError> {
+        self.get_payload(path, query).await?
+            .ok_or_else(|| listens::ListenRetrievalError::Other(reqwest::StatusCode::NO_CONTENT, String::new()))
+    }
+
+    /// Retrieves a page of the listens of the given user.
+    pub async fn get_listens(&self, user: &str, query: listens::ListensQuery) -> Result<listens::ListensPage, listens::ListenRetrievalError> {
+        self.get_required_payload(&format!("user/{user}/listens"), &query.to_query()).await
+    }
+
+    /// Retrieves every listen of the given user between the two times (exclusive), newest first, a page at a time.
+    pub async fn get_all_listens(&self, user: &str, min_ts: Option<chrono::DateTime<chrono::Utc>>, mut max_ts: Option<chrono::DateTime<chrono::Utc>>) -> Result<Vec<listens::Listen>, listens::ListenRetrievalError> {
+        use super::constants::MAX_ITEMS_PER_GET;
+        let mut all = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        loop {
+            let page = self.get_listens(user, listens::ListensQuery { min_ts, max_ts, count: Some(MAX_ITEMS_PER_GET) }).await?;
+            let exhausted = page.listens.len() < MAX_ITEMS_PER_GET as usize;
+            let oldest = page.listens.last().and_then(listens::Listen::listened_at);
+            let added = extend_unseen(&mut all, &mut seen, page.listens);
+            match oldest {
+                // Pages are delimited by time, so the next one overlaps by a second; otherwise
+                // listens sharing a timestamp with the last one of this page would be skipped.
+                Some(oldest) if !exhausted && added > 0 => max_ts = Some(oldest + chrono::TimeDelta::seconds(1)),
+                _ => break,
+            }
+        }
+        Ok(all)
+    }
+
+    /// What the user is listening to right now, if anything.
+    pub async fn get_playing_now(&self, user: &str) -> Result<Option<listens::ListenTrackMetadata>, listens::ListenRetrievalError> {
+        let page: listens::PlayingNowPage = self.get_required_payload(&format!("user/{user}/playing-now"), &[]).await?;
+        Ok(page.listens.into_iter().next().map(|listen| listen.track_metadata))
+    }
+
+    /// The number of listens the user has submitted, in total.
+    pub async fn get_listen_count(&self, user: &str) -> Result<u64, listens::ListenRetrievalError> {
+        let count: listens::ListenCount = self.get_required_payload(&format!("user/{user}/listen-count"), &[]).await?;
+        Ok(count.count)
+    }
+
+    /// - <https://listenbrainz.readthedocs.io/en/latest/users/api/statistics.html#get--1-stats-user-(user_name)-artists>
+    pub async fn get_top_artists(&self, user: &str, query: stats::StatsQuery) -> Result<Option<stats::Ranking<stats::TopArtist>>, listens::ListenRetrievalError> {
+        let raw: Option<stats::RawRanking<_>> = self.get_payload(&format!("stats/user/{user}/artists"), &query.to_query()).await?;
+        Ok(raw.map(Into::into))
+    }
+
+    /// - <https://listenbrainz.readthedocs.io/en/latest/users/api/statistics.html#get--1-stats-user-(user_name)-releases>
+    pub async fn get_top_releases(&self, user: &str, query: stats::StatsQuery) -> Result<Option<stats::Ranking<stats::TopRelease>>, listens::ListenRetrievalError> {
+        let raw: Option<stats::RawRanking<_>> = self.get_payload(&format!("stats/user/{user}/releases"), &query.to_query()).await?;
+        Ok(raw.map(Into::into))
+    }
+
+    /// - <https://listenbrainz.readthedocs.io/en/latest/users/api/statistics.html#get--1-stats-user-(user_name)-recordings>
+    pub async fn get_top_recordings(&self, user: &str, query: stats::StatsQuery) -> Result<Option<stats::Ranking<stats::TopRecording>>, listens::ListenRetrievalError> {
+        let raw: Option<stats::RawRanking<_>> = self.get_payload(&format!("stats/user/{user}/recordings"), &query.to_query()).await?;
+        Ok(raw.map(Into::into))
+    }
+
+    /// - <https://listenbrainz.readthedocs.io/en/latest/users/api/statistics.html#get--1-stats-user-(user_name)-listening-activity>
+    pub async fn get_listening_activity(&self, user: &str, range: stats::StatsRange) -> Result<Option<Vec<stats::ActivityPeriod>>, listens::ListenRetrievalError> {
+        let raw: Option<stats::RawListeningActivity> = self.get_payload(&format!("stats/user/{user}/listening-activity"), &[("range", range.to_str().to_owned())]).await?;
+        Ok(raw.map(|raw| raw.listening_activity))
+    }
+
+    /// Loves, hates, or clears the feedback on a recording.
+    /// - <https://listenbrainz.readthedocs.io/en/latest/users/api/recordings.html#post--1-feedback-recording-feedback>
+    pub async fn submit_feedback(&self, recording: impl Into<feedback::FeedbackRecording<'_>>, score: feedback::FeedbackScore) -> Result<(), feedback::FeedbackSubmissionError> {
+        let body = serde_json::to_string(&feedback::RawFeedbackBody::new(recording.into(), score)).expect("feedback is always serializable");
+        // setting the same feedback twice has no further effect, so this can be retried freely
+        let response = self.limiter.send_idempotent(self.net.post(format!("{API_ROOT}feedback/recording-feedback")).body(body)).await?;
+
+        use reqwest::StatusCode;
+        use feedback::FeedbackSubmissionError;
+        let code = response.status();
+        match code {
+            StatusCode::OK => Ok(()),
+            StatusCode::TOO_MANY_REQUESTS => Err(FeedbackSubmissionError::Ratelimited),
+            StatusCode::UNAUTHORIZED => Err(error::InvalidTokenError)?,
+            code => Err(FeedbackSubmissionError::Other(code, response.text().await?))
+        }
+    }
+
+    /// Retrieves a page of the feedback the given user has given, newest first.
+    /// - <https://listenbrainz.readthedocs.io/en/latest/users/api/recordings.html#get--1-feedback-user-(user_name)-get-feedback>
+    pub async fn get_feedback(&self, user: &str, query: feedback::FeedbackQuery) -> Result<feedback::FeedbackPage, listens::ListenRetrievalError> {
+        let request = self.net.get(format!("{API_ROOT}feedback/user/{user}/get-feedback")).query(&query.to_query());
+        let response = self.limiter.send_idempotent(request).await?;
+
+        use reqwest::StatusCode;
+        use listens::ListenRetrievalError;
+        let code = response.status();
+        let body = response.text().await?;
+        match code {
+            StatusCode::OK => Ok(serde_json::from_str(&body)?),
+            StatusCode::NOT_FOUND => Err(ListenRetrievalError::UserNotFound),
+            StatusCode::TOO_MANY_REQUESTS => Err(ListenRetrievalError::Ratelimited),
+            code => Err(ListenRetrievalError::Other(code, body))
+        }
+    }
+
+    /// Retrieves all of the feedback the given user has given with the given score, or any score if `None`.
+    pub async fn get_all_feedback(&self, user: &str, score: Option<feedback::FeedbackScore>) -> Result<Vec<feedback::Feedback>, listens::ListenRetrievalError> {
+        use super::constants::MAX_ITEMS_PER_GET;
+        let mut all = Vec::new();
+        loop {
+            let page = self.get_feedback(user, feedback::FeedbackQuery { score, count: Some(MAX_ITEMS_PER_GET), offset: Some(all.len() as u32) }).await?;
+            let exhausted = page.feedback.len() < MAX_ITEMS_PER_GET as usize || all.len() as u64 + page.feedback.len() as u64 >= page.total_count;
+            all.extend(page.feedback);
+            if exhausted { break }
+        }
+        Ok(all)
+    }
+
     pub async fn submit_listen(&self, track: submit_listens::BasicTrackMetadata<'_>, time: chrono::DateTime<chrono::Utc>, extra: Option<submit_listens::additional_info::AdditionalInfo<'_>>) -> Result<(), submit_listens::ListenSubmissionError> {
         if time < super::constants::LISTEN_MINIMUM_DATE {
             return Err(error::ListenDateTooHistoric)?;
@@ -177,4 +346,34 @@ impl<PS: AsRef<str>> Client<PS> {
     }
 }
 
+/// Appends the listens that haven't been seen yet, returning how many there were.
+fn extend_unseen(all: &mut Vec<listens::Listen>, seen: &mut std::collections::HashSet<(i64, Option<String>)>, page: Vec<listens::Listen>) -> usize {
+    let before = all.len();
+    all.extend(page.into_iter().filter(|listen| seen.insert((listen.listened_at, listen.recording_msid.clone()))));
+    all.len() - before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    fn listen(listened_at: i64, msid: &str) -> listens::Listen {
+        serde_json::from_value(serde_json::json!({
+            "listened_at": listened_at,
+            "recording_msid": msid,
+            "track_metadata": { "artist_name": "Queen", "track_name": msid },
+        })).unwrap()
+    }
+
+    #[test]
+    fn overlapping_pages_keep_listens_sharing_a_timestamp() {
+        let mut all = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        assert_eq!(extend_unseen(&mut all, &mut seen, vec![listen(300, "a"), listen(200, "b")]), 2);
+        // The next page starts at the second of the last listen, which another listen shares.
+        assert_eq!(extend_unseen(&mut all, &mut seen, vec![listen(200, "b"), listen(200, "c"), listen(100, "d")]), 2);
+        assert_eq!(extend_unseen(&mut all, &mut seen, vec![listen(100, "d")]), 0);
+        let msids = all.iter().map(|listen| listen.recording_msid.as_deref().unwrap()).collect::<Vec<_>>();
+        assert_eq!(msids, ["a", "b", "c", "d"]);
+    }
+}
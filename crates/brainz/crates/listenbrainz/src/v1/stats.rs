@@ -0,0 +1,167 @@
+//! Statistics which ListenBrainz computes periodically; they can lag behind the latest listens by a day or so.
+//! - <https://listenbrainz.readthedocs.io/en/latest/users/api/statistics.html>
+
+use musicbrainz::{Artist, Id, Recording, Release};
+use serde::Deserialize;
+
+/// The period a statistic covers.
+/// - <https://listenbrainz.readthedocs.io/en/latest/users/api/statistics.html#constants>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsRange {
+    ThisWeek,
+    ThisMonth,
+    ThisYear,
+    /// The previous week.
+    Week,
+    /// The previous month.
+    Month,
+    /// The previous quarter.
+    Quarter,
+    /// The previous year.
+    Year,
+    HalfYearly,
+    #[default]
+    AllTime,
+}
+impl StatsRange {
+    pub const fn to_str(&self) -> &'static str {
+        match self {
+            Self::ThisWeek => "this_week",
+            Self::ThisMonth => "this_month",
+            Self::ThisYear => "this_year",
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Quarter => "quarter",
+            Self::Year => "year",
+            Self::HalfYearly => "half_yearly",
+            Self::AllTime => "all_time",
+        }
+    }
+}
+
+/// Which page of a ranking to retrieve.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatsQuery {
+    pub range: StatsRange,
+    /// The number of entries to return; clamped to [`MAX_ITEMS_PER_GET`](crate::constants::MAX_ITEMS_PER_GET).
+    pub count: Option<u16>,
+    /// The number of top entries to skip.
+    pub offset: Option<u32>,
+}
+impl StatsQuery {
+    pub(crate) fn to_query(self) -> Vec<(&'static str, String)> {
+        let mut query = vec![("range", self.range.to_str().to_owned())];
+        if let Some(count) = self.count { query.push(("count", count.min(crate::constants::MAX_ITEMS_PER_GET).to_string())) }
+        if let Some(offset) = self.offset { query.push(("offset", offset.to_string())) }
+        query
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TopArtist {
+    pub artist_name: String,
+    #[serde(default)]
+    pub artist_mbid: Option<Id<Artist>>,
+    pub listen_count: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TopRelease {
+    pub release_name: String,
+    pub artist_name: String,
+    #[serde(default)]
+    pub release_mbid: Option<Id<Release>>,
+    #[serde(default)]
+    pub artist_mbids: Vec<Id<Artist>>,
+    pub listen_count: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TopRecording {
+    pub track_name: String,
+    pub artist_name: String,
+    #[serde(default)]
+    pub release_name: Option<String>,
+    #[serde(default)]
+    pub recording_mbid: Option<Id<Recording>>,
+    #[serde(default)]
+    pub release_mbid: Option<Id<Release>>,
+    #[serde(default)]
+    pub artist_mbids: Vec<Id<Artist>>,
+    pub listen_count: u64,
+}
+
+/// A page of a ranking, along with the period it covers.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Ranking<T> {
+    /// Seconds since the Unix epoch.
+    pub from_ts: i64,
+    /// Seconds since the Unix epoch.
+    pub to_ts: i64,
+    /// Seconds since the Unix epoch.
+    pub last_updated: i64,
+    pub offset: u32,
+    /// How many entries there are in the whole ranking.
+    pub total: u64,
+    pub entries: Vec<T>,
+}
+
+/// The names of the fields that hold the entries and their total differ between endpoints.
+#[derive(Debug, Deserialize)]
+pub(crate) struct RawRanking<T> {
+    from_ts: i64,
+    to_ts: i64,
+    last_updated: i64,
+    #[serde(default)]
+    offset: u32,
+    #[serde(alias = "total_artist_count", alias = "total_release_count", alias = "total_recording_count")]
+    total: u64,
+    #[serde(alias = "artists", alias = "releases", alias = "recordings")]
+    entries: Vec<T>,
+}
+impl<T> From<RawRanking<T>> for Ranking<T> {
+    fn from(raw: RawRanking<T>) -> Self {
+        Self { from_ts: raw.from_ts, to_ts: raw.to_ts, last_updated: raw.last_updated, offset: raw.offset, total: raw.total, entries: raw.entries }
+    }
+}
+
+/// How many listens there were within a slice of the range, e.g. a day of a week.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ActivityPeriod {
+    /// Seconds since the Unix epoch.
+    pub from_ts: i64,
+    /// Seconds since the Unix epoch.
+    pub to_ts: i64,
+    /// A label for the slice, e.g. `Monday` or `January 2024`.
+    pub time_range: String,
+    pub listen_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RawListeningActivity {
+    pub listening_activity: Vec<ActivityPeriod>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_rankings() {
+        let body = r#"{"payload":{
+            "artists":[{"artist_mbid":"0383dadf-2a4e-4d10-a46a-e9e041da8eb3","artist_mbids":["0383dadf-2a4e-4d10-a46a-e9e041da8eb3"],"artist_name":"Queen","listen_count":42}],
+            "count":1,"from_ts":1700000000,"last_updated":1700600000,"offset":0,"range":"week","to_ts":1700604800,
+            "total_artist_count":120,"user_id":"someone"
+        }}"#;
+        let ranking: Ranking<TopArtist> = serde_json::from_str::<super::super::listens::RawPayloadResponse<RawRanking<TopArtist>>>(body).unwrap().payload.into();
+        assert_eq!(ranking.total, 120);
+        assert_eq!(ranking.entries[0].artist_name, "Queen");
+        assert!(ranking.entries[0].artist_mbid.is_some());
+    }
+
+    #[test]
+    fn clamps_count() {
+        let query = StatsQuery { range: StatsRange::Month, count: Some(5000), offset: None }.to_query();
+        assert_eq!(query, [("range", "month".to_owned()), ("count", "1000".to_owned())]);
+    }
+}
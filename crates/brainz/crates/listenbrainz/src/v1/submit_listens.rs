@@ -31,6 +31,13 @@ pub struct BasicTrackMetadata<'a> {
     #[serde(rename = "release_name")] pub release: Option<&'a str>
 }
 
+/// A listen from the past, to be submitted through [`Client::import_listens`](super::Client::import_listens).
+pub struct ImportedListen<'a> {
+    pub track: BasicTrackMetadata<'a>,
+    pub listened_at: chrono::DateTime<chrono::Utc>,
+    pub extra: Option<additional_info::AdditionalInfo<'a>>
+}
+
 #[derive(serde::Serialize, Debug)]
 pub(crate) struct ListeningPayloadTrackMetadata<'a> {
     #[serde(flatten)]
@@ -0,0 +1,269 @@
+//! Entities as returned by the web service; which fields are filled in depends on what was included in the request.
+//! - <https://musicbrainz.org/doc/MusicBrainz_API#Lookups>
+
+use serde::Deserialize;
+
+use crate::Id;
+
+/// A folksonomy tag, and how many users applied it.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    pub count: i32,
+}
+
+/// A tag from the curated list of genres.
+/// - <https://musicbrainz.org/genres>
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Genre {
+    pub name: String,
+    pub count: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ArtistCredit {
+    /// The name as credited, which may differ from the artist's own name.
+    pub name: String,
+    /// What comes after the name when the credits are joined, e.g. ` & `.
+    #[serde(default)]
+    pub joinphrase: String,
+    pub artist: CreditedArtist,
+}
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreditedArtist {
+    pub id: Id<crate::Artist>,
+    pub name: String,
+}
+
+/// Joins the credits as they would be displayed, e.g. `Queen & David Bowie`.
+pub fn artist_credit_name(credits: &[ArtistCredit]) -> String {
+    credits.iter().flat_map(|credit| [credit.name.as_str(), credit.joinphrase.as_str()]).collect()
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReleaseGroupSummary {
+    pub id: Id<crate::ReleaseGroup>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(rename = "primary-type", default)]
+    pub primary_type: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReleaseSummary {
+    pub id: Id<crate::Release>,
+    pub title: String,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(rename = "release-group")]
+    pub release_group: Option<ReleaseGroupSummary>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RecordingSummary {
+    pub id: Id<crate::Recording>,
+    pub title: String,
+    /// In milliseconds.
+    pub length: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct WorkSummary {
+    pub id: Id<crate::Work>,
+    pub title: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UrlTarget {
+    pub resource: String,
+}
+
+/// A relationship to another entity (or a URL); only the field named by [`Self::target_type`] is set.
+/// - <https://musicbrainz.org/relationships>
+#[derive(Deserialize, Debug, Clone)]
+pub struct Relation {
+    /// E.g. `performance` (from a recording to the work it's a performance of), or `composer`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(rename = "target-type")]
+    pub target_type: String,
+    /// `forward` or `backward`.
+    pub direction: String,
+    /// E.g. `live` or `instrument` names.
+    #[serde(default)]
+    pub attributes: Vec<String>,
+    #[serde(default)]
+    pub begin: Option<String>,
+    #[serde(default)]
+    pub end: Option<String>,
+
+    #[serde(default)]
+    pub artist: Option<CreditedArtist>,
+    #[serde(default)]
+    pub recording: Option<RecordingSummary>,
+    #[serde(default)]
+    pub release: Option<ReleaseSummary>,
+    #[serde(default)]
+    pub work: Option<WorkSummary>,
+    #[serde(default)]
+    pub url: Option<UrlTarget>,
+}
+
+/// - <https://musicbrainz.org/doc/Recording>
+#[derive(Deserialize, Debug, Clone)]
+pub struct Recording {
+    pub id: Id<crate::Recording>,
+    pub title: String,
+    /// In milliseconds.
+    pub length: Option<u32>,
+    #[serde(default)]
+    pub disambiguation: String,
+    #[serde(rename = "artist-credit", default)]
+    pub artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    pub isrcs: Vec<String>,
+    #[serde(default)]
+    pub releases: Vec<ReleaseSummary>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub genres: Vec<Genre>,
+    #[serde(default)]
+    pub relations: Vec<Relation>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MediumTrack {
+    pub id: Id<crate::Track>,
+    /// As printed on the release, e.g. `A1`.
+    pub number: String,
+    pub position: u32,
+    pub title: String,
+    pub length: Option<u32>,
+    #[serde(default)]
+    pub recording: Option<RecordingSummary>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Medium {
+    pub position: u32,
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(rename = "track-count")]
+    pub track_count: u32,
+    #[serde(default)]
+    pub tracks: Vec<MediumTrack>,
+}
+
+/// What the Cover Art Archive has for a release.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoverArtArchiveStatus {
+    pub artwork: bool,
+    pub front: bool,
+    pub back: bool,
+    pub count: u32,
+}
+
+/// - <https://musicbrainz.org/doc/Release>
+#[derive(Deserialize, Debug, Clone)]
+pub struct Release {
+    pub id: Id<crate::Release>,
+    pub title: String,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub barcode: Option<String>,
+    #[serde(default)]
+    pub disambiguation: String,
+    #[serde(rename = "artist-credit", default)]
+    pub artist_credit: Vec<ArtistCredit>,
+    #[serde(rename = "release-group", default)]
+    pub release_group: Option<ReleaseGroupSummary>,
+    #[serde(default)]
+    pub media: Vec<Medium>,
+    #[serde(rename = "cover-art-archive", default)]
+    pub cover_art_archive: Option<CoverArtArchiveStatus>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub genres: Vec<Genre>,
+    #[serde(default)]
+    pub relations: Vec<Relation>,
+}
+
+/// - <https://musicbrainz.org/doc/Release_Group>
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReleaseGroup {
+    pub id: Id<crate::ReleaseGroup>,
+    pub title: String,
+    /// E.g. `Album`, `Single` or `EP`.
+    #[serde(rename = "primary-type", default)]
+    pub primary_type: Option<String>,
+    /// E.g. `Compilation` or `Live`.
+    #[serde(rename = "secondary-types", default)]
+    pub secondary_types: Vec<String>,
+    #[serde(rename = "first-release-date", default)]
+    pub first_release_date: Option<String>,
+    #[serde(default)]
+    pub disambiguation: String,
+    #[serde(rename = "artist-credit", default)]
+    pub artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    pub releases: Vec<ReleaseSummary>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub genres: Vec<Genre>,
+    #[serde(default)]
+    pub relations: Vec<Relation>,
+}
+
+/// - <https://musicbrainz.org/doc/Artist>
+#[derive(Deserialize, Debug, Clone)]
+pub struct Artist {
+    pub id: Id<crate::Artist>,
+    pub name: String,
+    #[serde(rename = "sort-name")]
+    pub sort_name: String,
+    /// E.g. `Person` or `Group`.
+    #[serde(rename = "type", default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub disambiguation: String,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub genres: Vec<Genre>,
+    #[serde(default)]
+    pub relations: Vec<Relation>,
+}
+
+/// - <https://musicbrainz.org/doc/Work>
+#[derive(Deserialize, Debug, Clone)]
+pub struct Work {
+    pub id: Id<crate::Work>,
+    pub title: String,
+    /// E.g. `Song`, `Symphony` or `Concerto`.
+    #[serde(rename = "type", default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub disambiguation: String,
+    #[serde(default)]
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub iswcs: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub genres: Vec<Genre>,
+    #[serde(default)]
+    pub relations: Vec<Relation>,
+}
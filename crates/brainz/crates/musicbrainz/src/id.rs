@@ -35,6 +35,11 @@ impl<T: IdPossessor> serde::Serialize for Id<T> {
         serializer.serialize_str(self.as_str())
     }
 }
+impl<'de, T: IdPossessor> serde::Deserialize<'de> for Id<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        HyphenatedUuidString::deserialize(deserializer).map(|uuid| Self(uuid, core::marker::PhantomData))
+    }
+}
 
 /// - <https://musicbrainz.org/doc/MusicBrainz_Database/Schema>
 #[derive(Debug)]
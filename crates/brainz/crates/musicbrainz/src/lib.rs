@@ -4,26 +4,36 @@ use id::*;
 
 
 pub mod request_client;
+pub use request_client::Client;
+pub mod search;
+pub mod entity;
+pub mod lookup;
 
 #[derive(serde::Serialize)]
 pub struct Tag<'a>(maybe_owned_string::MaybeOwnedString<'a>);
 
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Artist;
 impl IdPossessor for Artist { const VARIANT: IdSubject = IdSubject::Artist; }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Release;
 impl IdPossessor for Release { const VARIANT: IdSubject = IdSubject::Release; }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ReleaseGroup;
 impl IdPossessor for ReleaseGroup { const VARIANT: IdSubject = IdSubject::ReleaseGroup; }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Recording;
 impl IdPossessor for Recording { const VARIANT: IdSubject = IdSubject::Recording; }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Track;
 impl IdPossessor for Track { const VARIANT: IdSubject = IdSubject::Track; }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Work;
 impl IdPossessor for Work { const VARIANT: IdSubject = IdSubject::Work; }
 
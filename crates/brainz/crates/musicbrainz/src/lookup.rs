@@ -0,0 +1,147 @@
+#![allow(private_bounds)]
+//! - <https://musicbrainz.org/doc/MusicBrainz_API#Lookups>
+
+use crate::{entity, id::IdPossessor, request_client::API_ROOT, Id};
+
+/// Extra data to include in a lookup; not every one applies to every kind of entity.
+/// - <https://musicbrainz.org/doc/MusicBrainz_API#inc.3D_arguments_which_affect_subqueries>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Include {
+    ArtistCredits,
+    Recordings,
+    Releases,
+    ReleaseGroups,
+    Media,
+    Isrcs,
+    Tags,
+    Genres,
+    ArtistRelationships,
+    RecordingRelationships,
+    ReleaseRelationships,
+    WorkRelationships,
+    UrlRelationships,
+    /// Relationships of the works of the recordings being included, e.g. the composer of a performed work.
+    WorkLevelRelationships,
+}
+impl Include {
+    pub const fn to_str(&self) -> &'static str {
+        match self {
+            Self::ArtistCredits => "artist-credits",
+            Self::Recordings => "recordings",
+            Self::Releases => "releases",
+            Self::ReleaseGroups => "release-groups",
+            Self::Media => "media",
+            Self::Isrcs => "isrcs",
+            Self::Tags => "tags",
+            Self::Genres => "genres",
+            Self::ArtistRelationships => "artist-rels",
+            Self::RecordingRelationships => "recording-rels",
+            Self::ReleaseRelationships => "release-rels",
+            Self::WorkRelationships => "work-rels",
+            Self::UrlRelationships => "url-rels",
+            Self::WorkLevelRelationships => "work-level-rels",
+        }
+    }
+}
+
+/// An entity which can be looked up by its ID.
+pub trait Entity: serde::de::DeserializeOwned {
+    type Subject: IdPossessor;
+    /// The path segment of the entity, e.g. `release-group`.
+    const PATH: &'static str;
+}
+impl Entity for entity::Recording { type Subject = crate::Recording; const PATH: &'static str = "recording"; }
+impl Entity for entity::Release { type Subject = crate::Release; const PATH: &'static str = "release"; }
+impl Entity for entity::ReleaseGroup { type Subject = crate::ReleaseGroup; const PATH: &'static str = "release-group"; }
+impl Entity for entity::Artist { type Subject = crate::Artist; const PATH: &'static str = "artist"; }
+impl Entity for entity::Work { type Subject = crate::Work; const PATH: &'static str = "work"; }
+
+#[derive(Debug, thiserror::Error)]
+pub enum LookupError {
+    #[error("network failure: {0}")]
+    NetworkFailure(#[from] reqwest::Error),
+    #[error("could not deserialize response: {0}")]
+    Deserialization(#[from] serde_json::Error),
+    #[error("not found")]
+    NotFound,
+    #[error("ratelimited")]
+    Ratelimited,
+    #[error("error {0}: {1}")]
+    Other(reqwest::StatusCode, String),
+}
+
+fn to_query(includes: &[Include]) -> Vec<(&'static str, String)> {
+    let mut query = vec![("fmt", "json".to_owned())];
+    if !includes.is_empty() {
+        query.push(("inc", includes.iter().map(Include::to_str).collect::<Vec<_>>().join(" ")));
+    }
+    query
+}
+
+impl<PS: AsRef<str>> crate::Client<PS> {
+    /// E.g. `client.lookup::<entity::Recording>(id, &[Include::Isrcs, Include::WorkRelationships])`.
+    pub async fn lookup<E: Entity>(&self, id: Id<E::Subject>, includes: &[Include]) -> Result<E, LookupError> {
+        let request = self.net.get(format!("{API_ROOT}{}/{id}", E::PATH)).query(&to_query(includes));
+        let response = self.limiter.send_idempotent(request).await?;
+
+        use reqwest::StatusCode;
+        let code = response.status();
+        let body = response.text().await?;
+        match code {
+            StatusCode::OK => Ok(serde_json::from_str(&body)?),
+            StatusCode::NOT_FOUND => Err(LookupError::NotFound),
+            // MusicBrainz signals going over the rate limit with 503s
+            StatusCode::SERVICE_UNAVAILABLE | StatusCode::TOO_MANY_REQUESTS => Err(LookupError::Ratelimited),
+            code => Err(LookupError::Other(code, body)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_includes() {
+        assert_eq!(to_query(&[]), vec![("fmt", "json".to_owned())]);
+        assert_eq!(to_query(&[Include::Isrcs, Include::WorkRelationships])[1], ("inc", "isrcs work-rels".to_owned()));
+    }
+
+    #[test]
+    fn deserializes_recordings() {
+        let body = r#"{
+            "id":"b1a9c0e9-d987-4042-ae91-78d6a3267d69","title":"Under Pressure","length":248000,"disambiguation":"",
+            "isrcs":["GBUM71029604"],
+            "tags":[{"name":"rock","count":3}],
+            "genres":[{"id":"0e3fc579-2d24-4f20-9dae-736e1ec78798","name":"rock","count":3,"disambiguation":""}],
+            "relations":[
+                {"type":"performance","type-id":"a3005666-a872-32c3-ad06-98af558e99b0","target-type":"work","direction":"forward",
+                 "attributes":[],"begin":null,"end":null,"ended":false,
+                 "work":{"id":"7f1a9b3e-6d0e-3b6f-9f4c-2a0e8f9d1c2b","title":"Under Pressure","type":"Song"}},
+                {"type":"free streaming","target-type":"url","direction":"forward",
+                 "url":{"id":"5b0f3d8a-0c4e-4e1f-8a8b-2c6d7e9f0a1b","resource":"https://open.spotify.com/track/2fuCquhmrzHpu5xcA1ci9x"}}
+            ]
+        }"#;
+        let recording = serde_json::from_str::<entity::Recording>(body).unwrap();
+        assert_eq!(recording.isrcs, ["GBUM71029604"]);
+        assert_eq!(recording.genres[0].name, "rock");
+        assert_eq!(recording.relations[0].work.as_ref().unwrap().title, "Under Pressure");
+        assert!(recording.relations[1].url.as_ref().unwrap().resource.starts_with("https://open.spotify.com/"));
+    }
+
+    #[test]
+    fn deserializes_releases() {
+        let body = r#"{
+            "id":"f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1","title":"Hot Space","status":"Official","date":"1982-05-21","country":"GB",
+            "cover-art-archive":{"artwork":true,"front":true,"back":false,"count":1,"darkened":false},
+            "release-group":{"id":"a3e2e4f5-9a8b-3c1d-8e7f-6a5b4c3d2e1f","title":"Hot Space","primary-type":"Album"},
+            "media":[{"position":1,"format":"Vinyl","track-count":1,"tracks":[
+                {"id":"c0d1e2f3-a4b5-4c6d-8e7f-9a0b1c2d3e4f","number":"B5","position":11,"title":"Under Pressure","length":248000}
+            ]}]
+        }"#;
+        let release = serde_json::from_str::<entity::Release>(body).unwrap();
+        assert!(release.cover_art_archive.unwrap().front);
+        assert_eq!(release.release_group.unwrap().primary_type.as_deref(), Some("Album"));
+        assert_eq!(release.media[0].tracks[0].number, "B5");
+    }
+}
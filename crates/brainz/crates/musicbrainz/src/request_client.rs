@@ -18,6 +18,7 @@ impl<S: AsRef<str>> ProgramInfo<S> {
         let mut out = String::with_capacity(capacity);
         out += self.name.as_ref();
         if let Some(version) = &self.version {
+            out += "/";
             out += version.as_ref();
         }
         out += " (";
@@ -28,3 +29,38 @@ impl<S: AsRef<str>> ProgramInfo<S> {
 }
 
 
+pub const API_ROOT: &str = "https://musicbrainz.org/ws/2/";
+
+/// A client for the MusicBrainz web service, which sends at most one request per second.
+/// - <https://musicbrainz.org/doc/MusicBrainz_API>
+pub struct Client<PS: AsRef<str>> {
+    pub(crate) net: reqwest::Client,
+    pub(crate) limiter: shared::ratelimit::RateLimiter,
+    program: ProgramInfo<PS>,
+}
+impl<PS: AsRef<str>> Client<PS> {
+    pub fn new(program: ProgramInfo<PS>) -> Self {
+        let net = reqwest::ClientBuilder::new()
+            .https_only(true)
+            .user_agent(program.to_user_agent())
+            .build().expect("could not build network client");
+        let limiter = shared::ratelimit::RateLimiter::with_interval(core::time::Duration::from_secs(1), Default::default());
+        Self { net, limiter, program }
+    }
+
+    pub fn get_program_info(&self) -> &ProgramInfo<PS> {
+        &self.program
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_user_agent() {
+        let program = ProgramInfo { name: "app", version: Some("1.2.3"), contact: "https://example.com" };
+        assert_eq!(program.to_user_agent(), "app/1.2.3 (https://example.com)");
+        assert_eq!(ProgramInfo { version: None, ..program }.to_user_agent(), "app (https://example.com)");
+    }
+}
@@ -0,0 +1,138 @@
+//! - <https://musicbrainz.org/doc/MusicBrainz_API/Search>
+
+use serde::Deserialize;
+
+use crate::{entity::{artist_credit_name, ArtistCredit, ReleaseSummary}, request_client::API_ROOT, Artist, Id, Recording};
+
+/// Escapes characters which have special meaning inside a quoted Lucene phrase.
+fn escape_phrase(phrase: &str) -> String {
+    phrase.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// What to search for; a recording is searched for by its ISRC alone if it's given, since it identifies recordings exactly.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecordingQuery<'a> {
+    pub title: &'a str,
+    pub artist: &'a str,
+    pub release: Option<&'a str>,
+    pub duration: Option<core::time::Duration>,
+    pub isrc: Option<&'a str>,
+}
+impl RecordingQuery<'_> {
+    /// How far off a duration may be while still boosting a result.
+    const DURATION_TOLERANCE_MILLIS: u128 = 5000;
+
+    /// - <https://musicbrainz.org/doc/MusicBrainz_API/Search#Recording>
+    pub fn to_lucene(&self) -> String {
+        if let Some(isrc) = self.isrc {
+            return format!("isrc:\"{}\"", escape_phrase(isrc));
+        }
+
+        // the release and duration aren't required, since they often differ between releases of the same recording
+        let mut query = format!("recording:\"{}\" AND artist:\"{}\"", escape_phrase(self.title), escape_phrase(self.artist));
+        if let Some(release) = self.release {
+            query += &format!(" release:\"{}\"", escape_phrase(release));
+        }
+        if let Some(duration) = self.duration {
+            let millis = duration.as_millis();
+            query += &format!(" dur:[{} TO {}]", millis.saturating_sub(Self::DURATION_TOLERANCE_MILLIS), millis + Self::DURATION_TOLERANCE_MILLIS);
+        }
+        query
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FoundRecording {
+    pub id: Id<Recording>,
+    /// How well the recording matches the query, from 0 to 100.
+    pub score: u8,
+    pub title: String,
+    /// In milliseconds.
+    pub length: Option<u32>,
+    #[serde(rename = "artist-credit", default)]
+    pub artist_credit: Vec<ArtistCredit>,
+    /// The releases the recording appears on.
+    #[serde(default)]
+    pub releases: Vec<ReleaseSummary>,
+}
+impl FoundRecording {
+    /// The artist credit as it would be displayed, e.g. `Queen & David Bowie`.
+    pub fn artist_credit_name(&self) -> String {
+        artist_credit_name(&self.artist_credit)
+    }
+
+    pub fn artist_ids(&self) -> Vec<Id<Artist>> {
+        self.artist_credit.iter().map(|credit| credit.artist.id).collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct RawRecordingSearchResponse {
+    recordings: Vec<FoundRecording>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SearchError {
+    #[error("network failure: {0}")]
+    NetworkFailure(#[from] reqwest::Error),
+    #[error("could not deserialize response: {0}")]
+    Deserialization(#[from] serde_json::Error),
+    #[error("ratelimited")]
+    Ratelimited,
+    #[error("error {0}: {1}")]
+    Other(reqwest::StatusCode, String),
+}
+
+impl<PS: AsRef<str>> crate::Client<PS> {
+    /// Returns at most `limit` (up to 100) recordings, best matches first.
+    pub async fn search_recordings(&self, query: &RecordingQuery<'_>, limit: u8) -> Result<Vec<FoundRecording>, SearchError> {
+        let request = self.net.get(format!("{API_ROOT}recording"))
+            .query(&[("query", query.to_lucene()), ("limit", limit.min(100).to_string()), ("fmt", "json".to_owned())]);
+        let response = self.limiter.send_idempotent(request).await?;
+
+        use reqwest::StatusCode;
+        let code = response.status();
+        let body = response.text().await?;
+        match code {
+            StatusCode::OK => Ok(serde_json::from_str::<RawRecordingSearchResponse>(&body)?.recordings),
+            // MusicBrainz signals going over the rate limit with 503s
+            StatusCode::SERVICE_UNAVAILABLE | StatusCode::TOO_MANY_REQUESTS => Err(SearchError::Ratelimited),
+            code => Err(SearchError::Other(code, body)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_queries() {
+        let query = RecordingQuery {
+            title: "Say \"Hello\"",
+            artist: "Artist",
+            release: Some("Album"),
+            duration: Some(core::time::Duration::from_secs(200)),
+            isrc: None,
+        };
+        assert_eq!(query.to_lucene(), r#"recording:"Say \"Hello\"" AND artist:"Artist" release:"Album" dur:[195000 TO 205000]"#);
+        assert_eq!(RecordingQuery { isrc: Some("USRC17607839"), ..query }.to_lucene(), r#"isrc:"USRC17607839""#);
+    }
+
+    #[test]
+    fn deserializes_results() {
+        let body = r#"{"created":"2025-01-01T00:00:00.000Z","count":1,"offset":0,"recordings":[{
+            "id":"b1a9c0e9-d987-4042-ae91-78d6a3267d69","score":100,"title":"Under Pressure","length":248000,
+            "artist-credit":[
+                {"name":"Queen","joinphrase":" & ","artist":{"id":"0383dadf-2a4e-4d10-a46a-e9e041da8eb3","name":"Queen"}},
+                {"name":"David Bowie","artist":{"id":"5441c29d-3602-4898-b1a1-b77fa23b8e50","name":"David Bowie"}}
+            ],
+            "releases":[{"id":"f6b1ee8a-6ef5-4c72-9b60-7d8ef1a9f5b1","title":"Hot Space","release-group":{"id":"a3e2e4f5-9a8b-3c1d-8e7f-6a5b4c3d2e1f"}}]
+        }]}"#;
+        let recordings = serde_json::from_str::<RawRecordingSearchResponse>(body).unwrap().recordings;
+        assert_eq!(recordings.len(), 1);
+        assert_eq!(recordings[0].artist_credit_name(), "Queen & David Bowie");
+        assert_eq!(recordings[0].artist_ids().len(), 2);
+        assert_eq!(recordings[0].releases[0].release_group.as_ref().unwrap().id.as_str(), "a3e2e4f5-9a8b-3c1d-8e7f-6a5b4c3d2e1f");
+    }
+}
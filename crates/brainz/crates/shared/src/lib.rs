@@ -1,3 +1,8 @@
+#[cfg(feature = "ratelimit")]
+pub mod ratelimit;
+#[cfg(any(test, feature = "mock-server"))]
+pub mod mock_server;
+
 /// A contextless hyphenated UUID string.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(transparent)]
@@ -0,0 +1,62 @@
+//! A local server speaking just enough HTTP to stand in for a web service in tests.
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+/// A canned response.
+#[derive(Debug, Clone)]
+pub struct Response {
+    /// Everything after `HTTP/1.1 `: the status, then any headers, e.g. `429 Too Many Requests\r\nretry-after: 1`.
+    /// `content-length` and `connection` are added.
+    pub head: String,
+    pub body: String,
+}
+impl From<&str> for Response {
+    fn from(head: &str) -> Self {
+        Self { head: head.to_owned(), body: String::new() }
+    }
+}
+
+#[derive(Debug)]
+pub struct MockServer {
+    /// e.g. `http://127.0.0.1:1234`, without a trailing slash.
+    pub root: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+impl MockServer {
+    /// Answers each request, on its own connection, with the next of `responses`, then stops accepting connections.
+    pub async fn serve(responses: impl IntoIterator<Item = Response>) -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let root = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let responses = responses.into_iter().collect::<Vec<_>>();
+        tokio::spawn({
+            let requests = requests.clone();
+            async move {
+                for Response { head, body } in responses {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 1024];
+                    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                        let read = stream.read(&mut buffer).await.unwrap();
+                        if read == 0 { break }
+                        request.extend_from_slice(&buffer[..read]);
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    requests.lock().unwrap().push(request.lines().next().unwrap_or_default().to_owned());
+
+                    let response = format!("HTTP/1.1 {head}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}", body.len());
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                    stream.shutdown().await.unwrap();
+                }
+            }
+        });
+        Self { root, requests }
+    }
+
+    /// The request lines received so far, e.g. `GET /path?query HTTP/1.1`.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,230 @@
+//! Spacing out requests to MetaBrainz services, and retrying the ones that were rejected for going too fast.
+//! - <https://musicbrainz.org/doc/MusicBrainz_API/Rate_Limiting>
+//! - <https://listenbrainz.readthedocs.io/en/latest/users/api/index.html#rate-limiting>
+
+use core::time::Duration;
+use reqwest::{header::HeaderMap, StatusCode};
+use tokio::{sync::Mutex, time::Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many times a request is retried before its last response (or error) is returned.
+    pub max_retries: u32,
+    /// Doubled after every attempt, unless the server says how long to wait.
+    pub base_delay: Duration,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_retries: 4, base_delay: Duration::from_secs(1) }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    /// When the next request may be sent, at the earliest.
+    next_allowed: Instant,
+}
+
+/// Shared by every request of a client; requests wait for their turn in the order they were made.
+#[derive(Debug)]
+pub struct RateLimiter {
+    state: Mutex<State>,
+    /// The least time between requests, for services with a fixed rate (like MusicBrainz).
+    interval: Option<Duration>,
+    retry: RetryPolicy,
+}
+impl RateLimiter {
+    /// Only waits when told to by the `X-RateLimit-*` headers of responses.
+    pub fn new(retry: RetryPolicy) -> Self {
+        Self { state: Mutex::new(State { next_allowed: Instant::now() }), interval: None, retry }
+    }
+
+    /// Also waits at least `interval` between requests.
+    pub fn with_interval(interval: Duration, retry: RetryPolicy) -> Self {
+        Self { interval: Some(interval), ..Self::new(retry) }
+    }
+
+    /// Waits until a request may be sent.
+    async fn acquire(&self) {
+        // the slot is reserved under the lock, so later requests queue up behind this one without it being held while waiting
+        let slot = {
+            let mut state = self.state.lock().await;
+            let slot = state.next_allowed.max(Instant::now());
+            if let Some(interval) = self.interval {
+                state.next_allowed = slot + interval;
+            }
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+
+    /// Updates when the next request may be sent from the headers of a response.
+    async fn observe(&self, headers: &HeaderMap) {
+        if let Some(wait) = wait_from_headers(headers) {
+            let mut state = self.state.lock().await;
+            state.next_allowed = state.next_allowed.max(Instant::now() + wait);
+        }
+    }
+
+    /// Sends a request that mustn't be repeated if it might've been processed; it's only retried when rate limited.
+    pub async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, reqwest::Error> {
+        self.send_with_retries(request, false).await
+    }
+
+    /// Sends a request that's safe to repeat, which is also retried on network failures and server errors.
+    pub async fn send_idempotent(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, reqwest::Error> {
+        self.send_with_retries(request, true).await
+    }
+
+    async fn send_with_retries(&self, request: reqwest::RequestBuilder, idempotent: bool) -> Result<reqwest::Response, reqwest::Error> {
+        let mut attempt = 0;
+        loop {
+            // streaming bodies can't be cloned, and so can't be retried
+            let Some(retry) = request.try_clone().filter(|_| attempt < self.retry.max_retries) else {
+                self.acquire().await;
+                let response = request.send().await?;
+                self.observe(response.headers()).await;
+                return Ok(response);
+            };
+
+            self.acquire().await;
+            let backoff = self.retry.base_delay * 2u32.saturating_pow(attempt);
+            attempt += 1;
+            match retry.send().await {
+                Ok(response) => {
+                    self.observe(response.headers()).await;
+                    let status = response.status();
+                    // a rate limited request wasn't processed, so it can always be sent again
+                    let rate_limited = status == StatusCode::TOO_MANY_REQUESTS ||
+                        (status == StatusCode::SERVICE_UNAVAILABLE && retry_after(response.headers()).is_some());
+                    if !(rate_limited || (idempotent && status.is_server_error())) {
+                        return Ok(response);
+                    }
+                    if wait_from_headers(response.headers()).is_none() {
+                        tokio::time::sleep(backoff).await;
+                    }
+                },
+                Err(error) if idempotent && (error.is_connect() || error.is_timeout()) => {
+                    tokio::time::sleep(backoff).await;
+                },
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+fn header_seconds(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// Only the delay form of `Retry-After` is supported, which is the one MetaBrainz services use.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    header_seconds(headers, "retry-after").map(Duration::from_secs)
+}
+
+/// How long to wait before the next request, if the headers say to wait at all.
+fn wait_from_headers(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(wait) = retry_after(headers) {
+        return Some(wait);
+    }
+    match header_seconds(headers, "x-ratelimit-remaining")? {
+        0 => header_seconds(headers, "x-ratelimit-reset-in").map(Duration::from_secs),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs.iter().map(|(name, value)| (reqwest::header::HeaderName::from_static(name), HeaderValue::from_static(value))).collect()
+    }
+
+    #[test]
+    fn reads_headers() {
+        assert_eq!(wait_from_headers(&headers(&[("x-ratelimit-remaining", "3"), ("x-ratelimit-reset-in", "5")])), None);
+        assert_eq!(wait_from_headers(&headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset-in", "5")])), Some(Duration::from_secs(5)));
+        assert_eq!(wait_from_headers(&headers(&[("retry-after", "2")])), Some(Duration::from_secs(2)));
+        assert_eq!(wait_from_headers(&headers(&[])), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn spaces_out_requests() {
+        let limiter = RateLimiter::with_interval(Duration::from_secs(1), RetryPolicy::default());
+        let start = Instant::now();
+        for _ in 0..3 { limiter.acquire().await }
+        assert_eq!(start.elapsed().as_secs(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waiting_does_not_hold_the_lock() {
+        let limiter = std::sync::Arc::new(RateLimiter::with_interval(Duration::from_secs(10), RetryPolicy::default()));
+        limiter.acquire().await;
+        let waiting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire().await }
+        });
+        tokio::task::yield_now().await;
+
+        let start = Instant::now();
+        limiter.observe(&headers(&[("retry-after", "1")])).await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        waiting.await.unwrap();
+        assert_eq!(start.elapsed().as_secs(), 10);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_reset() {
+        let limiter = RateLimiter::new(RetryPolicy::default());
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.observe(&headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset-in", "10")])).await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed().as_secs(), 10);
+    }
+
+    async fn serve(responses: &[&str]) -> (String, crate::mock_server::MockServer) {
+        let server = crate::mock_server::MockServer::serve(responses.iter().copied().map(Into::into)).await;
+        (format!("{}/", server.root), server)
+    }
+
+    fn limiter(max_retries: u32) -> RateLimiter {
+        RateLimiter::new(RetryPolicy { max_retries, base_delay: Duration::from_millis(1) })
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_of_idempotent_requests() {
+        let (url, server) = serve(&["503 Service Unavailable", "502 Bad Gateway", "200 OK"]).await;
+        let response = limiter(4).send_idempotent(reqwest::Client::new().get(url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_last_retry() {
+        let (url, server) = serve(&["500 Internal Server Error"; 3]).await;
+        let response = limiter(2).send_idempotent(reqwest::Client::new().get(url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_repeat_requests_that_may_have_been_processed() {
+        let (url, server) = serve(&["500 Internal Server Error", "200 OK"]).await;
+        let response = limiter(4).send(reqwest::Client::new().post(url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limited_requests_after_retry_after() {
+        let (url, server) = serve(&["429 Too Many Requests\r\nretry-after: 1", "200 OK"]).await;
+        let start = Instant::now();
+        let response = limiter(4).send(reqwest::Client::new().post(url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(server.requests().len(), 2);
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+}
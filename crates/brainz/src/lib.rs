@@ -5,5 +5,7 @@
 pub use listenbrainz::{self as listen};
 /// MusicBrainz
 pub use musicbrainz::{self as music};
+/// Cover Art Archive
+pub use cover_art_archive::{self as art};
 
 
@@ -0,0 +1,84 @@
+//! Every message on the socket is a frame: a little-endian `u32` opcode, a little-endian `u32` length, and then that many bytes of JSON.
+
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+
+/// Frames larger than this are assumed to be garbage rather than something worth allocating for.
+const MAX_FRAME_LENGTH: u32 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Opcode {
+    Handshake = 0,
+    Frame = 1,
+    Close = 2,
+    Ping = 3,
+    Pong = 4,
+}
+impl TryFrom<u32> for Opcode {
+    type Error = u32;
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Handshake,
+            1 => Self::Frame,
+            2 => Self::Close,
+            3 => Self::Ping,
+            4 => Self::Pong,
+            other => return Err(other),
+        })
+    }
+}
+
+pub async fn write(writer: &mut (impl AsyncWrite + Unpin), opcode: Opcode, payload: &[u8]) -> std::io::Result<()> {
+    let length = u32::try_from(payload.len()).map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "payload too large"))?;
+    let mut buffer = Vec::with_capacity(8 + payload.len());
+    buffer.extend_from_slice(&(opcode as u32).to_le_bytes());
+    buffer.extend_from_slice(&length.to_le_bytes());
+    buffer.extend_from_slice(payload);
+    writer.write_all(&buffer).await?;
+    writer.flush().await
+}
+
+pub async fn write_json(writer: &mut (impl AsyncWrite + Unpin), opcode: Opcode, payload: &impl serde::Serialize) -> std::io::Result<()> {
+    let payload = serde_json::to_vec(payload).map_err(std::io::Error::other)?;
+    write(writer, opcode, &payload).await
+}
+
+pub async fn read(reader: &mut (impl AsyncRead + Unpin)) -> std::io::Result<(Opcode, Vec<u8>)> {
+    let mut header = [0; 8];
+    reader.read_exact(&mut header).await?;
+    let opcode = u32::from_le_bytes(header[..4].try_into().unwrap());
+    let length = u32::from_le_bytes(header[4..].try_into().unwrap());
+
+    let opcode = Opcode::try_from(opcode)
+        .map_err(|opcode| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("unknown opcode {opcode}")))?;
+    if length > MAX_FRAME_LENGTH {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("frame of {length} bytes is too large")));
+    }
+
+    let mut payload = vec![0; length as usize];
+    reader.read_exact(&mut payload).await?;
+    Ok((opcode, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn roundtrip() {
+        let mut buffer = Vec::new();
+        write(&mut buffer, Opcode::Frame, br#"{"cmd":"SET_ACTIVITY"}"#).await.unwrap();
+        assert_eq!(&buffer[..8], &[1, 0, 0, 0, 22, 0, 0, 0]);
+
+        let (opcode, payload) = read(&mut buffer.as_slice()).await.unwrap();
+        assert_eq!(opcode, Opcode::Frame);
+        assert_eq!(payload, br#"{"cmd":"SET_ACTIVITY"}"#);
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_opcodes() {
+        let frame = [9, 0, 0, 0, 0, 0, 0, 0];
+        let error = read(&mut frame.as_slice()).await.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,285 @@
+//! Minimal asynchronous client for the local RPC socket of the Discord desktop client; just enough to set a rich presence.
+//!
+//! - <https://discord.com/developers/docs/topics/rpc>
+
+use std::{collections::VecDeque, path::{Path, PathBuf}, time::Duration};
+
+use serde::Deserialize;
+use tokio::{net::UnixStream, time::Instant};
+
+pub mod activity;
+mod frame;
+#[cfg(feature = "mock")]
+pub mod mock;
+
+pub use activity::Activity;
+pub use frame::Opcode;
+
+/// Discord only allows this many activity updates per [`ACTIVITY_UPDATE_WINDOW`].
+pub const ACTIVITY_UPDATES_PER_WINDOW: usize = 5;
+pub const ACTIVITY_UPDATE_WINDOW: Duration = Duration::from_secs(20);
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(3);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Discord listens on the first free socket of `discord-ipc-0` through `discord-ipc-9`.
+const SOCKET_COUNT: u8 = 10;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConnectError {
+    #[error("no discord socket could be connected to; discord probably isn't open")]
+    NotFound,
+    #[error("io failure: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("timed out waiting for the handshake to complete")]
+    TimedOut,
+    #[error("handshake rejected (code {code}): {message}")]
+    Rejected { code: u64, message: String },
+    #[error("couldn't deserialize message: {0}")]
+    Deserialization(#[from] serde_json::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RequestError {
+    #[error("io failure: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("the connection was closed")]
+    Disconnected,
+    #[error("timed out waiting for a response")]
+    TimedOut,
+    #[error("ratelimited; try again in {retry_after:?}")]
+    Ratelimited { retry_after: Duration },
+    #[error("discord returned an error (code {code}): {message}")]
+    Discord { code: u64, message: String },
+    #[error("couldn't deserialize message: {0}")]
+    Deserialization(#[from] serde_json::Error),
+}
+impl RequestError {
+    /// Whether the connection can no longer be used, and a new one has to be made.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Self::Io(..) | Self::Disconnected | Self::TimedOut)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorData {
+    code: u64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    #[serde(default)]
+    cmd: Option<String>,
+    #[serde(default)]
+    evt: Option<String>,
+    #[serde(default)]
+    nonce: Option<String>,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    pub id: String,
+    pub username: String,
+}
+
+/// The places the socket might be, in order of preference.
+pub fn socket_candidates() -> Vec<PathBuf> {
+    let mut roots = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"].iter()
+        .filter_map(std::env::var_os)
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    roots.push(PathBuf::from("/tmp"));
+
+    let mut candidates = Vec::with_capacity(roots.len() * SOCKET_COUNT as usize * 3);
+    for root in roots {
+        // Sandboxed installations put the socket in a subdirectory.
+        for directory in [root.clone(), root.join("app/com.discordapp.Discord"), root.join("snap.discord")] {
+            for index in 0..SOCKET_COUNT {
+                candidates.push(directory.join(format!("discord-ipc-{index}")));
+            }
+        }
+    }
+    candidates
+}
+
+pub struct Client {
+    stream: UnixStream,
+    nonce: u64,
+    /// When the most recent activity updates were sent, oldest first.
+    recent_activity_updates: VecDeque<Instant>,
+    user: Option<User>,
+}
+impl core::fmt::Debug for Client {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Client").field("user", &self.user).finish()
+    }
+}
+impl Client {
+    /// Connects to the first socket of a running Discord client that completes the handshake.
+    pub async fn connect(client_id: u64) -> Result<Self, ConnectError> {
+        let mut last_error = ConnectError::NotFound;
+        for path in socket_candidates() {
+            if !path.exists() { continue }
+            match Self::connect_to(&path, client_id).await {
+                Ok(client) => return Ok(client),
+                Err(error) => {
+                    tracing::debug!(?error, ?path, "couldn't connect to discord socket");
+                    last_error = error;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    pub async fn connect_to(path: impl AsRef<Path>, client_id: u64) -> Result<Self, ConnectError> {
+        let stream = UnixStream::connect(path).await?;
+        let mut client = Self {
+            stream,
+            nonce: 0,
+            recent_activity_updates: VecDeque::with_capacity(ACTIVITY_UPDATES_PER_WINDOW),
+            user: None,
+        };
+
+        tokio::time::timeout(HANDSHAKE_TIMEOUT, client.handshake(client_id)).await
+            .map_err(|_| ConnectError::TimedOut)??;
+
+        Ok(client)
+    }
+
+    async fn handshake(&mut self, client_id: u64) -> Result<(), ConnectError> {
+        frame::write_json(&mut self.stream, Opcode::Handshake, &serde_json::json!({
+            "v": 1,
+            "client_id": client_id.to_string(),
+        })).await?;
+
+        loop {
+            let (opcode, payload) = frame::read(&mut self.stream).await?;
+            match opcode {
+                Opcode::Frame => {
+                    let message: Message = serde_json::from_slice(&payload)?;
+                    match message.evt.as_deref() {
+                        Some("READY") => {
+                            self.user = message.data.get("user").cloned().and_then(|user| serde_json::from_value(user).ok());
+                            return Ok(());
+                        },
+                        Some("ERROR") => {
+                            let error: ErrorData = serde_json::from_value(message.data)?;
+                            return Err(ConnectError::Rejected { code: error.code, message: error.message });
+                        },
+                        _ => continue,
+                    }
+                },
+                Opcode::Close => {
+                    let error: ErrorData = serde_json::from_slice(&payload)?;
+                    return Err(ConnectError::Rejected { code: error.code, message: error.message });
+                },
+                Opcode::Ping => frame::write(&mut self.stream, Opcode::Pong, &payload).await?,
+                Opcode::Handshake | Opcode::Pong => continue,
+            }
+        }
+    }
+
+    /// The user logged into the Discord client, if it was provided during the handshake.
+    pub fn user(&self) -> Option<&User> {
+        self.user.as_ref()
+    }
+
+    /// How long until another activity update can be sent, or `None` if one can be sent right away.
+    pub fn activity_update_available_in(&self) -> Option<Duration> {
+        self.activity_updates_available_in(1)
+    }
+
+    /// How long until `count` activity updates could be sent back to back, or `None` if they can be sent right away.
+    ///
+    /// `count` is capped at [`ACTIVITY_UPDATES_PER_WINDOW`].
+    pub fn activity_updates_available_in(&self, count: usize) -> Option<Duration> {
+        let now = Instant::now();
+        let active = self.recent_activity_updates.iter()
+            .filter(|sent| now.duration_since(**sent) < ACTIVITY_UPDATE_WINDOW)
+            .collect::<Vec<_>>();
+        let must_expire = (active.len() + count.min(ACTIVITY_UPDATES_PER_WINDOW)).checked_sub(ACTIVITY_UPDATES_PER_WINDOW)?;
+        let last_to_expire = *active.get(must_expire.checked_sub(1)?)?;
+        (*last_to_expire + ACTIVITY_UPDATE_WINDOW).checked_duration_since(now)
+    }
+
+    fn prune_activity_updates(&mut self) {
+        let now = Instant::now();
+        while self.recent_activity_updates.front().is_some_and(|sent| now.duration_since(*sent) >= ACTIVITY_UPDATE_WINDOW) {
+            self.recent_activity_updates.pop_front();
+        }
+    }
+
+    /// Sets (or, with `None`, clears) the activity.
+    ///
+    /// Instead of letting Discord silently queue updates beyond its ratelimit, this fails with
+    /// [`RequestError::Ratelimited`] without sending anything.
+    pub async fn set_activity(&mut self, activity: Option<&Activity>) -> Result<(), RequestError> {
+        self.prune_activity_updates();
+        if let Some(retry_after) = self.activity_update_available_in() {
+            return Err(RequestError::Ratelimited { retry_after });
+        }
+
+        self.recent_activity_updates.push_back(Instant::now());
+        self.request("SET_ACTIVITY", serde_json::json!({
+            "pid": std::process::id(),
+            "activity": activity,
+        })).await?;
+        Ok(())
+    }
+
+    pub async fn clear_activity(&mut self) -> Result<(), RequestError> {
+        self.set_activity(None).await
+    }
+
+    async fn request(&mut self, command: &str, args: serde_json::Value) -> Result<serde_json::Value, RequestError> {
+        self.nonce += 1;
+        let nonce = self.nonce.to_string();
+
+        frame::write_json(&mut self.stream, Opcode::Frame, &serde_json::json!({
+            "cmd": command,
+            "args": args,
+            "nonce": nonce,
+        })).await?;
+
+        tokio::time::timeout(REQUEST_TIMEOUT, self.await_response(&nonce)).await
+            .map_err(|_| RequestError::TimedOut)?
+    }
+
+    async fn await_response(&mut self, nonce: &str) -> Result<serde_json::Value, RequestError> {
+        loop {
+            let (opcode, payload) = match frame::read(&mut self.stream).await {
+                Ok(frame) => frame,
+                Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => return Err(RequestError::Disconnected),
+                Err(error) => return Err(error.into()),
+            };
+
+            match opcode {
+                Opcode::Frame => {
+                    let message: Message = serde_json::from_slice(&payload)?;
+                    if message.nonce.as_deref() != Some(nonce) {
+                        tracing::trace!(cmd = ?message.cmd, evt = ?message.evt, "ignoring unrelated message");
+                        continue;
+                    }
+                    if message.evt.as_deref() == Some("ERROR") {
+                        let error: ErrorData = serde_json::from_value(message.data)?;
+                        return Err(RequestError::Discord { code: error.code, message: error.message });
+                    }
+                    return Ok(message.data);
+                },
+                Opcode::Close => return Err(RequestError::Disconnected),
+                Opcode::Ping => frame::write(&mut self.stream, Opcode::Pong, &payload).await?,
+                Opcode::Handshake | Opcode::Pong => continue,
+            }
+        }
+    }
+
+    /// Tells Discord that the connection is being closed, which also clears the activity.
+    pub async fn close(mut self) -> Result<(), std::io::Error> {
+        frame::write_json(&mut self.stream, Opcode::Close, &serde_json::json!({})).await?;
+        use tokio::io::AsyncWriteExt as _;
+        self.stream.shutdown().await
+    }
+}
@@ -0,0 +1,154 @@
+//! A stand-in for the socket of the Discord client, so that code using [`Client`](crate::Client) can be tested without Discord.
+
+use std::{path::{Path, PathBuf}, sync::Arc};
+
+use tokio::{net::{UnixListener, UnixStream}, sync::{mpsc, Mutex}, task::JoinHandle};
+
+use crate::{frame, Opcode};
+
+/// Something the mock server received.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Received {
+    Handshake { client_id: String },
+    Command { cmd: String, args: serde_json::Value },
+    Close,
+}
+
+#[derive(Debug, Default)]
+struct Behavior {
+    /// Respond to commands with this error instead of succeeding.
+    error: Option<(u64, String)>,
+    /// Reject handshakes with this error.
+    reject_handshake: Option<(u64, String)>,
+    /// Accept commands without ever responding.
+    unresponsive: bool,
+}
+
+pub struct MockServer {
+    path: PathBuf,
+    received: mpsc::UnboundedReceiver<Received>,
+    behavior: Arc<Mutex<Behavior>>,
+    connection: Arc<Mutex<Option<JoinHandle<()>>>>,
+    listener: JoinHandle<()>,
+}
+impl MockServer {
+    /// Listens at the given path, which must not already exist.
+    pub fn bind(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        let listener = UnixListener::bind(&path)?;
+        let (tx, received) = mpsc::unbounded_channel();
+        let behavior = Arc::new(Mutex::new(Behavior::default()));
+        let connection = Arc::new(Mutex::new(None::<JoinHandle<()>>));
+
+        let listener = {
+            let behavior = behavior.clone();
+            let connection = connection.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let handle = tokio::spawn(serve(stream, tx.clone(), behavior.clone()));
+                    if let Some(previous) = connection.lock().await.replace(handle) {
+                        previous.abort();
+                    }
+                }
+            })
+        };
+
+        Ok(Self { path, received, behavior, connection, listener })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The next thing received, or `None` if nothing arrives within a second.
+    pub async fn next(&mut self) -> Option<Received> {
+        tokio::time::timeout(std::time::Duration::from_secs(1), self.received.recv()).await.ok().flatten()
+    }
+
+    pub async fn fail_commands_with(&self, code: u64, message: impl Into<String>) {
+        self.behavior.lock().await.error = Some((code, message.into()));
+    }
+
+    pub async fn reject_handshakes_with(&self, code: u64, message: impl Into<String>) {
+        self.behavior.lock().await.reject_handshake = Some((code, message.into()));
+    }
+
+    pub async fn stop_responding(&self) {
+        self.behavior.lock().await.unresponsive = true;
+    }
+
+    /// Drops the current connection, as if Discord was closed.
+    pub async fn disconnect(&self) {
+        if let Some(connection) = self.connection.lock().await.take() {
+            connection.abort();
+            let _ = connection.await;
+        }
+    }
+}
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.listener.abort();
+        if let Ok(mut connection) = self.connection.try_lock() {
+            if let Some(connection) = connection.take() {
+                connection.abort();
+            }
+        }
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn serve(mut stream: UnixStream, tx: mpsc::UnboundedSender<Received>, behavior: Arc<Mutex<Behavior>>) {
+    let Ok((Opcode::Handshake, payload)) = frame::read(&mut stream).await else { return };
+    let handshake: serde_json::Value = serde_json::from_slice(&payload).unwrap_or_default();
+    let _ = tx.send(Received::Handshake {
+        client_id: handshake["client_id"].as_str().unwrap_or_default().to_owned(),
+    });
+
+    if let Some((code, message)) = behavior.lock().await.reject_handshake.clone() {
+        let _ = frame::write_json(&mut stream, Opcode::Close, &serde_json::json!({ "code": code, "message": message })).await;
+        return;
+    }
+
+    let ready = serde_json::json!({
+        "cmd": "DISPATCH",
+        "evt": "READY",
+        "data": { "v": 1, "user": { "id": "0", "username": "mock" } },
+        "nonce": null,
+    });
+    if frame::write_json(&mut stream, Opcode::Frame, &ready).await.is_err() { return }
+
+    while let Ok((opcode, payload)) = frame::read(&mut stream).await {
+        match opcode {
+            Opcode::Frame => {
+                let message: serde_json::Value = serde_json::from_slice(&payload).unwrap_or_default();
+                let cmd = message["cmd"].as_str().unwrap_or_default().to_owned();
+                let _ = tx.send(Received::Command { cmd: cmd.clone(), args: message["args"].clone() });
+
+                let behavior = behavior.lock().await;
+                if behavior.unresponsive { continue }
+                let response = match &behavior.error {
+                    Some((code, error)) => serde_json::json!({
+                        "cmd": cmd,
+                        "evt": "ERROR",
+                        "data": { "code": code, "message": error },
+                        "nonce": message["nonce"],
+                    }),
+                    None => serde_json::json!({
+                        "cmd": cmd,
+                        "evt": null,
+                        "data": message["args"]["activity"],
+                        "nonce": message["nonce"],
+                    }),
+                };
+                drop(behavior);
+                if frame::write_json(&mut stream, Opcode::Frame, &response).await.is_err() { return }
+            },
+            Opcode::Ping => { let _ = frame::write(&mut stream, Opcode::Pong, &payload).await; },
+            Opcode::Close => {
+                let _ = tx.send(Received::Close);
+                return;
+            },
+            Opcode::Handshake | Opcode::Pong => {},
+        }
+    }
+}
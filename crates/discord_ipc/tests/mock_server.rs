@@ -0,0 +1,98 @@
+//! Exercises the client against the mock server.
+
+use discord_ipc::{activity::{Assets, Button}, mock::{MockServer, Received}, Activity, Client, ConnectError, RequestError, ACTIVITY_UPDATES_PER_WINDOW};
+
+const CLIENT_ID: u64 = 1286481105410588672;
+
+fn socket_path(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("discord-ipc-test-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+async fn connected(name: &str) -> (MockServer, Client) {
+    let mut server = MockServer::bind(socket_path(name)).unwrap();
+    let client = Client::connect_to(server.path(), CLIENT_ID).await.unwrap();
+    assert_eq!(server.next().await, Some(Received::Handshake { client_id: CLIENT_ID.to_string() }));
+    (server, client)
+}
+
+fn activity() -> Activity {
+    Activity {
+        details: Some("Track".to_owned()),
+        state: Some("Artist".to_owned()),
+        assets: Some(Assets {
+            large_image: Some("https://example.com/cover.jpg".to_owned()),
+            ..Default::default()
+        }),
+        buttons: vec![Button { label: "Listen".to_owned(), url: "https://example.com".to_owned() }],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn handshake_and_set_activity() {
+    let (mut server, mut client) = connected("set").await;
+    assert_eq!(client.user().map(|user| user.username.as_str()), Some("mock"));
+
+    client.set_activity(Some(&activity())).await.unwrap();
+    let Some(Received::Command { cmd, args }) = server.next().await else { panic!("expected a command") };
+    assert_eq!(cmd, "SET_ACTIVITY");
+    assert_eq!(args["pid"], std::process::id());
+    assert_eq!(args["activity"]["details"], "Track");
+    assert_eq!(args["activity"]["assets"]["large_image"], "https://example.com/cover.jpg");
+    assert!(args["activity"]["assets"].get("small_image").is_none());
+    assert_eq!(args["activity"]["buttons"][0]["label"], "Listen");
+
+    client.clear_activity().await.unwrap();
+    let Some(Received::Command { args, .. }) = server.next().await else { panic!("expected a command") };
+    assert!(args["activity"].is_null());
+
+    client.close().await.unwrap();
+    assert_eq!(server.next().await, Some(Received::Close));
+}
+
+#[tokio::test]
+async fn errors_are_surfaced() {
+    let (server, mut client) = connected("error").await;
+    server.fail_commands_with(4000, "child \"activity\" fails").await;
+
+    let error = client.set_activity(Some(&activity())).await.unwrap_err();
+    assert!(matches!(&error, RequestError::Discord { code: 4000, .. }), "{error:?}");
+    assert!(!error.is_fatal());
+}
+
+#[tokio::test]
+async fn rejected_handshake() {
+    let server = MockServer::bind(socket_path("rejected")).unwrap();
+    server.reject_handshakes_with(4000, "Invalid Client ID").await;
+
+    let error = Client::connect_to(server.path(), CLIENT_ID).await.unwrap_err();
+    assert!(matches!(&error, ConnectError::Rejected { code: 4000, .. }), "{error:?}");
+}
+
+#[tokio::test]
+async fn ratelimit_is_enforced_locally() {
+    let (mut server, mut client) = connected("ratelimit").await;
+
+    for sent in 0..ACTIVITY_UPDATES_PER_WINDOW {
+        assert!(client.activity_updates_available_in(ACTIVITY_UPDATES_PER_WINDOW - sent).is_none());
+        assert!(client.activity_updates_available_in(ACTIVITY_UPDATES_PER_WINDOW - sent + 1).is_some() || sent == 0);
+        client.set_activity(Some(&activity())).await.unwrap();
+        assert!(matches!(server.next().await, Some(Received::Command { .. })));
+    }
+
+    assert!(client.activity_update_available_in().is_some());
+    let error = client.set_activity(Some(&activity())).await.unwrap_err();
+    assert!(matches!(error, RequestError::Ratelimited { .. }), "{error:?}");
+    assert_eq!(server.next().await, None, "nothing should be sent while ratelimited");
+}
+
+#[tokio::test]
+async fn disconnect_is_detected() {
+    let (server, mut client) = connected("disconnect").await;
+    server.disconnect().await;
+
+    let error = client.set_activity(Some(&activity())).await.unwrap_err();
+    assert!(error.is_fatal(), "{error:?}");
+}
@@ -0,0 +1,75 @@
+use maybe_owned_string::MaybeOwnedString;
+use serde::Deserialize;
+
+use crate::{auth, image::Image, parameters, ApiRequest, Client, Error, InfoQuery};
+
+#[derive(Debug, Clone)]
+pub struct AlbumInfo {
+    pub name: String,
+    pub artist: String,
+    pub mbid: Option<String>,
+    pub url: String,
+    pub images: Vec<Image>,
+    pub listeners: u64,
+    pub play_count: u64,
+    /// Only present if a username was given.
+    pub user_play_count: Option<u64>,
+    pub tags: Vec<String>,
+}
+
+impl<A: auth::state::AuthorizationStatus> Client<A> {
+    /// - <https://www.last.fm/api/show/album.getInfo>
+    pub async fn get_album_info(&self, artist: &str, album: &str, query: InfoQuery<'_>) -> Result<AlbumInfo, Error> {
+        let mut parameters = parameters::Map::from_collection(Default::default());
+        parameters.add("artist".to_owned(), MaybeOwnedString::Borrowed(artist));
+        parameters.add("album".to_owned(), MaybeOwnedString::Borrowed(album));
+        query.add_to(&mut parameters);
+
+        let response = self.dispatch_unauthorized(ApiRequest {
+            endpoint: "album.getInfo",
+            method: reqwest::Method::GET,
+            parameters,
+        }).await?;
+
+        let raw: raw::Response = crate::read_response(response).await?;
+        let album = raw.album;
+        Ok(AlbumInfo {
+            name: album.name,
+            artist: album.artist,
+            mbid: album.mbid.and_then(crate::non_empty),
+            url: album.url,
+            images: album.image,
+            listeners: album.listeners,
+            play_count: album.playcount,
+            user_play_count: album.userplaycount,
+            tags: album.tags,
+        })
+    }
+}
+
+mod raw {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    pub struct Album {
+        pub name: String,
+        pub artist: String,
+        pub mbid: Option<String>,
+        pub url: String,
+        #[serde(default)]
+        pub image: Vec<Image>,
+        #[serde(deserialize_with = "crate::lenient_number")]
+        pub listeners: u64,
+        #[serde(deserialize_with = "crate::lenient_number")]
+        pub playcount: u64,
+        #[serde(default, deserialize_with = "crate::lenient_optional_number")]
+        pub userplaycount: Option<u64>,
+        #[serde(default, deserialize_with = "crate::lenient_tags")]
+        pub tags: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Response {
+        pub album: Album,
+    }
+}
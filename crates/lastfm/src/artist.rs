@@ -0,0 +1,87 @@
+use maybe_owned_string::MaybeOwnedString;
+use serde::Deserialize;
+
+use crate::{auth, image::Image, parameters, ApiRequest, Client, Error, InfoQuery};
+
+#[derive(Debug, Clone)]
+pub struct ArtistInfo {
+    pub name: String,
+    pub mbid: Option<String>,
+    pub url: String,
+    /// Nowadays always a placeholder; see [`crate::image::largest`].
+    pub images: Vec<Image>,
+    pub listeners: u64,
+    pub play_count: u64,
+    /// Only present if a username was given.
+    pub user_play_count: Option<u64>,
+    pub tags: Vec<String>,
+    /// A short biography, in HTML.
+    pub summary: Option<String>,
+}
+
+impl<A: auth::state::AuthorizationStatus> Client<A> {
+    /// - <https://www.last.fm/api/show/artist.getInfo>
+    pub async fn get_artist_info(&self, artist: &str, query: InfoQuery<'_>) -> Result<ArtistInfo, Error> {
+        let mut parameters = parameters::Map::from_collection(Default::default());
+        parameters.add("artist".to_owned(), MaybeOwnedString::Borrowed(artist));
+        query.add_to(&mut parameters);
+
+        let response = self.dispatch_unauthorized(ApiRequest {
+            endpoint: "artist.getInfo",
+            method: reqwest::Method::GET,
+            parameters,
+        }).await?;
+
+        let raw: raw::Response = crate::read_response(response).await?;
+        let artist = raw.artist;
+        Ok(ArtistInfo {
+            name: artist.name,
+            mbid: artist.mbid.and_then(crate::non_empty),
+            url: artist.url,
+            images: artist.image,
+            listeners: artist.stats.listeners,
+            play_count: artist.stats.playcount,
+            user_play_count: artist.stats.userplaycount,
+            tags: artist.tags,
+            summary: artist.bio.map(|bio| bio.summary).and_then(crate::non_empty),
+        })
+    }
+}
+
+mod raw {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    pub struct Stats {
+        #[serde(deserialize_with = "crate::lenient_number")]
+        pub listeners: u64,
+        #[serde(deserialize_with = "crate::lenient_number")]
+        pub playcount: u64,
+        #[serde(default, deserialize_with = "crate::lenient_optional_number")]
+        pub userplaycount: Option<u64>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Bio {
+        #[serde(default)]
+        pub summary: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Artist {
+        pub name: String,
+        pub mbid: Option<String>,
+        pub url: String,
+        #[serde(default)]
+        pub image: Vec<Image>,
+        pub stats: Stats,
+        #[serde(default, deserialize_with = "crate::lenient_tags")]
+        pub tags: Vec<String>,
+        pub bio: Option<Bio>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Response {
+        pub artist: Artist,
+    }
+}
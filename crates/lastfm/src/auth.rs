@@ -3,7 +3,7 @@ use std::str::FromStr;
 use serde::{Serialize, Deserialize};
 
 
-use crate::GeneralError;
+use crate::{Endpoints, GeneralError};
 
 pub mod state {
     pub trait AuthorizationStatus {}
@@ -20,6 +20,9 @@ pub struct ClientIdentity {
     key: internal::ThirtyTwoCharactersLowercaseHexAsciiString,
     secret: internal::ThirtyTwoCharactersLowercaseHexAsciiString,
     pub user_agent: String,
+    /// The service this identity belongs to; Last.fm unless otherwise specified.
+    #[serde(default, skip_serializing_if = "Endpoints::is_lastfm")]
+    pub endpoints: Endpoints,
 }
 impl ClientIdentity {
     pub fn new(user_agent: String, key: &str, secret: &str) -> Result<Self, internal::InvalidThirtyTwoCharactersLowercaseHexAsciiStringError> {
@@ -27,11 +30,17 @@ impl ClientIdentity {
             Err(err) => Err(err),
             Ok(key) => match internal::ThirtyTwoCharactersLowercaseHexAsciiString::new(secret) {
                 Err(err) => Err(err),
-                Ok(secret) => Ok(Self { user_agent, key, secret })
+                Ok(secret) => Ok(Self { user_agent, key, secret, endpoints: Endpoints::LASTFM })
             },
         }
     }
 
+    /// Use this identity with a different service implementing the Last.fm protocol.
+    pub fn with_endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
     pub async fn generate_authorization_token(&self) -> Result<AuthorizationToken, AuthorizationTokenGenerationError> {
         AuthorizationToken::generate(self).await
     }
@@ -55,36 +64,27 @@ impl AuthorizationToken {
 
     /// <https://www.last.fm/api/show/auth.getToken>
     pub async fn generate(client: &ClientIdentity) -> Result<AuthorizationToken, AuthorizationTokenGenerationError> {
-        let url = format!("{}?method=auth.gettoken&api_key={}&format=json", crate::API_URL, client.key);
-        let response = reqwest::get(url).await?;
-
-        #[derive(serde::Serialize, serde::Deserialize)]
-        #[serde(untagged)]
-        enum Response {
-            Ok { token: AuthorizationToken },
-            Fail { #[serde(rename = "error")] code: u8, message: String }
-        }
-        
-        let response = response.text().await?;
-        let response = serde_json::from_str(&response).expect("deserialization failure");
-        // ^ Do I really want to panic here? What if it returns an NGINX 500 HTML error page, or something? (TODO)
+        let url = format!("{}?method=auth.gettoken&api_key={}&format=json", client.endpoints.api, client.key);
+        let response = reqwest::get(url).await.map_err(crate::Error::from)?;
 
+        #[derive(serde::Deserialize)]
+        struct Response { token: AuthorizationToken }
 
-        match response {
-            Response::Ok { token } => Ok(token),
-            Response::Fail { code,  .. } => panic!("fail w/ code {code}") // FIXME better error handling
-        }
+        let response: Response = crate::read_response(response).await?;
+        Ok(response.token)
     }
 
-    pub fn generate_authorization_url(&self, client: &ClientIdentity) -> String {
-        format!("https://www.last.fm/api/auth/?api_key={}&token={self}", client.key)
+    /// Returns `None` if the service has no authorization page; see [`Endpoints::authorization`].
+    pub fn generate_authorization_url(&self, client: &ClientIdentity) -> Option<String> {
+        let authorization = client.endpoints.authorization.as_ref()?;
+        Some(format!("{authorization}?api_key={}&token={self}", client.key))
     }
 
     /// [`Self::get_authorization_url`] flow must be completed prior to obtaining a session token.
     /// - <https://www.last.fm/api/show/auth.getSession>
     pub async fn generate_session_key(&self, client: &ClientIdentity) -> Result<SessionKey, SessionKeyThroughAuthorizationTokenError> {
         let signature = format!("{:x}", md5::compute(format!("api_key{}methodauth.getSessiontoken{self}{}", client.key, client.secret)));
-        let response = reqwest::Client::new().post(crate::API_URL)
+        let response = reqwest::Client::new().post(client.endpoints.api.as_ref())
             .header("Content-Length", "0")
             .header("User-Agent", &client.user_agent)
             .query(&[
@@ -94,17 +94,14 @@ impl AuthorizationToken {
                 ("api_sig", &signature),
                 ("token", self.0.as_str()),
             ])
-            .send().await?
-            .text().await?;
-
-        match serde_json::from_str(&response).expect("output could not be deserialized") {
-            SessionKeyGenerationResponse::Ok { session } => Ok(session.key),
-            SessionKeyGenerationResponse::Fail { code,  .. } => match code {
-                4 => Err(SessionKeyThroughAuthorizationTokenError::Invalid),
-                14 => Err(SessionKeyThroughAuthorizationTokenError::Unauthorized),
-                15 => Err(SessionKeyThroughAuthorizationTokenError::Expired),
-                _ => Err(SessionKeyThroughAuthorizationTokenError::General(GeneralError::try_from(code).expect("unknown error code")))
-            }
+            .send().await.map_err(crate::Error::from)?;
+
+        match crate::read_response::<SessionKeyGenerationResponse>(response).await {
+            Ok(response) => Ok(response.session.key),
+            Err(crate::Error::General(crate::GeneralError::AuthenticationFailure)) => Err(SessionKeyThroughAuthorizationTokenError::Invalid),
+            Err(crate::Error::Other { code: 14, .. }) => Err(SessionKeyThroughAuthorizationTokenError::Unauthorized),
+            Err(crate::Error::Other { code: 15, .. }) => Err(SessionKeyThroughAuthorizationTokenError::Expired),
+            Err(error) => Err(error)?,
         }
     }
 }
@@ -122,8 +119,8 @@ impl core::fmt::Display for AuthorizationToken {
 
 #[derive(Debug, thiserror::Error)]
 pub enum AuthorizationTokenGenerationError {
-    #[error("network error: {0}")]
-    NetworkError(#[from] reqwest::Error)
+    #[error(transparent)]
+    Request(#[from] crate::Error)
 }
 
 /// Returned by the session generation endpoints upon success.
@@ -135,18 +132,14 @@ struct SessionInfo {
 }
 
 #[derive(Serialize, Deserialize)]
-#[serde(untagged)]
-enum SessionKeyGenerationResponse {
-    Ok { session: SessionInfo },
-    Fail { #[serde(rename = "error")] code: u8, message: String }
+struct SessionKeyGenerationResponse {
+    session: SessionInfo
 }
 
 
 /// <https://www.last.fm/api/show/auth.getSession#Errors>
 #[derive(Debug, thiserror::Error)]
 pub enum SessionKeyThroughAuthorizationTokenError {
-    #[error("network failure: {0}")]
-    NetworkFailure(#[from] reqwest::Error),
 
     /// The authorization token is not valid. This may mean it has already been used to successfully create a session.
     #[error("authorization token invalid")]
@@ -158,8 +151,8 @@ pub enum SessionKeyThroughAuthorizationTokenError {
     #[error("token expired")]
     Expired, // 15
 
-    #[error("{0}")]
-    General(#[from] crate::GeneralError)
+    #[error(transparent)]
+    Request(#[from] crate::Error)
 }
 
 /// A key authenticating an authorized user session.
@@ -208,14 +201,11 @@ impl core::fmt::Display for ApiSignature {
 #[derive(Debug, thiserror::Error)]
 
 pub enum SessionKeyThroughCredentialsError {
-    #[error("network failure: {0}")]
-    NetworkFailure(#[from] reqwest::Error),
-
     #[error("username or password is incorrect")]
-    IncorrectCredentials,
+    IncorrectCredentials, // 4
 
-    #[error("{0}")]
-    General(#[from] crate::GeneralError)
+    #[error(transparent)]
+    Request(#[from] crate::Error)
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -227,8 +217,7 @@ pub struct AccountCredentials<'a> {
 impl AccountCredentials<'_> {
     pub async fn generate_session_key(&self, client: &ClientIdentity) -> Result<SessionKey, SessionKeyThroughCredentialsError> {
         let signature = format!("{:x}", md5::compute(format!("api_key{}methodauth.getMobileSessionpassword{}username{}{}", client.key, self.password, self.username, client.secret)));
-        let url = format!("{}?format=json&method=auth.getMobileSession&api_key={}&api_sig={signature}&username={}&password={}", crate::API_URL, client.key, self.username, self.password);
-        let response = reqwest::Client::new().post(crate::API_URL)
+        let response = reqwest::Client::new().post(client.endpoints.api.as_ref())
             .header("Content-Length", "0")
             .header("User-Agent", &client.user_agent)
             .query(&[
@@ -239,15 +228,12 @@ impl AccountCredentials<'_> {
                 ("username", self.username),
                 ("password", self.password),
             ])
-            .send().await?
-            .text().await?;
-
-        match serde_json::from_str(&response).expect("output could not be deserialized") {
-            SessionKeyGenerationResponse::Ok { session } => Ok(session.key),
-            SessionKeyGenerationResponse::Fail { code,  .. } => match code {
-                4 => Err(SessionKeyThroughCredentialsError::IncorrectCredentials),
-                _ =>  Err(SessionKeyThroughCredentialsError::General(GeneralError::try_from(code).expect("unknown error code")))
-            }
+            .send().await.map_err(crate::Error::from)?;
+
+        match crate::read_response::<SessionKeyGenerationResponse>(response).await {
+            Ok(response) => Ok(response.session.key),
+            Err(crate::Error::General(crate::GeneralError::AuthenticationFailure)) => Err(SessionKeyThroughCredentialsError::IncorrectCredentials),
+            Err(error) => Err(error)?,
         }
     }
 }
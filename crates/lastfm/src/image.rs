@@ -0,0 +1,38 @@
+use serde::Deserialize;
+
+/// The sizes Last.fm lists images in, from smallest to largest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageSize {
+    /// 34×34 pixels.
+    Small,
+    /// 64×64 pixels.
+    Medium,
+    /// 174×174 pixels.
+    Large,
+    /// 300×300 pixels.
+    ExtraLarge,
+    /// Usually the same as [`Self::ExtraLarge`].
+    Mega,
+    /// Listed by some endpoints without a size.
+    #[serde(rename = "")]
+    Unspecified,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Image {
+    #[serde(rename = "#text")]
+    pub url: String,
+    pub size: ImageSize,
+}
+
+/// Last.fm stopped serving artist images in 2019, and lists this grey star placeholder instead.
+const PLACEHOLDER: &str = "2a96cbd8b46e442fc41c2b86b821562f";
+
+/// The largest of the images, ignoring empty URLs and placeholders.
+pub fn largest(images: &[Image]) -> Option<&str> {
+    images.iter()
+        .filter(|image| !image.url.is_empty() && !image.url.contains(PLACEHOLDER) && image.size != ImageSize::Unspecified)
+        .max_by_key(|image| image.size)
+        .map(|image| image.url.as_str())
+}
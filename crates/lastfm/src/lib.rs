@@ -5,10 +5,15 @@ use maybe_owned_string::MaybeOwnedString;
 use serde::Deserialize;
 pub mod auth;
 pub mod scrobble;
+pub mod endpoints;
+pub mod user;
+pub mod track;
+pub mod album;
+pub mod artist;
+pub mod image;
 mod parameters;
 
-
-pub(crate) const API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+pub use endpoints::Endpoints;
 
 pub struct Client<A: auth::state::AuthorizationStatus> {
     pub identity: auth::ClientIdentity,
@@ -20,6 +25,23 @@ impl<A: auth::state::AuthorizationStatus> Client<A> {
     pub const fn is_authorized(&self) -> bool {
         self.session_key.is_some()
     }
+
+    /// The service this client dispatches requests to, as determined by its [`auth::ClientIdentity`].
+    pub const fn endpoints(&self) -> &Endpoints {
+        &self.identity.endpoints
+    }
+
+    /// Dispatches a request for a method that doesn't require authentication (or a signature).
+    async fn dispatch_unauthorized(&self, mut request: ApiRequest<'_>) -> Result<reqwest::Response, reqwest::Error> {
+        request.parameters.add("method".to_string(), MaybeOwnedString::Borrowed(request.endpoint));
+        request.parameters.add("api_key".to_string(), MaybeOwnedString::Borrowed(self.identity.get_key()));
+        request.parameters.add("format".to_string(), MaybeOwnedString::Borrowed("json"));
+        let request = self.net.request(request.method, self.endpoints().api.as_ref())
+            .header("User-Agent", &self.identity.user_agent)
+            .query(&request.parameters)
+            .build()?;
+        self.net.execute(request).await
+    }
 }
 impl Client<auth::state::Unauthorized> {
     pub fn new(identity: auth::ClientIdentity) -> Client<auth::state::Unauthorized> {
@@ -60,7 +82,7 @@ impl<'a> Client<auth::state::Authorized> {
         request.parameters.add("api_key".to_string(), MaybeOwnedString::Borrowed(self.identity.get_key()));
         request.parameters.add("api_sig".to_string(), MaybeOwnedString::Owned(request.parameters.sign(self.session_key(), &self.identity).to_string()));
         request.parameters.add("format".to_string(), MaybeOwnedString::Borrowed("json"));
-        let request = self.net.request(request.method, crate::API_URL)
+        let request = self.net.request(request.method, self.endpoints().api.as_ref())
             .header("Content-Length", "0")
             .header("User-Agent", &self.identity.user_agent)
             .query(&request.parameters)
@@ -69,29 +91,41 @@ impl<'a> Client<auth::state::Authorized> {
     }
 
 
-    pub async fn scrobble(&self, scrobbles: &[scrobble::Scrobble<'a>]) -> reqwest::Result<scrobble::response::ScrobbleServerResponse> {
+    pub async fn scrobble(&self, scrobbles: &[scrobble::Scrobble<'a>]) -> Result<scrobble::response::ScrobbleServerResponse, Error> {
         let response = self.dispatch_authorized(ApiRequest {
             endpoint: "track.scrobble",
             method: reqwest::Method::POST,
             parameters: scrobbles.into(),
         }).await?;
 
-        let response = response.text().await.unwrap();
-        let response = scrobble::response::ScrobbleServerResponse::new(response, scrobbles.len());
-        
-        Ok(response)
+        scrobble::response::ScrobbleServerResponse::new(&response_body(response).await?)
     }
 
-    pub async fn set_now_listening(&self, track: &scrobble::HeardTrackInfo<'_>) -> reqwest::Result<String> {
+    /// Returns what Last.fm made of the track, including any corrections; fails with [`Error::Ignored`] if it was ignored.
+    pub async fn set_now_listening(&self, track: &scrobble::HeardTrackInfo<'_>) -> Result<scrobble::response::Acknowledgement, Error> {
         let response = self.dispatch_authorized(ApiRequest {
             endpoint: "track.updateNowPlaying",
             method: reqwest::Method::POST,
             parameters: track.into(),
         }).await?;
 
-        let response = response.text().await.unwrap();
-        
-        Ok(response)
+        let raw: scrobble::response::raw::NowPlayingResponse = read_response(response).await?;
+        Ok(raw.now_playing.into_acknowledgement()??)
+    }
+}
+
+/// Options shared by the `*.getInfo` methods.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InfoQuery<'a> {
+    /// Look up the corrected names if the given ones are known misspellings.
+    pub autocorrect: bool,
+    /// Include the play count (and, for tracks, whether it's loved) of this user.
+    pub username: Option<&'a str>,
+}
+impl<'a> InfoQuery<'a> {
+    fn add_to(self, parameters: &mut parameters::Map<'a>) {
+        if self.autocorrect { parameters.add("autocorrect".to_owned(), MaybeOwnedString::Borrowed("1")) }
+        if let Some(username) = self.username { parameters.add("username".to_owned(), MaybeOwnedString::Borrowed(username)) }
     }
 }
 
@@ -102,6 +136,97 @@ struct ApiRequest<'a> {
     parameters: parameters::Map<'a>
 }
 
+/// An error from any call to the API.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("network failure: {0}")]
+    NetworkFailure(#[from] reqwest::Error),
+    #[error("could not deserialize response: {0}")]
+    Deserialization(#[from] serde_json::Error),
+    /// The service responded with an error status but no error of its own, e.g. with the HTML error page of a proxy.
+    #[error("error status {0}")]
+    Http(reqwest::StatusCode),
+    #[error("{0}")]
+    General(#[from] GeneralError),
+    /// An error code specific to the method that was called.
+    #[error("error {code}: {message}")]
+    Other { code: u8, message: String },
+    /// The now-playing update was ignored.
+    #[error("{0}")]
+    Ignored(#[from] scrobble::response::ScrobbleError),
+}
+impl Error {
+    /// Whether trying again later may succeed, in which case the request is worth queueing rather than dropping.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::NetworkFailure(error) => error.is_timeout() || error.is_connect() || error.is_request(),
+            Self::Http(status) => status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+            Self::General(error) => error.is_retryable(),
+            Self::Ignored(error) => *error == scrobble::response::ScrobbleError::DailyLimitReached,
+            Self::Deserialization(_) | Self::Other { .. } => false,
+        }
+    }
+}
+
+/// The body of a response, or the error the service returned instead.
+pub(crate) async fn response_body(response: reqwest::Response) -> Result<String, Error> {
+    #[derive(Deserialize)]
+    struct Failure { #[serde(rename = "error")] code: u8, message: String }
+
+    let status = response.status();
+    let body = response.text().await?;
+    if let Ok(Failure { code, message }) = serde_json::from_str::<Failure>(&body) {
+        return Err(match GeneralError::try_from(code) {
+            Ok(general) => Error::General(general),
+            Err(()) => Error::Other { code, message }
+        })
+    }
+    if !status.is_success() {
+        return Err(Error::Http(status))
+    }
+    Ok(body)
+}
+
+/// Last.fm sends most numbers as strings, but not consistently.
+pub(crate) fn lenient_number<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw { Number(u64), String(String) }
+    match Raw::deserialize(deserializer)? {
+        Raw::Number(number) => Ok(number),
+        Raw::String(string) => string.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// Like [`lenient_number`], for numbers that may be absent.
+pub(crate) fn lenient_optional_number<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    lenient_number(deserializer).map(Some)
+}
+
+/// The names of the tags of an entity, which are sent as an empty string if there are none.
+pub(crate) fn lenient_tags<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    struct Tag { name: String }
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw { Tags { tag: scrobble::response::raw::MaybeMany<Tag> }, Empty(String) }
+    Ok(match Raw::deserialize(deserializer)? {
+        Raw::Tags { tag: scrobble::response::raw::MaybeMany::Many(tags) } => tags.into_iter().map(|tag| tag.name).collect(),
+        Raw::Tags { tag: scrobble::response::raw::MaybeMany::One(tag) } => vec![tag.name],
+        Raw::Empty(_) => Vec::new(),
+    })
+}
+
+/// An empty string means there isn't one.
+pub(crate) fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+/// Deserializes the body of a response, surfacing any error the service returned instead.
+pub(crate) async fn read_response<T: serde::de::DeserializeOwned>(response: reqwest::Response) -> Result<T, Error> {
+    Ok(serde_json::from_str(&response_body(response).await?)?)
+}
+
 #[derive(thiserror::Error, Debug)]
 
 pub enum GeneralError {
@@ -155,6 +280,12 @@ pub enum GeneralError {
     #[error("ratelimit exceeded")]
     RatelimitExceeded, // 29
 }
+impl GeneralError {
+    /// Whether trying again later may succeed.
+    pub const fn is_retryable(&self) -> bool {
+        matches!(self, Self::ServiceOffline | Self::TemporaryError | Self::RatelimitExceeded)
+    }
+}
 impl TryFrom<u8> for GeneralError {
     type Error = ();
     fn try_from(value: u8) -> Result<Self, Self::Error> {
@@ -1,3 +1,11 @@
+/// The maximum number of scrobbles accepted in a single request.
+/// - <https://www.last.fm/api/show/track.scrobble#Params>
+pub const MAX_SCROBBLES_PER_REQUEST: usize = 50;
+
+/// Scrobbles with a timestamp older than this are ignored with [`response::ScrobbleError::TimestampTooOld`].
+/// - <https://www.last.fm/api/scrobbling#scrobble-requests>
+pub const MAX_SCROBBLE_AGE: chrono::TimeDelta = chrono::TimeDelta::days(14);
+
 /// Details on a track that was listened to or is currently being listened to.
 /// 
 /// It is the shared parameters of the following endpoints:
@@ -20,9 +28,9 @@ pub struct HeardTrackInfo<'a> {
     /// The album artist, if it differs from the track artist
     pub album_artist: Option<&'a str>,
 
-    /// The MusicBrainz Track ID.
+    /// What Last.fm calls the MusicBrainz Track ID, which is actually the ID of the recording.
     // TODO: Gate this type definition behind a feature, making it a `&'a str` otherwise.
-    pub mbid: Option<brainz::music::Id<brainz::music::Track>>,
+    pub mbid: Option<brainz::music::Id<brainz::music::Recording>>,
 
     /// The duration of the track in seconds.
     pub duration_in_seconds: Option<u32>,
@@ -69,82 +77,23 @@ pub struct ScrobbleEnrichmentParameters {
 pub mod response {
     use super::*;
 
-    pub struct ScrobbleServerResponse<'a> {
-        json: core::pin::Pin<String>,
-        pub results: Vec<Result<Acknowledgement<'a>, ScrobbleError>>,
+    #[derive(Debug)]
+    pub struct ScrobbleServerResponse {
+        /// In the same order as the scrobbles that were submitted.
+        pub results: Vec<Result<Acknowledgement, ScrobbleError>>,
         pub counts: raw::ResponseAttributes,
     }
-    impl<'a> ScrobbleServerResponse<'a> {
-        pub fn new(json: String, capacity: usize) -> Self {
-            let json = core::pin::Pin::new(json);
-    
-            let (results, counts) = {
-                // Disassociate to allow reference into JSON despite it "moving" into this struct.
-                // It's alright since it's pinned and doesn't actually move.
-                let json = unsafe {
-                    let bytes = core::slice::from_raw_parts(json.as_ptr(), json.len());
-                    let bytes = core::mem::transmute::<&'a [u8], &[u8]>(bytes);
-                    core::str::from_utf8_unchecked(bytes)
-                };
-
-                // TODO: I'm not actually handling any errors! Uh oh!
-                let raw: raw::Response = serde_json::from_str(json).expect("cannot deserialize");
-                let mut vec = Vec::with_capacity(capacity);
-                let raw = raw.scrobbles; let counts = raw.counts;
-                let raw = match raw.inner {
-                    raw::MaybeMany::One(single) => vec![single],
-                    raw::MaybeMany::Many(many) => many
-                };
-
-                for response in raw {
-                    if response.ignored_message.code != "0" {
-                        let code: u8 = response.ignored_message.code.parse().expect("could not parse ignoration code");
-                        vec.push(Err(ScrobbleError::try_from(code).expect("unknown ignoration code")))
-                    } else {
-                        let timestamp = response.timestamp.parse().expect("bad timestamp");
-                        let mut album: Option<MaybeCorrected<&str>> = None;
-                        let mut album_artist: Option<MaybeCorrected<&str>> = None;
-
-                        let track = MaybeCorrected {
-                            value: response.track.text,
-                            corrected: response.track.corrected == "1",
-                        };
-                        let artist = MaybeCorrected {
-                            value: response.artist.text,
-                            corrected: response.artist.corrected == "1",
-                        };
-
-                        if !response.album.text.is_empty() {
-                            album = Some(MaybeCorrected {
-                                value: response.album.text,
-                                corrected: response.album.corrected == "1",
-                            });
-                        }
-                        if !response.album_artist.text.is_empty() {
-                            album_artist = Some(MaybeCorrected {
-                                value: response.album_artist.text,
-                                corrected: response.album_artist.corrected == "1",
-                            });
-                        }
-                    
-                        vec.push(Ok(Acknowledgement {
-                            artist,
-                            track,
-                            album,
-                            album_artist,
-                            timestamp
-                        }))
-                    }
-                }
-
-                (vec, counts)
+    impl ScrobbleServerResponse {
+        pub fn new(json: &str) -> Result<Self, crate::Error> {
+            let raw: raw::Response = serde_json::from_str(json)?;
+            let raw = raw.scrobbles; let counts = raw.counts;
+            let raw = match raw.inner {
+                raw::MaybeMany::One(single) => vec![single],
+                raw::MaybeMany::Many(many) => many
             };
-            
-            Self {
-                json,
-                results,
-                counts
-            }
+
+            let results = raw.into_iter().map(raw::ScrobbleResponse::into_acknowledgement).collect::<Result<_, _>>()?;
+            Ok(Self { results, counts })
         }
     }
     
@@ -170,35 +119,41 @@ pub mod response {
 
         /// Scrobble was ignored because the daily limit was reached.
         #[error("scrobble daily limit reached")]
-        DailyLimitReached
+        DailyLimitReached,
+
+        /// Scrobble was ignored for a reason that isn't documented.
+        #[error("ignored with unknown code {0}")]
+        Unknown(u8),
     }
-    impl TryFrom<u8> for ScrobbleError {
-        type Error = ();
-        fn try_from(value: u8) -> Result<Self, Self::Error> {
+    impl From<u8> for ScrobbleError {
+        fn from(value: u8) -> Self {
             match value {
-                1 => Ok(Self::BadArtist),
-                2 => Ok(Self::BadTrack),
-                3 => Ok(Self::TimestampTooOld),
-                4 => Ok(Self::TimestampTooNew),
-                5 => Ok(Self::DailyLimitReached),
-                _ => Err(())
+                1 => Self::BadArtist,
+                2 => Self::BadTrack,
+                3 => Self::TimestampTooOld,
+                4 => Self::TimestampTooNew,
+                5 => Self::DailyLimitReached,
+                code => Self::Unknown(code)
             }
         }
     }
 
 
-    #[derive(PartialEq)]
+    #[derive(PartialEq, Debug, Clone)]
     pub struct MaybeCorrected<T> {
         pub corrected: bool,
         pub value: T
     }
 
-    pub struct Acknowledgement<'a> {
-        artist: MaybeCorrected<&'a str>,
-        track: MaybeCorrected<&'a str>,
-        album: Option<MaybeCorrected<&'a str>>,
-        album_artist: Option<MaybeCorrected<&'a str>>,
-        timestamp: u32,
+    /// What Last.fm recorded for a submitted track, which may have been corrected.
+    #[derive(Debug, Clone)]
+    pub struct Acknowledgement {
+        pub artist: MaybeCorrected<String>,
+        pub track: MaybeCorrected<String>,
+        pub album: Option<MaybeCorrected<String>>,
+        pub album_artist: Option<MaybeCorrected<String>>,
+        /// Seconds since the Unix epoch; `None` for now-playing updates.
+        pub timestamp: Option<u32>,
     }
 
 
@@ -206,6 +161,10 @@ pub mod response {
         use super::*;
         use serde::*;
 
+        fn invalid(what: &str, value: &str) -> serde_json::Error {
+            <serde_json::Error as serde::de::Error>::custom(format!("invalid {what}: {value:?}"))
+        }
+
 
         #[derive(Debug, Deserialize)]
         #[serde(untagged)]
@@ -215,28 +174,61 @@ pub mod response {
         }
 
         #[derive(Debug, Deserialize)]
-        pub struct MaybeCorrected<'a> {
+        pub struct MaybeCorrected {
             #[serde(rename = "#text", default)]  // not present on albums if omitted but always present on album artist if omitted ?? idk. regardless, empty string means omitted
-            pub text: &'a str,
-            pub corrected: &'a str, // "0" || "1",
+            pub text: String,
+            pub corrected: String, // "0" || "1",
+        }
+        impl MaybeCorrected {
+            fn omitted() -> Self {
+                Self { text: String::new(), corrected: "0".to_owned() }
+            }
+            fn into_corrected(self) -> super::MaybeCorrected<String> {
+                super::MaybeCorrected { corrected: self.corrected == "1", value: self.text }
+            }
+            fn into_corrected_if_present(self) -> Option<super::MaybeCorrected<String>> {
+                Some(self).filter(|value| !value.text.is_empty()).map(Self::into_corrected)
+            }
         }
 
         #[derive(Debug, Deserialize)]
-        pub struct IgnoredMessage<'a> {
-            pub code: &'a str // u8
+        pub struct IgnoredMessage {
+            pub code: String // u8
         }
 
         #[derive(Debug, Deserialize)]
         #[serde(rename_all = "camelCase")]
-        pub struct ScrobbleResponse<'a> {
-            #[serde(borrow)] pub artist: MaybeCorrected<'a>,
-            #[serde(borrow)] pub track: MaybeCorrected<'a>,
-            #[serde(borrow)] pub album: MaybeCorrected<'a>,
-            #[serde(borrow)] pub album_artist: MaybeCorrected<'a>,
-            #[serde(borrow)] pub timestamp: &'a str, // int (seconds past unix epoch)
-            #[serde(borrow)] pub ignored_message: IgnoredMessage<'a>,
+        pub struct ScrobbleResponse {
+            pub artist: MaybeCorrected,
+            pub track: MaybeCorrected,
+            #[serde(default = "MaybeCorrected::omitted")]
+            pub album: MaybeCorrected,
+            #[serde(default = "MaybeCorrected::omitted")]
+            pub album_artist: MaybeCorrected,
+            /// Absent on now-playing updates.
+            pub timestamp: Option<String>, // int (seconds past unix epoch)
+            pub ignored_message: IgnoredMessage,
+        }
+        impl ScrobbleResponse {
+            /// Fails if the response is malformed; the inner result is whether the track was ignored.
+            pub fn into_acknowledgement(self) -> Result<Result<Acknowledgement, ScrobbleError>, serde_json::Error> {
+                let code = self.ignored_message.code.parse::<u8>().map_err(|_| invalid("ignored message code", &self.ignored_message.code))?;
+                if code != 0 {
+                    return Ok(Err(ScrobbleError::from(code)))
+                }
+                let timestamp = match self.timestamp {
+                    Some(timestamp) => Some(timestamp.parse().map_err(|_| invalid("timestamp", &timestamp))?),
+                    None => None,
+                };
+                Ok(Ok(Acknowledgement {
+                    artist: self.artist.into_corrected(),
+                    track: self.track.into_corrected(),
+                    album: self.album.into_corrected_if_present(),
+                    album_artist: self.album_artist.into_corrected_if_present(),
+                    timestamp,
+                }))
+            }
         }
-
         #[derive(Debug, Deserialize)]
         pub struct ResponseAttributes {
             pub ignored: usize,
@@ -244,17 +236,19 @@ pub mod response {
         }
 
         #[derive(Debug, Deserialize)]
-        pub struct ScrobblesContainer<'a> {
-            #[serde(rename = "scrobble", borrow)] pub inner: MaybeMany<ScrobbleResponse<'a>>,
+        pub struct ScrobblesContainer {
+            #[serde(rename = "scrobble")] pub inner: MaybeMany<ScrobbleResponse>,
             #[serde(rename = "@attr")] pub counts: ResponseAttributes
         }
 
         #[derive(Debug, Deserialize)]
-        pub struct Response<'a> {
-            #[serde(borrow)] pub scrobbles: ScrobblesContainer<'a>,
+        pub struct Response {
+            pub scrobbles: ScrobblesContainer,
+        }
+
+        #[derive(Debug, Deserialize)]
+        pub struct NowPlayingResponse {
+            #[serde(rename = "nowplaying")] pub now_playing: ScrobbleResponse,
         }
     }
 }
-
-
-
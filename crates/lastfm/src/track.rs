@@ -0,0 +1,212 @@
+use maybe_owned_string::MaybeOwnedString;
+
+use serde::Deserialize;
+
+use crate::{auth, image::Image, parameters, ApiRequest, Client, Error, InfoQuery};
+
+/// The album a track is on, as listed by `track.getInfo`.
+#[derive(Debug, Clone)]
+pub struct TrackAlbum {
+    pub title: String,
+    pub artist: String,
+    pub mbid: Option<String>,
+    pub images: Vec<Image>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrackInfo {
+    pub name: String,
+    pub mbid: Option<String>,
+    pub url: String,
+    pub artist: String,
+    pub artist_mbid: Option<String>,
+    pub album: Option<TrackAlbum>,
+    pub duration: Option<core::time::Duration>,
+    pub listeners: u64,
+    pub play_count: u64,
+    /// Only present if a username was given.
+    pub user_play_count: Option<u64>,
+    /// Only present if a username was given.
+    pub user_loved: Option<bool>,
+    pub tags: Vec<String>,
+}
+
+/// The canonical names of a track which Last.fm knows by a misspelling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Correction {
+    pub artist: String,
+    pub track: String,
+    pub artist_corrected: bool,
+    pub track_corrected: bool,
+}
+
+impl<A: auth::state::AuthorizationStatus> Client<A> {
+    /// - <https://www.last.fm/api/show/track.getInfo>
+    pub async fn get_track_info(&self, artist: &str, track: &str, query: InfoQuery<'_>) -> Result<TrackInfo, Error> {
+        let mut parameters = parameters::Map::from_collection(Default::default());
+        parameters.add("artist".to_owned(), MaybeOwnedString::Borrowed(artist));
+        parameters.add("track".to_owned(), MaybeOwnedString::Borrowed(track));
+        query.add_to(&mut parameters);
+
+        let response = self.dispatch_unauthorized(ApiRequest {
+            endpoint: "track.getInfo",
+            method: reqwest::Method::GET,
+            parameters,
+        }).await?;
+
+        let raw: raw::InfoResponse = crate::read_response(response).await?;
+        let track = raw.track;
+        Ok(TrackInfo {
+            name: track.name,
+            mbid: track.mbid.and_then(crate::non_empty),
+            url: track.url,
+            artist: track.artist.name,
+            artist_mbid: track.artist.mbid.and_then(crate::non_empty),
+            album: track.album.map(|album| TrackAlbum {
+                title: album.title,
+                artist: album.artist,
+                mbid: album.mbid.and_then(crate::non_empty),
+                images: album.image,
+            }),
+            duration: track.duration.filter(|millis| *millis > 0).map(core::time::Duration::from_millis),
+            listeners: track.listeners,
+            play_count: track.playcount,
+            user_play_count: track.userplaycount,
+            user_loved: track.userloved.map(|loved| loved == 1),
+            tags: track.toptags,
+        })
+    }
+
+    /// `None` if the names aren't a known misspelling.
+    /// - <https://www.last.fm/api/show/track.getCorrection>
+    pub async fn get_track_correction(&self, artist: &str, track: &str) -> Result<Option<Correction>, Error> {
+        let mut parameters = parameters::Map::from_collection(Default::default());
+        parameters.add("artist".to_owned(), MaybeOwnedString::Borrowed(artist));
+        parameters.add("track".to_owned(), MaybeOwnedString::Borrowed(track));
+
+        let response = self.dispatch_unauthorized(ApiRequest {
+            endpoint: "track.getCorrection",
+            method: reqwest::Method::GET,
+            parameters,
+        }).await?;
+
+        let raw: raw::CorrectionResponse = crate::read_response(response).await?;
+        let raw::Corrections::Some { correction } = raw.corrections else { return Ok(None) };
+        let correction = Correction {
+            artist: correction.track.artist.name,
+            track: correction.track.name,
+            artist_corrected: correction.attributes.artistcorrected == "1",
+            track_corrected: correction.attributes.trackcorrected == "1",
+        };
+        Ok(Some(correction).filter(|correction| correction.artist_corrected || correction.track_corrected))
+    }
+}
+
+
+impl Client<auth::state::Authorized> {
+    async fn set_loved(&self, endpoint: &'static str, artist: &str, track: &str) -> Result<(), Error> {
+        let mut parameters = parameters::Map::from_collection(Default::default());
+        parameters.add("artist".to_owned(), MaybeOwnedString::Borrowed(artist));
+        parameters.add("track".to_owned(), MaybeOwnedString::Borrowed(track));
+
+        let response = self.dispatch_authorized(ApiRequest {
+            endpoint,
+            method: reqwest::Method::POST,
+            parameters,
+        }).await?;
+
+        // there's nothing of note in a successful response
+        crate::read_response::<serde::de::IgnoredAny>(response).await?;
+        Ok(())
+    }
+
+    /// Adds the track to the user's loved tracks. Loving a track that's already loved has no effect.
+    /// - <https://www.last.fm/api/show/track.love>
+    pub async fn love(&self, artist: &str, track: &str) -> Result<(), Error> {
+        self.set_loved("track.love", artist, track).await
+    }
+
+    /// Removes the track from the user's loved tracks. Unloving a track that isn't loved has no effect.
+    /// - <https://www.last.fm/api/show/track.unlove>
+    pub async fn unlove(&self, artist: &str, track: &str) -> Result<(), Error> {
+        self.set_loved("track.unlove", artist, track).await
+    }
+}
+
+mod raw {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    pub struct Artist {
+        pub name: String,
+        pub mbid: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Album {
+        pub title: String,
+        pub artist: String,
+        pub mbid: Option<String>,
+        #[serde(default)]
+        pub image: Vec<Image>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Track {
+        pub name: String,
+        pub mbid: Option<String>,
+        pub url: String,
+        pub artist: Artist,
+        pub album: Option<Album>,
+        /// In milliseconds; zero if unknown.
+        #[serde(default, deserialize_with = "crate::lenient_optional_number")]
+        pub duration: Option<u64>,
+        #[serde(deserialize_with = "crate::lenient_number")]
+        pub listeners: u64,
+        #[serde(deserialize_with = "crate::lenient_number")]
+        pub playcount: u64,
+        #[serde(default, deserialize_with = "crate::lenient_optional_number")]
+        pub userplaycount: Option<u64>,
+        #[serde(default, deserialize_with = "crate::lenient_optional_number")]
+        pub userloved: Option<u64>,
+        #[serde(default, deserialize_with = "crate::lenient_tags")]
+        pub toptags: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct InfoResponse {
+        pub track: Track,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct CorrectedTrack {
+        pub name: String,
+        pub artist: Artist,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct CorrectionAttributes {
+        pub artistcorrected: String, // "0" || "1"
+        pub trackcorrected: String, // "0" || "1"
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Correction {
+        pub track: CorrectedTrack,
+        #[serde(rename = "@attr")]
+        pub attributes: CorrectionAttributes,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    pub enum Corrections {
+        Some { correction: Correction },
+        /// Sent as whitespace when there's no correction.
+        None(String),
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct CorrectionResponse {
+        pub corrections: Corrections,
+    }
+}
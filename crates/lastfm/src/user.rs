@@ -0,0 +1,221 @@
+use maybe_owned_string::MaybeOwnedString;
+
+use crate::{auth, parameters, ApiRequest, Client, Error};
+
+/// The maximum number of tracks returned per page by `user.getRecentTracks`.
+pub const MAX_RECENT_TRACKS_PER_PAGE: u8 = 200;
+/// The maximum number of tracks returned per page by `user.getLovedTracks`.
+pub const MAX_LOVED_TRACKS_PER_PAGE: u16 = 1000;
+
+/// - <https://www.last.fm/api/show/user.getRecentTracks#Params>
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RecentTracksQuery {
+    /// Only return scrobbles after this time.
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    /// Only return scrobbles before this time.
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    /// The page to fetch, starting at one.
+    pub page: Option<u32>,
+    /// The number of results per page; clamped to [`MAX_RECENT_TRACKS_PER_PAGE`].
+    pub limit: Option<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecentTrack {
+    pub artist: String,
+    pub track: String,
+    pub album: Option<String>,
+    pub mbid: Option<String>,
+    /// `None` if the track is currently being listened to.
+    pub scrobbled_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LovedTrack {
+    pub artist: String,
+    pub track: String,
+    pub mbid: Option<String>,
+    pub loved_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug)]
+pub struct LovedTracksPage {
+    pub tracks: Vec<LovedTrack>,
+    pub page: u32,
+    pub total_pages: u32,
+    pub total: u32,
+}
+
+#[derive(Debug)]
+pub struct RecentTracksPage {
+    pub tracks: Vec<RecentTrack>,
+    pub page: u32,
+    pub total_pages: u32,
+    pub total: u32,
+}
+
+impl<A: auth::state::AuthorizationStatus> Client<A> {
+    /// - <https://www.last.fm/api/show/user.getRecentTracks>
+    pub async fn get_recent_tracks(&self, user: &str, query: RecentTracksQuery) -> Result<RecentTracksPage, Error> {
+        let mut parameters = parameters::Map::from_collection(Default::default());
+        parameters.add("user".to_owned(), MaybeOwnedString::Borrowed(user));
+        if let Some(from) = query.from { parameters.add("from".to_owned(), MaybeOwnedString::Owned(from.timestamp().to_string())) }
+        if let Some(to) = query.to { parameters.add("to".to_owned(), MaybeOwnedString::Owned(to.timestamp().to_string())) }
+        if let Some(page) = query.page { parameters.add("page".to_owned(), MaybeOwnedString::Owned(page.to_string())) }
+        if let Some(limit) = query.limit { parameters.add("limit".to_owned(), MaybeOwnedString::Owned(limit.min(MAX_RECENT_TRACKS_PER_PAGE).to_string())) }
+
+        let response = self.dispatch_unauthorized(ApiRequest {
+            endpoint: "user.getRecentTracks",
+            method: reqwest::Method::GET,
+            parameters,
+        }).await?;
+
+        let raw: raw::Response = crate::read_response(response).await?;
+        let tracks = match raw.recent_tracks.track {
+            crate::scrobble::response::raw::MaybeMany::One(single) => vec![single],
+            crate::scrobble::response::raw::MaybeMany::Many(many) => many,
+        };
+
+        Ok(RecentTracksPage {
+            tracks: tracks.into_iter().map(|track| RecentTrack {
+                artist: track.artist.text,
+                track: track.name,
+                album: Some(track.album.text).filter(|album| !album.is_empty()),
+                mbid: Some(track.mbid).filter(|mbid| !mbid.is_empty()),
+                scrobbled_at: track.date
+                    .and_then(|date| date.uts.parse().ok())
+                    .and_then(|uts| chrono::DateTime::from_timestamp(uts, 0)),
+            }).collect(),
+            page: raw.recent_tracks.attributes.page.parse().unwrap_or(1),
+            total_pages: raw.recent_tracks.attributes.total_pages.parse().unwrap_or(0),
+            total: raw.recent_tracks.attributes.total.parse().unwrap_or(0),
+        })
+    }
+
+    /// - <https://www.last.fm/api/show/user.getLovedTracks>
+    pub async fn get_loved_tracks(&self, user: &str, page: Option<u32>, limit: Option<u16>) -> Result<LovedTracksPage, Error> {
+        let mut parameters = parameters::Map::from_collection(Default::default());
+        parameters.add("user".to_owned(), MaybeOwnedString::Borrowed(user));
+        if let Some(page) = page { parameters.add("page".to_owned(), MaybeOwnedString::Owned(page.to_string())) }
+        if let Some(limit) = limit { parameters.add("limit".to_owned(), MaybeOwnedString::Owned(limit.min(MAX_LOVED_TRACKS_PER_PAGE).to_string())) }
+
+        let response = self.dispatch_unauthorized(ApiRequest {
+            endpoint: "user.getLovedTracks",
+            method: reqwest::Method::GET,
+            parameters,
+        }).await?;
+
+        let raw: raw::LovedResponse = crate::read_response(response).await?;
+        let tracks = match raw.loved_tracks.track {
+            crate::scrobble::response::raw::MaybeMany::One(single) => vec![single],
+            crate::scrobble::response::raw::MaybeMany::Many(many) => many,
+        };
+
+        Ok(LovedTracksPage {
+            tracks: tracks.into_iter().map(|track| LovedTrack {
+                artist: track.artist.name,
+                track: track.name,
+                mbid: Some(track.mbid).filter(|mbid| !mbid.is_empty()),
+                loved_at: track.date
+                    .and_then(|date| date.uts.parse().ok())
+                    .and_then(|uts| chrono::DateTime::from_timestamp(uts, 0)),
+            }).collect(),
+            page: raw.loved_tracks.attributes.page.parse().unwrap_or(1),
+            total_pages: raw.loved_tracks.attributes.total_pages.parse().unwrap_or(0),
+            total: raw.loved_tracks.attributes.total.parse().unwrap_or(0),
+        })
+    }
+}
+
+impl Client<auth::state::Authorized> {
+    /// Retrieves the name of the user that the session key belongs to.
+    /// - <https://www.last.fm/api/show/user.getInfo>
+    pub async fn get_authenticated_username(&self) -> Result<String, Error> {
+        let response = self.dispatch_authorized(ApiRequest {
+            endpoint: "user.getInfo",
+            method: reqwest::Method::GET,
+            parameters: parameters::Map::from_collection(Default::default()),
+        }).await?;
+
+        #[derive(serde::Deserialize)]
+        struct User { name: String }
+        #[derive(serde::Deserialize)]
+        struct Response { user: User }
+
+        let response: Response = crate::read_response(response).await?;
+        Ok(response.user.name)
+    }
+}
+
+mod raw {
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    pub struct Text {
+        #[serde(rename = "#text", default)]
+        pub text: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Date {
+        pub uts: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct RecentTrack {
+        pub artist: Text,
+        pub album: Text,
+        pub name: String,
+        #[serde(default)]
+        pub mbid: String,
+        pub date: Option<Date>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Pagination {
+        pub page: String,
+        pub total_pages: String,
+        pub total: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct RecentTracks {
+        pub track: crate::scrobble::response::raw::MaybeMany<RecentTrack>,
+        #[serde(rename = "@attr")]
+        pub attributes: Pagination,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Response {
+        #[serde(rename = "recenttracks")]
+        pub recent_tracks: RecentTracks,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Name {
+        pub name: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct LovedTrack {
+        pub artist: Name,
+        pub name: String,
+        #[serde(default)]
+        pub mbid: String,
+        pub date: Option<Date>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct LovedTracks {
+        pub track: crate::scrobble::response::raw::MaybeMany<LovedTrack>,
+        #[serde(rename = "@attr")]
+        pub attributes: Pagination,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct LovedResponse {
+        #[serde(rename = "lovedtracks")]
+        pub loved_tracks: LovedTracks,
+    }
+}
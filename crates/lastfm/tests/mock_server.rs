@@ -0,0 +1,305 @@
+//! Exercises the client against a local server speaking just enough HTTP to stand in for a Last.fm-compatible service.
+
+use lastfm::{auth::{ClientIdentity, SessionKey}, scrobble::{HeardTrackInfo, Scrobble}, Endpoints};
+use shared::mock_server::{MockServer, Response};
+
+const KEY: &str = "0123456789abcdef0123456789abcdef";
+const SECRET: &str = "fedcba9876543210fedcba9876543210";
+const SESSION_KEY: &str = "abcdefghijklmnopqrstuvwxyz012345";
+
+/// Serves a single request with the given JSON body.
+async fn serve_once(body: &'static str) -> (Endpoints, MockServer) {
+    serve_once_with("200 OK", "application/json", body).await
+}
+
+/// Like [`serve_once`], with an arbitrary status and content type.
+async fn serve_once_with(status: &'static str, content_type: &'static str, body: &'static str) -> (Endpoints, MockServer) {
+    let server = MockServer::serve([Response {
+        head: format!("{status}\r\ncontent-type: {content_type}"),
+        body: body.to_owned(),
+    }]).await;
+    (Endpoints::new(format!("{}/2.0/", server.root), format!("{}/api/auth/", server.root)), server)
+}
+
+fn client(endpoints: Endpoints) -> lastfm::Client<lastfm::auth::state::Authorized> {
+    let identity = ClientIdentity::new("am-osx-status-tests".to_owned(), KEY, SECRET).unwrap().with_endpoints(endpoints);
+    let session_key: SessionKey = serde_json::from_str(&format!("\"{SESSION_KEY}\"")).unwrap();
+    lastfm::Client::authorized(identity, session_key)
+}
+
+const HEARD: HeardTrackInfo<'static> = HeardTrackInfo {
+    artist: "Artist",
+    track: "Track",
+    track_number: None,
+    album: Some("Album"),
+    album_artist: None,
+    mbid: None,
+    duration_in_seconds: Some(200),
+};
+
+#[tokio::test]
+async fn scrobble() {
+    let (endpoints, server) = serve_once(r##"{"scrobbles":{"scrobble":{
+        "artist":{"corrected":"0","#text":"Artist"},
+        "album":{"corrected":"0","#text":"Album"},
+        "track":{"corrected":"0","#text":"Track"},
+        "albumArtist":{"corrected":"0","#text":""},
+        "ignoredMessage":{"code":"0","#text":""},
+        "timestamp":"1700000000"
+    },"@attr":{"ignored":0,"accepted":1}}}"##).await;
+
+    let client = client(endpoints);
+    let response = client.scrobble(&[Scrobble {
+        info: HEARD,
+        timestamp: chrono::DateTime::from_timestamp(1700000000, 0).unwrap(),
+        chosen_by_user: None,
+    }]).await.unwrap();
+
+    assert_eq!(response.counts.accepted, 1);
+    assert_eq!(response.counts.ignored, 0);
+    assert!(response.results.iter().all(Result::is_ok));
+
+    let request = server.requests().remove(0);
+    assert!(request.starts_with("POST /2.0/?"), "{request}");
+    assert!(request.contains("method=track.scrobble"), "{request}");
+    assert!(request.contains("artist%5B0%5D=Artist"), "{request}");
+    assert!(request.contains("timestamp%5B0%5D=1700000000"), "{request}");
+    assert!(request.contains(&format!("sk={SESSION_KEY}")), "{request}");
+    assert!(request.contains("api_sig="), "{request}");
+}
+
+#[tokio::test]
+async fn set_now_listening() {
+    let (endpoints, server) = serve_once(r##"{"nowplaying":{
+        "artist":{"corrected":"0","#text":"Artist"},
+        "album":{"corrected":"0","#text":"Album"},
+        "track":{"corrected":"0","#text":"Track"},
+        "albumArtist":{"corrected":"0","#text":""},
+        "ignoredMessage":{"code":"0","#text":""}
+    }}"##).await;
+
+    let client = client(endpoints);
+    let acknowledgement = client.set_now_listening(&HEARD).await.unwrap();
+    assert_eq!(acknowledgement.track.value, "Track");
+    assert_eq!(acknowledgement.album_artist, None);
+    assert_eq!(acknowledgement.timestamp, None);
+
+    let request = server.requests().remove(0);
+    assert!(request.starts_with("POST /2.0/?"), "{request}");
+    assert!(request.contains("method=track.updateNowPlaying"), "{request}");
+    assert!(request.contains("track=Track"), "{request}");
+    assert!(request.contains("duration=200"), "{request}");
+}
+
+#[tokio::test]
+async fn authorization_against_alternate_service() {
+    let (endpoints, server) = serve_once(r#"{"token":"abcdefghijklmnopqrstuvwxyz012345"}"#).await;
+    let identity = ClientIdentity::new("am-osx-status-tests".to_owned(), KEY, SECRET).unwrap().with_endpoints(endpoints.clone());
+
+    let token = identity.generate_authorization_token().await.unwrap();
+    assert_eq!(token.as_ref(), "abcdefghijklmnopqrstuvwxyz012345");
+    assert_eq!(
+        token.generate_authorization_url(&identity),
+        Some(format!("{}?api_key={KEY}&token={token}", endpoints.authorization.unwrap()))
+    );
+
+    let request = server.requests().remove(0);
+    assert!(request.starts_with("GET /2.0/?method=auth.gettoken"), "{request}");
+}
+
+#[test]
+fn known_services() {
+    assert!(Endpoints::default().is_lastfm());
+    assert_eq!(Endpoints::gnu_fm("https://fm.example.com/").api, "https://fm.example.com/2.0/");
+    assert_eq!(Endpoints::maloja("https://maloja.example.com").api, "https://maloja.example.com/apis/audioscrobbler/");
+    assert_eq!(Endpoints::maloja("https://maloja.example.com").authorization, None);
+
+    let identity = ClientIdentity::new("am-osx-status-tests".to_owned(), KEY, SECRET).unwrap();
+    let serialized = serde_json::to_string(&identity).unwrap();
+    assert!(!serialized.contains("endpoints"), "default endpoints shouldn't be serialized");
+
+    let libre = identity.with_endpoints(Endpoints::LIBREFM);
+    let roundtrip: ClientIdentity = serde_json::from_str(&serde_json::to_string(&libre).unwrap()).unwrap();
+    assert_eq!(roundtrip.endpoints, Endpoints::LIBREFM);
+}
+
+#[tokio::test]
+async fn recent_tracks() {
+    let (endpoints, server) = serve_once(r##"{"recenttracks":{"track":[
+        {"artist":{"mbid":"","#text":"Artist"},"album":{"mbid":"","#text":"Album"},"name":"Now Playing","mbid":"","@attr":{"nowplaying":"true"}},
+        {"artist":{"mbid":"","#text":"Artist"},"album":{"mbid":"","#text":""},"name":"Track","mbid":"","date":{"uts":"1700000000","#text":"14 Nov 2023, 22:13"}}
+    ],"@attr":{"user":"someone","totalPages":"3","page":"1","perPage":"2","total":"6"}}}"##).await;
+
+    let page = client(endpoints).get_recent_tracks("someone", lastfm::user::RecentTracksQuery {
+        limit: Some(2),
+        ..Default::default()
+    }).await.unwrap();
+
+    assert_eq!(page.total_pages, 3);
+    assert_eq!(page.tracks.len(), 2);
+    assert_eq!(page.tracks[0].scrobbled_at, None);
+    assert_eq!(page.tracks[1].album, None);
+    assert_eq!(page.tracks[1].scrobbled_at, chrono::DateTime::from_timestamp(1700000000, 0));
+
+    let request = server.requests().remove(0);
+    assert!(request.starts_with("GET /2.0/?"), "{request}");
+    assert!(request.contains("method=user.getRecentTracks"), "{request}");
+    assert!(request.contains("user=someone"), "{request}");
+    assert!(!request.contains("api_sig="), "{request}");
+}
+
+#[tokio::test]
+async fn read_errors_are_surfaced() {
+    let (endpoints, _) = serve_once(r#"{"error":6,"message":"User not found"}"#).await;
+    let error = client(endpoints).get_recent_tracks("nobody", Default::default()).await.unwrap_err();
+    assert!(matches!(error, lastfm::Error::General(lastfm::GeneralError::MissingParameter)), "{error:?}");
+}
+
+#[tokio::test]
+async fn love() {
+    let (endpoints, server) = serve_once("{}").await;
+    client(endpoints).love("Artist", "Track").await.unwrap();
+
+    let request = server.requests().remove(0);
+    assert!(request.starts_with("POST /2.0/?"), "{request}");
+    assert!(request.contains("method=track.love"), "{request}");
+    assert!(request.contains("artist=Artist"), "{request}");
+    assert!(request.contains("api_sig="), "{request}");
+}
+
+#[tokio::test]
+async fn loved_tracks() {
+    let (endpoints, server) = serve_once(r##"{"lovedtracks":{"track":[
+        {"artist":{"url":"","name":"Artist","mbid":""},"date":{"uts":"1700000000","#text":"14 Nov 2023, 22:13"},"mbid":"","url":"","name":"Track","image":[],"streamable":{"fulltrack":"0","#text":"0"}}
+    ],"@attr":{"user":"someone","totalPages":"1","page":"1","perPage":"50","total":"1"}}}"##).await;
+
+    let page = client(endpoints).get_loved_tracks("someone", None, None).await.unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.tracks[0].artist, "Artist");
+    assert_eq!(page.tracks[0].loved_at, chrono::DateTime::from_timestamp(1700000000, 0));
+
+    let request = server.requests().remove(0);
+    assert!(request.contains("method=user.getLovedTracks"), "{request}");
+}
+
+#[tokio::test]
+async fn scrobble_corrections_and_ignores() {
+    let (endpoints, _) = serve_once(r##"{"scrobbles":{"scrobble":[
+        {"artist":{"corrected":"1","#text":"Beyoncé"},"album":{"corrected":"0"},"track":{"corrected":"0","#text":"Halo"},
+         "albumArtist":{"corrected":"0","#text":""},"ignoredMessage":{"code":"0","#text":""},"timestamp":"1700000000"},
+        {"artist":{"corrected":"0","#text":"Artist"},"album":{"corrected":"0","#text":""},"track":{"corrected":"0","#text":"Track"},
+         "albumArtist":{"corrected":"0","#text":""},"ignoredMessage":{"code":"9","#text":"Something new"},"timestamp":"1700000000"}
+    ],"@attr":{"ignored":1,"accepted":1}}}"##).await;
+
+    let response = client(endpoints).scrobble(&[]).await.unwrap();
+    let accepted = response.results[0].as_ref().unwrap();
+    assert!(accepted.artist.corrected);
+    assert_eq!(accepted.artist.value, "Beyoncé");
+    assert_eq!(accepted.album, None);
+    assert_eq!(accepted.timestamp, Some(1700000000));
+    assert_eq!(response.results[1].as_ref().unwrap_err(), &lastfm::scrobble::response::ScrobbleError::Unknown(9));
+}
+
+#[tokio::test]
+async fn ignored_now_playing() {
+    let (endpoints, _) = serve_once(r##"{"nowplaying":{
+        "artist":{"corrected":"0","#text":"Artist"},"track":{"corrected":"0","#text":"Track"},
+        "albumArtist":{"corrected":"0","#text":""},"ignoredMessage":{"code":"1","#text":"Artist was ignored"}
+    }}"##).await;
+    let error = client(endpoints).set_now_listening(&HEARD).await.unwrap_err();
+    assert!(matches!(error, lastfm::Error::Ignored(lastfm::scrobble::response::ScrobbleError::BadArtist)), "{error:?}");
+    assert!(!error.is_retryable());
+}
+
+#[tokio::test]
+async fn service_errors_are_typed() {
+    let (endpoints, _) = serve_once_with("503 Service Unavailable", "text/html", "<html><body>503 Service Unavailable</body></html>").await;
+    let error = client(endpoints).scrobble(&[]).await.unwrap_err();
+    assert!(matches!(error, lastfm::Error::Http(status) if status == 503), "{error:?}");
+    assert!(error.is_retryable());
+
+    let (endpoints, _) = serve_once_with("503 Service Unavailable", "application/json", r#"{"error":11,"message":"Service Offline"}"#).await;
+    let error = client(endpoints).set_now_listening(&HEARD).await.unwrap_err();
+    assert!(matches!(error, lastfm::Error::General(lastfm::GeneralError::ServiceOffline)), "{error:?}");
+    assert!(error.is_retryable());
+
+    let (endpoints, _) = serve_once(r#"{"error":9,"message":"Invalid session key"}"#).await;
+    let error = client(endpoints).love("Artist", "Track").await.unwrap_err();
+    assert!(matches!(error, lastfm::Error::General(lastfm::GeneralError::InvalidSessionKey)), "{error:?}");
+    assert!(!error.is_retryable());
+}
+
+#[tokio::test]
+async fn track_info() {
+    let (endpoints, server) = serve_once(r##"{"track":{
+        "name":"Believe","mbid":"","url":"https://www.last.fm/music/Cher/_/Believe","duration":"240000",
+        "streamable":{"#text":"0","fulltrack":"0"},"listeners":"1234","playcount":"5678",
+        "artist":{"name":"Cher","mbid":"bfcc6d75-a6a5-4bc6-8282-47aec8531818","url":"https://www.last.fm/music/Cher"},
+        "album":{"artist":"Cher","title":"Believe","mbid":"","url":"","image":[
+            {"#text":"https://lastfm.freetls.fastly.net/i/u/34s/abc.png","size":"small"},
+            {"#text":"https://lastfm.freetls.fastly.net/i/u/300x300/abc.png","size":"extralarge"},
+            {"#text":"","size":"mega"}
+        ],"@attr":{"position":"1"}},
+        "userplaycount":"3","userloved":"1",
+        "toptags":{"tag":[{"name":"pop","url":""},{"name":"dance","url":""}]}
+    }}"##).await;
+
+    let info = client(endpoints).get_track_info("cher", "believe", lastfm::InfoQuery { autocorrect: true, username: Some("someone") }).await.unwrap();
+    assert_eq!(info.artist, "Cher");
+    assert_eq!(info.mbid, None);
+    assert!(info.artist_mbid.is_some());
+    assert_eq!(info.duration, Some(std::time::Duration::from_secs(240)));
+    assert_eq!(info.user_loved, Some(true));
+    assert_eq!(info.tags, ["pop", "dance"]);
+    let album = info.album.unwrap();
+    assert_eq!(lastfm::image::largest(&album.images), Some("https://lastfm.freetls.fastly.net/i/u/300x300/abc.png"));
+
+    let request = server.requests().remove(0);
+    assert!(request.contains("method=track.getInfo"), "{request}");
+    assert!(request.contains("autocorrect=1"), "{request}");
+    assert!(!request.contains("api_sig="), "{request}");
+}
+
+#[tokio::test]
+async fn artist_info_without_images() {
+    let (endpoints, _) = serve_once(r##"{"artist":{
+        "name":"Cher","mbid":"bfcc6d75-a6a5-4bc6-8282-47aec8531818","url":"https://www.last.fm/music/Cher",
+        "image":[{"#text":"https://lastfm.freetls.fastly.net/i/u/300x300/2a96cbd8b46e442fc41c2b86b821562f.png","size":"extralarge"}],
+        "stats":{"listeners":"100","playcount":"200"},"tags":"",
+        "bio":{"summary":"Cher is a singer."}
+    }}"##).await;
+
+    let info = client(endpoints).get_artist_info("Cher", Default::default()).await.unwrap();
+    assert_eq!(lastfm::image::largest(&info.images), None);
+    assert!(info.tags.is_empty());
+    assert_eq!(info.user_play_count, None);
+    assert_eq!(info.summary.as_deref(), Some("Cher is a singer."));
+}
+
+#[tokio::test]
+async fn album_info() {
+    let (endpoints, _) = serve_once(r##"{"album":{
+        "name":"Believe","artist":"Cher","mbid":"","url":"https://www.last.fm/music/Cher/Believe",
+        "image":[{"#text":"https://lastfm.freetls.fastly.net/i/u/174s/abc.png","size":"large"}],
+        "listeners":"100","playcount":200,"tags":{"tag":{"name":"pop","url":""}}
+    }}"##).await;
+
+    let info = client(endpoints).get_album_info("Cher", "Believe", Default::default()).await.unwrap();
+    assert_eq!(info.play_count, 200);
+    assert_eq!(info.tags, ["pop"]);
+    assert_eq!(lastfm::image::largest(&info.images), Some("https://lastfm.freetls.fastly.net/i/u/174s/abc.png"));
+}
+
+#[tokio::test]
+async fn track_corrections() {
+    let (endpoints, _) = serve_once(r##"{"corrections":{"correction":{
+        "track":{"name":"Halo","mbid":"","url":"","artist":{"name":"Beyoncé","mbid":"","url":""}},
+        "@attr":{"index":"0","artistcorrected":"1","trackcorrected":"0"}
+    }}}"##).await;
+    let correction = client(endpoints).get_track_correction("Beyonce", "Halo").await.unwrap().unwrap();
+    assert_eq!(correction.artist, "Beyoncé");
+    assert!(correction.artist_corrected && !correction.track_corrected);
+
+    let (endpoints, _) = serve_once(r#"{"corrections":"\n            "}"#).await;
+    assert_eq!(client(endpoints).get_track_correction("Beyoncé", "Halo").await.unwrap(), None);
+}
@@ -0,0 +1,381 @@
+//! Fuzzy matching of track metadata against the results of searching external services (iTunes, MusicBrainz, etc.),
+//! which rarely agree exactly on titles; e.g. `Here Comes the Sun` is `Here Comes the Sun (Remastered 2009)` on one
+//! and `Here Comes The Sun - Remastered 2009` on another.
+
+use unicode_normalization::UnicodeNormalization;
+
+/// What's known about a track, either the one being looked up or a search result.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Metadata<'a> {
+    pub title: &'a str,
+    pub artist: Option<&'a str>,
+    pub album: Option<&'a str>,
+    /// In seconds.
+    pub duration: Option<f32>,
+}
+
+/// Words which, when in parentheses or brackets (or after a dash), describe a release of a song rather than the song itself.
+/// Written as they are after [`normalize`], since they're matched against whole words.
+const DECORATIONS: &[&str] = &[
+    "remaster", "remastered", "edition", "deluxe", "mono", "stereo", "bonus track", "explicit",
+    "album version", "single version", "original version", "radio edit", "anniversary",
+];
+/// Words which introduce the featured artists of a song.
+/// `with` isn't one of them, since it's as likely to be part of the title, e.g. `(With Love)`.
+const FEATURING: &[&str] = &["feat", "ft", "featuring"];
+/// Separators between the artists of a credit, used unless others are configured.
+pub const DEFAULT_ARTIST_SEPARATORS: &[&str] = &[" & ", ", ", " feat. ", " feat ", " ft. ", " featuring ", " x "];
+/// Artists whose names contain a separator, but which are a single artist.
+pub const DEFAULT_ARTIST_EXCEPTIONS: &[&str] = &[
+    "Simon & Garfunkel", "Earth, Wind & Fire", "Hall & Oates", "Mumford & Sons",
+    "Crosby, Stills, Nash & Young", "Emerson, Lake & Palmer", "Tyler, the Creator",
+];
+/// Remaster notes, which are removed from track names before they're submitted.
+pub const DEFAULT_TITLE_PATTERNS: &[&str] = &[
+    r"(?i)\s*[(\[][^)\]]*\bremaster(?:ed)?\b[^)\]]*[)\]]",
+    r"(?i)\s+-\s+(?:\d{4}\s+)?(?:digital(?:ly)?\s+)?remaster(?:ed)?\b.*$",
+];
+/// Apple's release type suffixes and edition notes, which are removed from album names before they're submitted.
+pub const DEFAULT_ALBUM_PATTERNS: &[&str] = &[
+    r" - (?:Single|EP)$",
+    r"(?i)\s*[(\[][^)\]]*\b(?:deluxe|expanded|remaster(?:ed)?|anniversary)\b[^)\]]*[)\]]",
+];
+
+/// Only whole words count, so that e.g. `(Monolith)` isn't taken for a mono release.
+fn is_decoration(text: &str) -> bool {
+    let text = format!(" {} ", normalize(text));
+    FEATURING.iter().any(|word| text.starts_with(&format!(" {word} "))) ||
+        DECORATIONS.iter().any(|words| text.contains(&format!(" {words} ")))
+}
+
+/// Lowercases, applies compatibility normalization, and drops punctuation, so that only letters, digits and single spaces are left.
+pub fn normalize(text: &str) -> String {
+    let text = text.nfkc().collect::<String>().to_lowercase().replace('&', " and ");
+    let mut out = String::with_capacity(text.len());
+    for word in text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()) {
+        if !out.is_empty() { out.push(' ') }
+        out.push_str(word);
+    }
+    out
+}
+
+/// Removes the ` - Single` and ` - EP` suffixes Apple adds to album names.
+pub fn strip_release_suffix(mut album: &str) -> &str {
+    for suffix in [" - Single", " - EP"] {
+        if let Some(stripped) = album.strip_suffix(suffix) { album = stripped }
+    }
+    album
+}
+
+/// Removes parentheticals, bracketed text and dash suffixes which describe the release (remasters, editions, featured artists, etc.).
+pub fn strip_decorations(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(['(', '[']) {
+        let close = if rest[start..].starts_with('(') { ')' } else { ']' };
+        let Some(length) = rest[start + 1..].find(close) else { break };
+        let end = start + 1 + length;
+        out.push_str(&rest[..start]);
+        if !is_decoration(&rest[start + 1..end]) {
+            out.push_str(&rest[start..=end]);
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+
+    if let Some((head, tail)) = out.rsplit_once(" - ") {
+        if is_decoration(tail) { out.truncate(head.len()) }
+    }
+    let lower = out.to_lowercase();
+    if let Some(index) = [" feat. ", " ft. ", " featuring "].iter().filter_map(|prefix| lower.find(prefix)).min() {
+        out.truncate(index);
+    }
+    out.trim().to_owned()
+}
+
+/// The first artist credited, e.g. `Queen` for `Queen & David Bowie`; see [`split_artists`].
+pub fn primary_artist<'a>(artist: &'a str, separators: &[impl AsRef<str>], exceptions: &[impl AsRef<str>]) -> &'a str {
+    split_artists(artist, separators, exceptions).first().copied().unwrap_or(artist.trim())
+}
+
+/// Splits a credit into the artists it names, at any of the separators (matched case-insensitively), e.g.
+/// `["Queen", "David Bowie"]` for `Queen & David Bowie`. Separators inside one of the exceptions, such as the
+/// `&` of `Simon & Garfunkel`, aren't split at.
+pub fn split_artists<'a>(artist: &'a str, separators: &[impl AsRef<str>], exceptions: &[impl AsRef<str>]) -> Vec<&'a str> {
+    // ASCII lowercasing keeps byte indices the same
+    let lower = artist.to_ascii_lowercase();
+    let protected = exceptions.iter()
+        .map(|exception| exception.as_ref().to_ascii_lowercase())
+        .filter(|exception| !exception.is_empty())
+        .flat_map(|exception| lower.match_indices(&exception).map(|(start, found)| start..start + found.len()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let separators = separators.iter()
+        .map(|separator| separator.as_ref().to_ascii_lowercase())
+        .filter(|separator| !separator.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut artists = Vec::new();
+    let mut start = 0;
+    loop {
+        let next = separators.iter()
+            .filter_map(|separator| {
+                lower[start..].match_indices(separator.as_str())
+                    .map(|(index, _)| (start + index, separator.len()))
+                    .find(|&(index, length)| !protected.iter().any(|range| index < range.end && range.start < index + length))
+            })
+            // the longest separator wins when several start at the same place (e.g. ` feat. ` over ` feat `)
+            .min_by_key(|&(index, length)| (index, core::cmp::Reverse(length)));
+        let Some((index, length)) = next else { break };
+        artists.push(artist[start..index].trim());
+        start = index + length;
+    }
+    artists.push(artist[start..].trim());
+    artists.retain(|artist| !artist.is_empty());
+    artists
+}
+
+/// Removes every match of the patterns from the name, unless that would leave nothing of it.
+pub fn remove_patterns<'a>(name: &str, patterns: impl IntoIterator<Item = &'a regex::Regex>) -> String {
+    let mut cleaned = name.to_owned();
+    for pattern in patterns {
+        cleaned = pattern.replace_all(&cleaned, "").into_owned();
+    }
+    let cleaned = cleaned.trim();
+    if cleaned.is_empty() { name.to_owned() } else { cleaned.to_owned() }
+}
+
+/// The Levenshtein distance between two strings, in characters.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// From zero (nothing in common) to one (identical), based on the edit distance relative to the longer string.
+pub fn similarity(a: &str, b: &str) -> f32 {
+    let length = a.chars().count().max(b.chars().count());
+    if length == 0 { return 1. }
+    1. - levenshtein(a, b) as f32 / length as f32
+}
+
+fn title_key(title: &str) -> String {
+    normalize(&strip_decorations(title))
+}
+fn album_key(album: &str) -> String {
+    normalize(&strip_decorations(strip_release_suffix(album)))
+}
+fn default_primary_artist(artist: &str) -> &str {
+    primary_artist(artist, DEFAULT_ARTIST_SEPARATORS, DEFAULT_ARTIST_EXCEPTIONS)
+}
+fn artist_similarity(a: &str, b: &str) -> f32 {
+    similarity(&normalize(a), &normalize(b))
+        .max(similarity(&normalize(default_primary_artist(a)), &normalize(default_primary_artist(b))))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matcher {
+    /// The lowest score a candidate can have and still be considered a match.
+    pub threshold: f32,
+    /// How far apart durations can be, in seconds, before the score is penalized.
+    pub duration_tolerance: f32,
+}
+impl Default for Matcher {
+    fn default() -> Self {
+        Self { threshold: 0.8, duration_tolerance: 5. }
+    }
+}
+impl Matcher {
+    const TITLE_WEIGHT: f32 = 0.5;
+    const ARTIST_WEIGHT: f32 = 0.3;
+    const ALBUM_WEIGHT: f32 = 0.2;
+    /// How many seconds past the tolerance it takes for the duration penalty to reach its maximum.
+    const DURATION_FALLOFF: f32 = 30.;
+
+    /// From zero to one; the artist and album only count if both sides have them.
+    pub fn score(&self, query: &Metadata, candidate: &Metadata) -> f32 {
+        let mut total = Self::TITLE_WEIGHT * similarity(&title_key(query.title), &title_key(candidate.title));
+        let mut weights = Self::TITLE_WEIGHT;
+        if let (Some(a), Some(b)) = (query.artist, candidate.artist) {
+            total += Self::ARTIST_WEIGHT * artist_similarity(a, b);
+            weights += Self::ARTIST_WEIGHT;
+        }
+        if let (Some(a), Some(b)) = (query.album, candidate.album) {
+            total += Self::ALBUM_WEIGHT * similarity(&album_key(a), &album_key(b));
+            weights += Self::ALBUM_WEIGHT;
+        }
+        let mut score = total / weights;
+
+        if let (Some(a), Some(b)) = (query.duration, candidate.duration) {
+            let excess = (a - b).abs() - self.duration_tolerance;
+            if excess > 0. {
+                // at most halves the score, since durations differ between releases more often than titles do
+                score *= 1. - 0.5 * (excess / Self::DURATION_FALLOFF).min(1.);
+            }
+        }
+        score
+    }
+
+    /// The highest-scoring candidate and its score, if it reaches the threshold; earlier candidates win ties.
+    pub fn best_match<T>(&self, query: &Metadata, candidates: impl IntoIterator<Item = T>, metadata: impl Fn(&T) -> Metadata<'_>) -> Option<(T, f32)> {
+        self.best_by(candidates, |candidate| self.score(query, &metadata(candidate)))
+    }
+
+    /// Like [`Self::best_match`], for candidates which need to be scored some other way (e.g. the best of several titles).
+    pub fn best_by<T>(&self, candidates: impl IntoIterator<Item = T>, score: impl Fn(&T) -> f32) -> Option<(T, f32)> {
+        let mut best: Option<(T, f32)> = None;
+        for candidate in candidates {
+            let score = score(&candidate);
+            if score >= self.threshold && best.as_ref().is_none_or(|(_, best)| score > *best) {
+                best = Some((candidate, score));
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track<'a>(title: &'a str, artist: &'a str, album: &'a str, duration: f32) -> Metadata<'a> {
+        Metadata { title, artist: Some(artist), album: Some(album), duration: Some(duration) }
+    }
+
+    #[test]
+    fn strips_decorations() {
+        assert_eq!(strip_decorations("Here Comes the Sun (Remastered 2009)"), "Here Comes the Sun");
+        assert_eq!(strip_decorations("Under Pressure - Remastered 2011"), "Under Pressure");
+        assert_eq!(strip_decorations("Song [Deluxe Edition] (feat. Someone)"), "Song");
+        assert_eq!(strip_decorations("Song ft. Someone"), "Song");
+        assert_eq!(strip_decorations("Song (Live)"), "Song (Live)");
+        assert_eq!(strip_decorations("Song - Interlude"), "Song - Interlude");
+        assert_eq!(strip_decorations("Unclosed (paren"), "Unclosed (paren");
+    }
+
+    #[test]
+    fn matches_whole_decoration_words() {
+        assert_eq!(strip_decorations("Song (Mono)"), "Song");
+        assert_eq!(strip_decorations("Song (2011 Remaster)"), "Song");
+        assert_eq!(strip_decorations("Song (Monolith)"), "Song (Monolith)");
+        assert_eq!(strip_decorations("Song (With Love)"), "Song (With Love)");
+        assert_eq!(strip_decorations("Song (Stereophonic)"), "Song (Stereophonic)");
+        assert_eq!(strip_decorations("Song - Left Feet"), "Song - Left Feet");
+        assert_eq!(strip_decorations("Song (Ft. Someone)"), "Song");
+    }
+
+    #[test]
+    fn strips_release_suffix() {
+        assert_eq!(strip_release_suffix("Album - Single"), "Album");
+        assert_eq!(strip_release_suffix("Album - EP"), "Album");
+        assert_eq!(strip_release_suffix("Album"), "Album");
+    }
+
+    #[test]
+    fn normalizes() {
+        assert_eq!(normalize("  Simon & Garfunkel!  "), "simon and garfunkel");
+        assert_eq!(normalize("Ｆｕｌｌｗｉｄｔｈ"), "fullwidth");
+    }
+
+    #[test]
+    fn splits_artists() {
+        let separators = [" & ", ", ", " feat. ", " feat ", " x "];
+        let exceptions = ["Simon & Garfunkel", "Earth, Wind & Fire"];
+        assert_eq!(split_artists("Queen & David Bowie", &separators, &exceptions), ["Queen", "David Bowie"]);
+        assert_eq!(split_artists("A, B & C", &separators, &exceptions), ["A", "B", "C"]);
+        assert_eq!(split_artists("Someone FEAT. Someone Else", &separators, &exceptions), ["Someone", "Someone Else"]);
+        assert_eq!(split_artists("Simon & Garfunkel", &separators, &exceptions), ["Simon & Garfunkel"]);
+        assert_eq!(split_artists("Earth, Wind & Fire & The Emotions", &separators, &exceptions), ["Earth, Wind & Fire", "The Emotions"]);
+        assert_eq!(split_artists("Beyoncé x Someone", &separators, &exceptions), ["Beyoncé", "Someone"]);
+        assert_eq!(split_artists("Daft Punk", &separators, &exceptions), ["Daft Punk"]);
+    }
+
+    #[test]
+    fn default_artists() {
+        let primary = |artist| primary_artist(artist, DEFAULT_ARTIST_SEPARATORS, DEFAULT_ARTIST_EXCEPTIONS);
+        assert_eq!(primary("Queen & David Bowie"), "Queen");
+        assert_eq!(primary("Daft Punk"), "Daft Punk");
+        assert_eq!(primary("Simon & Garfunkel"), "Simon & Garfunkel");
+        assert_eq!(primary("Tyler, the Creator"), "Tyler, the Creator");
+        assert_eq!(primary("Tyler, The Creator feat. Kali Uchis"), "Tyler, The Creator");
+        assert_eq!(primary("Earth, Wind & Fire"), "Earth, Wind & Fire");
+        assert_eq!(split_artists("Earth, Wind & Fire, The Emotions", DEFAULT_ARTIST_SEPARATORS, DEFAULT_ARTIST_EXCEPTIONS), ["Earth, Wind & Fire", "The Emotions"]);
+    }
+
+    fn patterns(patterns: &[&str]) -> Vec<regex::Regex> {
+        patterns.iter().map(|pattern| regex::Regex::new(pattern).unwrap()).collect()
+    }
+
+    #[test]
+    fn default_title_patterns() {
+        let patterns = patterns(DEFAULT_TITLE_PATTERNS);
+        assert_eq!(remove_patterns("Under Pressure (Remastered 2011)", &patterns), "Under Pressure");
+        assert_eq!(remove_patterns("Here Comes the Sun - 2009 Digital Remaster", &patterns), "Here Comes the Sun");
+        assert_eq!(remove_patterns("Song [2015 Remaster]", &patterns), "Song");
+        assert_eq!(remove_patterns("Song - Remastered", &patterns), "Song");
+        assert_eq!(remove_patterns("Song (Live)", &patterns), "Song (Live)");
+        assert_eq!(remove_patterns("Remastered", &patterns), "Remastered");
+        assert_eq!(remove_patterns("(Remastered)", &patterns), "(Remastered)");
+    }
+
+    #[test]
+    fn default_album_patterns() {
+        let patterns = patterns(DEFAULT_ALBUM_PATTERNS);
+        assert_eq!(remove_patterns("Help! - Single", &patterns), "Help!");
+        assert_eq!(remove_patterns("Some EP - EP", &patterns), "Some EP");
+        assert_eq!(remove_patterns("Rumours (Super Deluxe)", &patterns), "Rumours");
+        assert_eq!(remove_patterns("Abbey Road (50th Anniversary Edition)", &patterns), "Abbey Road");
+        assert_eq!(remove_patterns("Hot Space (2011 Remaster)", &patterns), "Hot Space");
+        assert_eq!(remove_patterns("Live at Wembley (Live)", &patterns), "Live at Wembley (Live)");
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(similarity("", ""), 1.);
+        assert_eq!(similarity("abc", "abc"), 1.);
+    }
+
+    #[test]
+    fn matches_remasters() {
+        let matcher = Matcher::default();
+        let query = track("Under Pressure", "Queen", "Hot Space", 248.);
+        let candidate = track("Under Pressure (Remastered 2011)", "Queen & David Bowie", "Hot Space (Deluxe Edition)", 247.);
+        assert!(matcher.score(&query, &candidate) > 0.95);
+    }
+
+    #[test]
+    fn penalizes_durations() {
+        let matcher = Matcher::default();
+        let query = track("Song", "Artist", "Album", 200.);
+        let close = matcher.score(&query, &track("Song", "Artist", "Album", 203.));
+        let far = matcher.score(&query, &track("Song", "Artist", "Album", 400.));
+        assert_eq!(close, 1.);
+        assert_eq!(far, 0.5);
+    }
+
+    #[test]
+    fn picks_best_match() {
+        let matcher = Matcher::default();
+        let query = track("Yesterday", "The Beatles", "Help! - Single", 125.);
+        let candidates = [
+            track("Yesterday Once More", "Carpenters", "Now & Then", 230.),
+            track("Yesterday (Remastered 2009)", "The Beatles", "Help!", 126.),
+            track("Yesterday", "The Beatles", "Anthology 2", 170.),
+        ];
+        let (best, _) = matcher.best_match(&query, candidates.iter(), |candidate| **candidate).unwrap();
+        assert_eq!(best.album, Some("Help!"));
+
+        let unrelated = [track("Something Else", "Someone", "Elsewhere", 125.)];
+        assert!(matcher.best_match(&query, unrelated.iter(), |candidate| **candidate).is_none());
+    }
+}
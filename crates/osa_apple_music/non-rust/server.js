@@ -892,6 +892,48 @@ server.listen((connection, [data]) => {
             $.free(json[0]);
             break;
         }
+        case "library play history": {
+            // Properties are fetched in bulk per-column, since fetching them per-track is painfully slow.
+            const tracks = music.libraryPlaylists[0].tracks;
+            const columns = {
+                persistentID: tracks.persistentID(),
+                name: tracks.name(),
+                artist: tracks.artist(),
+                album: tracks.album(),
+                albumArtist: tracks.albumArtist(),
+                duration: tracks.duration(),
+                playedCount: tracks.playedCount(),
+                playedDate: tracks.playedDate(),
+                unplayed: tracks.unplayed(),
+            };
+            const rows = columns.persistentID.map((_, i) => Object.fromEntries(
+                Object.entries(columns).map(([key, values]) => [key, values[i] ?? null])
+            ));
+            const json = cstr.sized(JSON.stringify(rows));
+            connection.send(json);
+            $.free(json[0]);
+            break;
+        }
+        case "library feedback": {
+            // Only tracks that are favorited or disliked are sent, since that's what's of interest and most of a library is neither.
+            const tracks = music.libraryPlaylists[0].tracks;
+            const columns = {
+                persistentID: tracks.persistentID(),
+                name: tracks.name(),
+                artist: tracks.artist(),
+                album: tracks.album(),
+                duration: tracks.duration(),
+                favorited: tracks.favorited(),
+                disliked: tracks.disliked(),
+            };
+            const rows = columns.persistentID.flatMap((_, i) => (columns.favorited[i] || columns.disliked[i]) ? [Object.fromEntries(
+                Object.entries(columns).map(([key, values]) => [key, values[i] ?? null])
+            )] : []);
+            const json = cstr.sized(JSON.stringify(rows));
+            connection.send(json);
+            $.free(json[0]);
+            break;
+        }
         default: {
             connection.send(ERR_UNKNOWN_COMMAND);
         }
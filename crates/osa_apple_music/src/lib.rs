@@ -92,6 +92,16 @@ impl Session {
     pub async fn now_playing(&mut self) -> Result<Option<crate::Track>, error::SessionEvaluationError> {
         self.exec("current track").await
     }
+
+    /// Retrieves the play count and last played date of every track in the library.
+    pub async fn library_play_history(&mut self) -> Result<Vec<track::LibraryTrackPlays>, error::SessionEvaluationError> {
+        self.exec("library play history").await
+    }
+
+    /// Retrieves every track in the library which is favorited or disliked.
+    pub async fn library_feedback(&mut self) -> Result<Vec<track::LibraryTrackFeedback>, error::SessionEvaluationError> {
+        self.exec("library feedback").await
+    }
 }
 impl Drop for Session {
     fn drop(&mut self) {
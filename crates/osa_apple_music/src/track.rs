@@ -146,15 +146,15 @@ pub struct EpisodeDetails {
 pub struct PlayedInfo {
     /// Number of times this track has been played.
     #[serde(rename = "playedCount")]
-    times: u32,
+    pub times: u32,
 
     /// The date and time this track was last played.
     #[serde(rename = "playedDate")]
-    last: Option<Time>,
+    pub last: Option<Time>,
 
     /// Whether this track has never been played before.
     #[serde(rename = "unplayed")]
-    never: bool
+    pub never: bool
 }
 
 #[derive(Debug, Deserialize)]
@@ -172,11 +172,11 @@ pub struct SkippedInfo {
 pub struct MovementInfo {
     /// The name of the movement.
     #[serde(rename = "movement")]
-    name: String,
+    pub name: String,
 
     /// The index of this movement in the work.
     #[serde(rename = "movementNumber")]
-    index: u16,
+    pub index: u16,
 }
 
 serde_with::serde_conv!(
@@ -582,6 +582,68 @@ impl Track {
     }
 }
 
+/// The play history of a track in the library, as retrieved in bulk by [`Session::library_play_history`](crate::Session::library_play_history).
+#[serde_as]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryTrackPlays {
+    /// The library's persistent ID for the track.
+    #[serde(rename = "persistentID")]
+    pub persistent_id: String,
+
+    /// The name of the track.
+    pub name: String,
+
+    /// The artist of the track.
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub artist: Option<String>,
+
+    /// The name of the album that this track is in.
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub album: Option<String>,
+
+    /// The artist(s) of the album for this track.
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub album_artist: Option<String>,
+
+    /// The length of the track, in seconds.
+    pub duration: Option<f32>,
+
+    /// The details on how many times and when this track was last played.
+    #[serde(flatten)]
+    pub played: PlayedInfo,
+}
+
+/// Whether a track in the library is favorited or disliked, as retrieved in bulk by [`Session::library_feedback`](crate::Session::library_feedback).
+#[serde_as]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryTrackFeedback {
+    /// The library's persistent ID for the track.
+    #[serde(rename = "persistentID")]
+    pub persistent_id: String,
+
+    /// The name of the track.
+    pub name: String,
+
+    /// The artist of the track.
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub artist: Option<String>,
+
+    /// The name of the album that this track is in.
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub album: Option<String>,
+
+    /// The length of the track, in seconds.
+    pub duration: Option<f32>,
+
+    /// Whether this track is favorited.
+    pub favorited: bool,
+
+    /// Whether this track is disliked by the user.
+    pub disliked: bool,
+}
+
 #[serde_as]
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
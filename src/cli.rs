@@ -33,6 +33,34 @@ pub enum Command {
     Configure {
         #[command(subcommand)]
         action: ConfigurationAction
+    },
+    /// Work with listening history.
+    History {
+        #[command(subcommand)]
+        action: HistoryAction
+    },
+    /// Bring the state of the Apple Music library over to the enabled services.
+    Sync {
+        #[command(subcommand)]
+        action: SyncAction
+    },
+    /// View and override the corrections applied to names before they're submitted.
+    Corrections {
+        #[command(subcommand)]
+        action: CorrectionsAction
+    },
+    /// Summarize the locally recorded listening history.
+    #[command(args_conflicts_with_subcommands = true)]
+    Stats {
+        #[command(subcommand)]
+        action: Option<StatsAction>,
+
+        #[command(flatten)]
+        filter: HistoryFilterArgs,
+
+        /// The number of entries to show in each ranking.
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
     }
 }
 
@@ -73,5 +101,260 @@ pub enum DiscordConfigurationAction {
     /// Disable the Discord presence.
     Disable,
 
-    // TODO: A way of changing the way the presence appears.
+    /// Show how the configured presence template renders, using the currently playing track if there is one.
+    Preview,
+}
+
+#[derive(Subcommand)]
+pub enum HistoryAction {
+    /// Backfill plays that the Apple Music library already knows about into the enabled services.
+    Import {
+        /// Preview what would be submitted without submitting anything.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// The service(s) to import into. Defaults to every enabled service.
+        #[arg(long = "to", value_enum, value_name = "SERVICE")]
+        services: Vec<HistoryService>,
+    },
+
+    /// Export the locally recorded listening history.
+    Export {
+        /// The format to export in.
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Jsonl)]
+        format: ExportFormat,
+
+        /// The file to write to; defaults to standard output.
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<std::path::PathBuf>,
+
+        #[command(flatten)]
+        filter: HistoryFilterArgs,
+
+        /// Also include plays that were skipped before they counted as a listen.
+        #[arg(long)]
+        include_skips: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SyncAction {
+    /// Love favorited tracks and hate (or, on Last.fm, unlove) disliked tracks.
+    Feedback {
+        /// Preview what would be submitted without submitting anything.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// The service(s) to sync to. Defaults to every enabled service.
+        #[arg(long = "to", value_enum, value_name = "SERVICE")]
+        services: Vec<HistoryService>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CorrectionsAction {
+    /// List the remembered corrections.
+    List {
+        /// Only list corrections of this kind of name.
+        #[arg(long, value_enum)]
+        field: Option<crate::corrections::Field>,
+    },
+
+    /// Always submit a name as something else. Setting a name to itself keeps it from being corrected.
+    Set {
+        #[arg(value_enum)]
+        field: crate::corrections::Field,
+        from: String,
+        to: String,
+
+        /// The artist the track or album is by (after correction); required for tracks and albums.
+        #[arg(long)]
+        artist: Option<String>,
+    },
+
+    /// Forget a correction, whether it was learned or set.
+    Remove {
+        #[arg(value_enum)]
+        field: crate::corrections::Field,
+        from: String,
+
+        /// The artist the track or album is by (after correction); required for tracks and albums.
+        #[arg(long)]
+        artist: Option<String>,
+    },
+}
+
+#[derive(clap::Args)]
+pub struct HistoryFilterArgs {
+    /// Only include plays from this date onwards (`YYYY-MM-DD` in local time, or RFC 3339).
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Only include plays before this date (`YYYY-MM-DD` in local time, or RFC 3339).
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Only include plays by this artist (case-insensitive); can be repeated.
+    #[arg(long = "artist", value_name = "NAME")]
+    pub artists: Vec<String>,
+}
+impl HistoryFilterArgs {
+    pub fn to_filter(&self, include_skips: bool) -> crate::history::store::HistoryFilter {
+        crate::history::store::HistoryFilter {
+            since: self.since,
+            until: self.until,
+            artists: self.artists.clone(),
+            include_skips,
+        }
+    }
+}
+
+fn parse_date(value: &str) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(date.to_utc());
+    }
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("expected `YYYY-MM-DD` or an RFC 3339 timestamp, got `{value}`"))?;
+    date.and_time(chrono::NaiveTime::MIN)
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(|date| date.to_utc())
+        .ok_or_else(|| format!("`{value}` doesn't exist in the local time zone"))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// Comma-separated values, with a header row.
+    Csv,
+    /// A single JSON array.
+    Json,
+    /// One JSON object per line.
+    Jsonl,
+    /// A JSON array of listens, as accepted by ListenBrainz's "import listens" page; skips are never included.
+    #[value(name = "listenbrainz")]
+    ListenBrainz,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum HistoryService {
+    #[cfg(feature = "listenbrainz")]
+    #[value(name = "listenbrainz")]
+    ListenBrainz,
+    #[cfg(feature = "lastfm")]
+    #[value(name = "lastfm")]
+    LastFM,
+}
+
+#[derive(Subcommand)]
+pub enum StatsAction {
+    /// Render a recap of the past week or month.
+    Report {
+        /// The period the recap covers.
+        #[arg(long, value_enum, default_value_t = RecapPeriod::Week)]
+        period: RecapPeriod,
+
+        /// The end of the period; defaults to now (`YYYY-MM-DD` in local time, or RFC 3339).
+        #[arg(long, value_name = "DATE", value_parser = parse_date)]
+        ending: Option<chrono::DateTime<chrono::Utc>>,
+
+        /// Render the recap as an HTML page instead of text.
+        #[arg(long)]
+        html: bool,
+
+        /// The file to write to; defaults to standard output.
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<std::path::PathBuf>,
+
+        /// Only include plays by this artist (case-insensitive); can be repeated.
+        #[arg(long = "artist", value_name = "NAME")]
+        artists: Vec<String>,
+
+        /// The number of entries to show in each ranking.
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Show the statistics ListenBrainz has computed for the configured account, which include listens from other devices.
+    #[cfg(feature = "listenbrainz")]
+    #[clap(name = "listenbrainz")]
+    ListenBrainz {
+        /// The period the statistics cover.
+        #[arg(long, value_enum, default_value_t = ListenBrainzRange::AllTime)]
+        range: ListenBrainzRange,
+
+        /// The number of entries to show in each ranking.
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum RecapPeriod {
+    Week,
+    Month,
+}
+
+#[cfg(feature = "listenbrainz")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum ListenBrainzRange {
+    ThisWeek,
+    ThisMonth,
+    ThisYear,
+    /// The previous week.
+    Week,
+    /// The previous month.
+    Month,
+    /// The previous quarter.
+    Quarter,
+    /// The previous year.
+    Year,
+    HalfYearly,
+    AllTime,
+}
+#[cfg(feature = "listenbrainz")]
+impl From<ListenBrainzRange> for brainz::listen::v1::stats::StatsRange {
+    fn from(range: ListenBrainzRange) -> Self {
+        match range {
+            ListenBrainzRange::ThisWeek => Self::ThisWeek,
+            ListenBrainzRange::ThisMonth => Self::ThisMonth,
+            ListenBrainzRange::ThisYear => Self::ThisYear,
+            ListenBrainzRange::Week => Self::Week,
+            ListenBrainzRange::Month => Self::Month,
+            ListenBrainzRange::Quarter => Self::Quarter,
+            ListenBrainzRange::Year => Self::Year,
+            ListenBrainzRange::HalfYearly => Self::HalfYearly,
+            ListenBrainzRange::AllTime => Self::AllTime,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser as _;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(["am-osx-status"].iter().chain(args))
+    }
+
+    #[test]
+    fn stats_filters_belong_to_their_subcommand() {
+        let Command::Stats { action: None, filter, limit } = parse(&["stats", "--since", "2024-01-01", "--artist", "Queen", "-n", "5"]).unwrap().command else { panic!() };
+        assert!(filter.since.is_some());
+        assert_eq!(filter.artists, ["Queen"]);
+        assert_eq!(limit, 5);
+
+        let Command::Stats { action: Some(StatsAction::Report { artists, limit, .. }), .. } = parse(&["stats", "report", "--artist", "Queen", "-n", "3"]).unwrap().command else { panic!() };
+        assert_eq!(artists, ["Queen"]);
+        assert_eq!(limit, 3);
+    }
+
+    #[test]
+    fn stats_rejects_flags_its_subcommand_does_not_apply() {
+        assert!(parse(&["stats", "--since", "2024-01-01", "report"]).is_err());
+        assert!(parse(&["stats", "--artist", "Queen", "report"]).is_err());
+        assert!(parse(&["stats", "report", "--until", "2024-01-01"]).is_err());
+        #[cfg(feature = "listenbrainz")]
+        assert!(parse(&["stats", "listenbrainz", "--artist", "Queen"]).is_err());
+    }
 }
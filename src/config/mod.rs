@@ -30,7 +30,7 @@ impl<'a> ConfigRetrievalError<'a> {
     }
 }
 
-fn ret_true() -> bool {
+pub(crate) fn ret_true() -> bool {
     true
 }
 
@@ -41,6 +41,12 @@ pub struct Config<'a> {
     pub path: ConfigPathChoice<'a>,
     #[serde(default)]
     pub backends: ConfigurableBackends,
+    #[serde(default)]
+    pub artwork_host: crate::data_fetching::services::custom_artwork_host::Config,
+    #[serde(default)]
+    pub itunes_cache: crate::data_fetching::services::itunes::CacheConfig,
+    #[serde(default)]
+    pub normalization: crate::normalization::Config,
 
     #[serde(
         default             = "crate::service::ipc::socket_path::clone_default",
@@ -53,6 +59,9 @@ impl Default for Config<'_> {
         Self {
             path: Default::default(),
             backends: Default::default(),
+            artwork_host: Default::default(),
+            itunes_cache: Default::default(),
+            normalization: Default::default(),
             socket_path: crate::service::ipc::socket_path::clone_default(),
         }
     }
@@ -88,7 +97,9 @@ impl<'a> Config<'a> {
     }
 
     pub async fn edit_with_wizard(&mut self)  {
-        self.backends.discord = wizard::io::prompt_bool("Enable Discord Rich Presence?");
+        #[cfg(feature = "discord")]
+        { self.backends.discord.enabled = wizard::io::prompt_bool("Enable Discord Rich Presence?"); }
+        self.backends.local_history = wizard::io::prompt_bool("Keep a local record of listening history?");
         wizard::io::prompt_lastfm(&mut self.backends.lastfm).await;
         wizard::io::prompt_listenbrainz(&mut self.backends.listenbrainz).await;
     }
@@ -118,27 +129,18 @@ impl<'a> Config<'a> {
 }
 
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct ConfigurableBackends {
     #[cfg(feature = "discord")]
-    #[cfg_attr(feature = "discord", serde(default = "ret_true"))]
-    pub discord: bool,
+    #[cfg_attr(feature = "discord", serde(default))]
+    pub discord: crate::status_backend::discord::Config,
     #[cfg(feature = "lastfm")]
     #[cfg_attr(feature = "lastfm", serde(default))]
     pub lastfm: Option<crate::status_backend::lastfm::Config>,
     #[cfg(feature = "listenbrainz")]
     #[cfg_attr(feature = "listenbrainz", serde(default))]
-    pub listenbrainz: Option<crate::status_backend::listenbrainz::Config>
-}
-impl Default for ConfigurableBackends {
-    fn default() -> Self {
-        Self {
-            #[cfg(feature = "discord")]
-            discord: true,
-            #[cfg(feature = "lastfm")]
-            lastfm: None,
-            #[cfg(feature = "listenbrainz")]
-            listenbrainz: None,
-        }
-    }
+    pub listenbrainz: Option<crate::status_backend::listenbrainz::Config>,
+    /// Off unless turned on (e.g. by the wizard), since it keeps a full record of what's been listened to on disk.
+    #[serde(default)]
+    pub local_history: bool,
 }
@@ -95,10 +95,14 @@ pub mod io {
     use crate::status_backend::lastfm;
     pub async fn prompt_lastfm(config: &mut Option<lastfm::Config>)  {
         if prompt_bool("Enable last.fm Scrobbling?") {
-            if let Some(config) = config.as_mut() {
-                config.enabled = true;
-            } else {
-                *config = authorize_lastfm().await
+            match config.as_mut() {
+                // e.g. a Maloja server that was added to the configuration by hand
+                Some(config) if config.session_key.is_none() => {
+                    config.session_key = authorize_lastfm_session(&config.identity).await;
+                    config.enabled = config.session_key.is_some();
+                }
+                Some(config) => config.enabled = true,
+                None => *config = authorize_lastfm().await,
             }
         } else if let Some(config) = config.as_mut() {
             config.enabled = false;
@@ -107,28 +111,35 @@ pub mod io {
 
     pub async fn authorize_lastfm() -> Option<lastfm::Config> {
         let client = &crate::status_backend::lastfm::DEFAULT_CLIENT_IDENTITY;
+        Some(crate::status_backend::lastfm::Config {
+            enabled: true,
+            identity: (*client).clone(),
+            session_key: Some(authorize_lastfm_session(client).await?),
+            artists: crate::normalization::ArtistCredit::Primary,
+        })
+    }
+
+    async fn authorize_lastfm_session(client: &::lastfm::auth::ClientIdentity) -> Option<::lastfm::auth::SessionKey> {
+        if client.endpoints.authorization.is_none() {
+            return authorize_lastfm_with_credentials(client).await;
+        }
         let auth = match client.generate_authorization_token().await {
             Ok(auth) => auth,
             Err(err) => {
                 use ::lastfm::auth::AuthorizationTokenGenerationError;
                 match err {
-                    AuthorizationTokenGenerationError::NetworkError(failure) => {
-                        eprintln!("Network failure: {}", failure.without_url());
-                        eprintln!("Continuing with last.fm support disabled. This can be reconfigured later.");
-                        return None;
-                    }
+                    AuthorizationTokenGenerationError::Request(::lastfm::Error::NetworkFailure(failure)) => eprintln!("Network failure: {}", failure.without_url()),
+                    AuthorizationTokenGenerationError::Request(error) => eprintln!("Could not start authorization: {error}"),
                 }
+                eprintln!("Continuing with last.fm support disabled. This can be reconfigured later.");
+                return None;
             }
         };
-        let auth_url = auth.generate_authorization_url(client);
+        let auth_url = auth.generate_authorization_url(client)?;
         println!("Continue after authorizing the application: {}", auth_url);
         if prompt_bool("Have you authorized the application?") {
             match auth.generate_session_key(client).await {
-                Ok(key) => Some(crate::status_backend::lastfm::Config {
-                    enabled: true,
-                    identity: (*client).clone(),
-                    session_key: Some(key)
-                }),
+                Ok(key) => Some(key),
                 Err(error) => {
                     ferror!("couldn't create session key: {}", error);
                 }
@@ -136,6 +147,20 @@ pub mod io {
         } else { None }
     }
 
+    /// For services without an authorization page, such as Maloja, which take an API key in place of the password.
+    async fn authorize_lastfm_with_credentials(client: &::lastfm::auth::ClientIdentity) -> Option<::lastfm::auth::SessionKey> {
+        let username = prompt("Enter your username:", 32);
+        let password = prompt(r#"Enter your API key (for Maloja) or password, or type "cancel":"#, 64);
+        let (username, password) = (username.trim(), password.trim());
+        if password == "cancel" { return None }
+        match (::lastfm::auth::AccountCredentials { username, password }).generate_session_key(client).await {
+            Ok(key) => Some(key),
+            Err(error) => {
+                ferror!("couldn't create session key: {}", error);
+            }
+        }
+    }
+
     use crate::status_backend::listenbrainz;
     pub async fn prompt_listenbrainz(config: &mut Option<listenbrainz::Config>) {
         if prompt_bool("Enable ListenBrainz synchronization?") {
@@ -161,6 +186,8 @@ pub mod io {
                         enabled: true,
                         program_info: crate::status_backend::listenbrainz::DEFAULT_PROGRAM_INFO.clone(),
                         user_token: Some(token),
+                        apply_corrections: false,
+                        artists: crate::normalization::ArtistCredit::All,
                     })
                 },
                 Err(error) => {
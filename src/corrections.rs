@@ -0,0 +1,381 @@
+//! Names that Last.fm has corrected (or that have been corrected by hand), remembered so that the corrected
+//! spelling is submitted from the start, and to other services, rather than only showing up on Last.fm.
+
+use std::{path::PathBuf, sync::LazyLock, time::SystemTime};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::util::{ferror, HOME};
+
+pub static DEFAULT_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+    HOME.join("Library/Application Support/am-osx-status/corrections.json")
+});
+
+/// Shared by every backend, and loaded from disk on first use.
+static STORE: LazyLock<Mutex<CorrectionStore>> = LazyLock::new(|| Mutex::new(CorrectionStore::load(DEFAULT_PATH.clone())));
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    Artist,
+    Track,
+    Album,
+    AlbumArtist,
+}
+impl Field {
+    /// Track and album names are only corrected for the artist they were corrected for,
+    /// since a title can be a misspelling for one artist and correct for another.
+    pub const fn is_per_artist(self) -> bool {
+        matches!(self, Self::Track | Self::Album)
+    }
+}
+impl core::fmt::Display for Field {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::Artist => "artist",
+            Self::Track => "track",
+            Self::Album => "album",
+            Self::AlbumArtist => "album artist",
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// Learned from what Last.fm recorded for a submission.
+    LastFm,
+    /// Set from the command line; never replaced by a learned correction.
+    User,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Correction {
+    pub field: Field,
+    /// For tracks and albums, the (corrected) name of the artist they're by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    pub from: String,
+    pub to: String,
+    pub source: Source,
+    pub updated_at: DateTime<Utc>,
+}
+impl Correction {
+    fn is_for(&self, field: Field, artist: Option<&str>, from: &str) -> bool {
+        self.field == field && self.from == from && (!field.is_per_artist() || self.artist.as_deref() == artist)
+    }
+}
+
+/// The names of a track as they're about to be submitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackNames {
+    pub artist: String,
+    pub track: String,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct CorrectionStore {
+    path: PathBuf,
+    /// When the file was last read or written, so changes made from the command line are picked up by the service.
+    modified: Option<SystemTime>,
+    entries: Vec<Correction>,
+}
+impl Default for CorrectionStore {
+    fn default() -> Self {
+        Self::load(DEFAULT_PATH.clone())
+    }
+}
+impl CorrectionStore {
+    pub fn load(path: PathBuf) -> Self {
+        let mut store = Self { path, modified: None, entries: vec![] };
+        store.refresh();
+        store
+    }
+
+    fn modified_on_disk(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok()
+    }
+
+    /// Re-reads the file if it changed since it was last read.
+    fn refresh(&mut self) {
+        let modified = self.modified_on_disk();
+        if modified.is_some() && modified == self.modified { return }
+        self.modified = modified;
+        self.entries = match std::fs::read(&self.path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|error| {
+                tracing::warn!(?error, path = ?self.path, "corrections file is malformed; ignoring it");
+                vec![]
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(error) => {
+                tracing::warn!(?error, path = ?self.path, "could not read corrections");
+                vec![]
+            }
+        };
+    }
+
+    pub async fn save(&mut self) -> Result<(), std::io::Error> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let data = serde_json::to_vec_pretty(&self.entries).expect("corrections are always serializable");
+        tokio::fs::write(&self.path, data).await?;
+        self.modified = self.modified_on_disk();
+        Ok(())
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    pub fn entries(&self) -> &[Correction] {
+        &self.entries
+    }
+
+    pub fn get(&self, field: Field, artist: Option<&str>, from: &str) -> Option<&Correction> {
+        self.entries.iter().find(|entry| entry.is_for(field, artist, from))
+    }
+
+    fn correct(&self, field: Field, artist: Option<&str>, name: &mut String) {
+        if let Some(correction) = self.get(field, artist, name) {
+            *name = correction.to.clone();
+        }
+    }
+
+    /// The artist is corrected first, since track and album corrections are looked up by the corrected artist.
+    pub fn apply(&self, names: &mut TrackNames) {
+        self.correct(Field::Artist, None, &mut names.artist);
+        self.correct(Field::Track, Some(&names.artist), &mut names.track);
+        if let Some(album) = names.album.as_mut() {
+            self.correct(Field::Album, Some(&names.artist), album);
+        }
+        if let Some(album_artist) = names.album_artist.as_mut() {
+            self.correct(Field::AlbumArtist, None, album_artist);
+        }
+    }
+
+    /// Replaces any correction of the same name, unless it was set by the user and this one wasn't.
+    /// Returns whether anything changed.
+    pub fn insert(&mut self, correction: Correction) -> bool {
+        let artist = correction.artist.as_deref();
+        match self.entries.iter_mut().find(|entry| entry.is_for(correction.field, artist, &correction.from)) {
+            Some(existing) if existing.source == Source::User && correction.source != Source::User => false,
+            Some(existing) if existing.to == correction.to && existing.source == correction.source => false,
+            Some(existing) => { *existing = correction; true },
+            None => { self.entries.push(correction); true },
+        }
+    }
+
+    pub fn remove(&mut self, field: Field, artist: Option<&str>, from: &str) -> Option<Correction> {
+        let index = self.entries.iter().position(|entry| entry.is_for(field, artist, from))?;
+        Some(self.entries.remove(index))
+    }
+}
+
+/// Applies every known correction to the names.
+pub async fn apply(names: &mut TrackNames) {
+    let mut store = STORE.lock().await;
+    store.refresh();
+    store.apply(names);
+}
+
+/// What Last.fm changed about a submission; track and album corrections are keyed by the corrected artist.
+#[cfg(feature = "lastfm")]
+fn learned(submitted: &lastfm::scrobble::HeardTrackInfo<'_>, acknowledgement: &lastfm::scrobble::response::Acknowledgement) -> Vec<Correction> {
+    let corrected_artist = &acknowledgement.artist.value;
+    let fields = [
+        (Field::Artist, Some(submitted.artist), Some(&acknowledgement.artist)),
+        (Field::Track, Some(submitted.track), Some(&acknowledgement.track)),
+        (Field::Album, submitted.album, acknowledgement.album.as_ref()),
+        (Field::AlbumArtist, submitted.album_artist, acknowledgement.album_artist.as_ref()),
+    ];
+    fields.into_iter()
+        .filter_map(|(field, from, to)| {
+            let (from, to) = (from?, to?);
+            if !to.corrected || to.value.is_empty() || to.value == from { return None }
+            Some(Correction {
+                field,
+                artist: field.is_per_artist().then(|| corrected_artist.clone()),
+                from: from.to_owned(),
+                to: to.value.clone(),
+                source: Source::LastFm,
+                updated_at: Utc::now(),
+            })
+        })
+        .collect()
+}
+
+/// Remembers what Last.fm changed about a submission, so the corrected names are submitted next time.
+#[cfg(feature = "lastfm")]
+pub async fn learn(submitted: &lastfm::scrobble::HeardTrackInfo<'_>, acknowledgement: &lastfm::scrobble::response::Acknowledgement) {
+    let learned = learned(submitted, acknowledgement);
+    if learned.is_empty() { return }
+
+    let mut store = STORE.lock().await;
+    store.refresh();
+    let mut changed = false;
+    for correction in learned {
+        tracing::info!(field = %correction.field, from = correction.from, to = correction.to, "learned last.fm correction");
+        changed |= store.insert(correction);
+    }
+    if changed {
+        if let Err(error) = store.save().await {
+            tracing::warn!(?error, "could not save corrections");
+        }
+    }
+}
+
+fn describe(correction: &Correction) -> String {
+    let by = correction.artist.as_deref().map(|artist| format!(" (by {artist})")).unwrap_or_default();
+    let source = match correction.source {
+        Source::LastFm => "last.fm",
+        Source::User => "user",
+    };
+    format!("{:<12} {}{by} → {}  [{source}, {}]", correction.field, correction.from, correction.to, correction.updated_at.format("%Y-%m-%d"))
+}
+
+fn artist_for(field: Field, artist: Option<&str>) -> Option<String> {
+    match (field.is_per_artist(), artist) {
+        (true, None) => ferror!("corrections to {field} names need the `--artist` they're by"),
+        (true, Some(artist)) => Some(artist.to_owned()),
+        (false, _) => None,
+    }
+}
+
+pub fn list(field: Option<Field>) {
+    let store = CorrectionStore::default();
+    let mut entries = store.entries().iter()
+        .filter(|entry| field.is_none_or(|field| entry.field == field))
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        println!("No corrections.");
+        return;
+    }
+    entries.sort_by(|a, b| (a.field, &a.from).cmp(&(b.field, &b.from)));
+    for entry in entries {
+        println!("{}", describe(entry));
+    }
+}
+
+pub async fn set(field: Field, artist: Option<&str>, from: &str, to: &str) {
+    let mut store = CorrectionStore::default();
+    let correction = Correction {
+        field,
+        artist: artist_for(field, artist),
+        from: from.to_owned(),
+        to: to.to_owned(),
+        source: Source::User,
+        updated_at: Utc::now(),
+    };
+    println!("{}", describe(&correction));
+    store.insert(correction);
+    store.save().await.unwrap_or_else(|error| ferror!("could not save corrections to {}: {error}", store.path().to_string_lossy()));
+}
+
+pub async fn remove(field: Field, artist: Option<&str>, from: &str) {
+    let mut store = CorrectionStore::default();
+    let artist = artist_for(field, artist);
+    let Some(removed) = store.remove(field, artist.as_deref(), from) else {
+        ferror!("there is no correction of the {field} name `{from}`");
+    };
+    store.save().await.unwrap_or_else(|error| ferror!("could not save corrections to {}: {error}", store.path().to_string_lossy()));
+    println!("Removed: {}", describe(&removed));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(name: &str) -> CorrectionStore {
+        let path = std::env::temp_dir().join(format!("am-osx-status-corrections-test-{}-{name}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        CorrectionStore::load(path)
+    }
+
+    fn correction(field: Field, artist: Option<&str>, from: &str, to: &str, source: Source) -> Correction {
+        Correction { field, artist: artist.map(str::to_owned), from: from.to_owned(), to: to.to_owned(), source, updated_at: Utc::now() }
+    }
+
+    fn names(artist: &str, track: &str) -> TrackNames {
+        TrackNames { artist: artist.to_owned(), track: track.to_owned(), album: None, album_artist: None }
+    }
+
+    #[test]
+    fn user_corrections_beat_learned_ones() {
+        let mut store = store("user");
+        assert!(store.insert(correction(Field::Artist, None, "Beatles", "The Beatles", Source::User)));
+        assert!(!store.insert(correction(Field::Artist, None, "Beatles", "Beatles, The", Source::LastFm)));
+        assert_eq!(store.get(Field::Artist, None, "Beatles").unwrap().to, "The Beatles");
+
+        // but a learned correction is replaced by the user's
+        assert!(store.insert(correction(Field::Artist, None, "Stones", "Rolling Stones", Source::LastFm)));
+        assert!(store.insert(correction(Field::Artist, None, "Stones", "The Rolling Stones", Source::User)));
+        assert_eq!(store.get(Field::Artist, None, "Stones").unwrap().source, Source::User);
+        assert_eq!(store.entries().len(), 2);
+    }
+
+    #[test]
+    fn artist_is_corrected_before_the_track() {
+        let mut store = store("order");
+        store.insert(correction(Field::Artist, None, "Beatles", "The Beatles", Source::LastFm));
+        store.insert(correction(Field::Track, Some("The Beatles"), "Yesterdy", "Yesterday", Source::LastFm));
+
+        let mut corrected = names("Beatles", "Yesterdy");
+        store.apply(&mut corrected);
+        assert_eq!(corrected, names("The Beatles", "Yesterday"));
+    }
+
+    #[test]
+    fn tracks_are_corrected_per_artist() {
+        let mut store = store("per-artist");
+        store.insert(correction(Field::Track, Some("Queen"), "Under Presure", "Under Pressure", Source::User));
+
+        let mut other = names("Someone Else", "Under Presure");
+        store.apply(&mut other);
+        assert_eq!(other.track, "Under Presure");
+
+        let mut corrected = names("Queen", "Under Presure");
+        store.apply(&mut corrected);
+        assert_eq!(corrected.track, "Under Pressure");
+
+        assert!(store.remove(Field::Track, Some("Someone Else"), "Under Presure").is_none());
+        assert!(store.remove(Field::Track, Some("Queen"), "Under Presure").is_some());
+    }
+
+    #[cfg(feature = "lastfm")]
+    #[test]
+    fn learns_only_what_was_corrected() {
+        use lastfm::scrobble::{response::{Acknowledgement, MaybeCorrected}, HeardTrackInfo};
+        let value = |value: &str, corrected| MaybeCorrected { corrected, value: value.to_owned() };
+
+        let submitted = HeardTrackInfo { artist: "Beatles", track: "Yesterdy", album: Some("Help!"), ..Default::default() };
+        let acknowledgement = Acknowledgement {
+            artist: value("The Beatles", true),
+            track: value("Yesterday", true),
+            album: Some(value("Help!", false)),
+            album_artist: None,
+            timestamp: None,
+        };
+        let learned = learned(&submitted, &acknowledgement);
+        assert_eq!(learned.len(), 2);
+        assert_eq!((learned[0].field, learned[0].artist.as_deref(), learned[0].to.as_str()), (Field::Artist, None, "The Beatles"));
+        assert_eq!((learned[1].field, learned[1].artist.as_deref(), learned[1].to.as_str()), (Field::Track, Some("The Beatles"), "Yesterday"));
+    }
+
+    #[tokio::test]
+    async fn saved_corrections_are_loaded_again() {
+        let mut store = store("save");
+        store.insert(correction(Field::Album, Some("Queen"), "A Night at the Opra", "A Night at the Opera", Source::User));
+        store.save().await.unwrap();
+
+        let loaded = CorrectionStore::load(store.path().to_owned());
+        let entry = loaded.get(Field::Album, Some("Queen"), "A Night at the Opra").unwrap();
+        assert_eq!(entry.to, "A Night at the Opera");
+        assert_eq!(entry.source, Source::User);
+        let _ = std::fs::remove_file(store.path());
+    }
+}
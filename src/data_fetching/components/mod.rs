@@ -1,12 +1,19 @@
 use std::{collections::HashSet, ops::AddAssign};
 
 pub mod image;
+pub mod palette;
 
 #[derive(Eq, PartialEq, Hash, Debug)]
 pub enum Component {
     AlbumImage,
     ArtistImage,
-    ITunesData
+    ITunesData,
+    /// Colors taken from the album artwork.
+    Palette,
+    /// The IDs of the recording (and its release and artists) on MusicBrainz.
+    MusicBrainzIds,
+    /// The canonical names of the track on Last.fm, if its names are a known misspelling.
+    LastFmCorrection,
 }
 
 #[derive(Default, Debug)]
@@ -0,0 +1,194 @@
+//! Colors that match the artwork, in the same spirit as the background and text colors Apple picks for
+//! generated playlist covers ([`mzstatic::image::effect::GeneratedPlaylistCoverPayload`]).
+
+use std::collections::HashMap;
+
+use mzstatic::image::effect::Rgb;
+
+/// Artwork is shrunk to at most this many pixels on each side before sampling; more doesn't change the result much.
+const SAMPLE_EDGE: u32 = 64;
+/// Bits kept per channel when grouping similar colors.
+const QUANTIZATION_BITS: u8 = 4;
+/// Colors at least this saturated count as vibrant, and less saturated ones as muted.
+const VIBRANT_SATURATION: f32 = 0.35;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PaletteError {
+    #[error("could not decode artwork: {0}")]
+    Decode(#[from] image::ImageError),
+    #[error("artwork has no opaque pixels")]
+    Empty,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// The most common color.
+    pub dominant: Rgb,
+    /// The most common saturated, mid-lightness color, if there is one.
+    pub vibrant: Option<Rgb>,
+    /// The most common unsaturated, mid-lightness color, if there is one.
+    pub muted: Option<Rgb>,
+    /// Black or white, whichever is more legible on top of [`Self::dominant`].
+    pub text: Rgb,
+}
+
+#[derive(Default)]
+struct Bucket {
+    count: u32,
+    sum: [u32; 3],
+}
+impl Bucket {
+    fn average(&self) -> Rgb {
+        let channel = |index: usize| (self.sum[index] / self.count) as u8;
+        Rgb::new(channel(0), channel(1), channel(2))
+    }
+}
+
+impl Palette {
+    /// Decodes and samples the artwork; this is CPU-bound, so it shouldn't be run directly on the async runtime.
+    pub fn from_image_data(data: &[u8]) -> Result<Self, PaletteError> {
+        let image = image::load_from_memory(data)?
+            .thumbnail(SAMPLE_EDGE, SAMPLE_EDGE)
+            .to_rgba8();
+
+        let shift = 8 - QUANTIZATION_BITS;
+        let mut buckets = HashMap::<(u8, u8, u8), Bucket>::new();
+        for pixel in image.pixels() {
+            let [r, g, b, a] = pixel.0;
+            if a < 128 { continue }
+            let bucket = buckets.entry((r >> shift, g >> shift, b >> shift)).or_default();
+            bucket.count += 1;
+            bucket.sum[0] += r as u32;
+            bucket.sum[1] += g as u32;
+            bucket.sum[2] += b as u32;
+        }
+
+        let mut colors = buckets.values().map(|bucket| (bucket.count, bucket.average())).collect::<Vec<_>>();
+        // ties are broken by the color itself, so the result doesn't depend on hashing order
+        colors.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        let dominant = colors.first().ok_or(PaletteError::Empty)?.1;
+        let mid_lightness = |color: &Rgb| (0.25..=0.8).contains(&hsl(*color).2);
+        let vibrant = colors.iter()
+            .map(|(count, color)| (*count as f32 * hsl(*color).1, *color))
+            .filter(|(_, color)| mid_lightness(color) && hsl(*color).1 >= VIBRANT_SATURATION)
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, color)| color);
+        let muted = colors.iter()
+            .map(|(_, color)| *color)
+            .find(|color| mid_lightness(color) && hsl(*color).1 < VIBRANT_SATURATION);
+
+        const BLACK: Rgb = Rgb::new(0, 0, 0);
+        const WHITE: Rgb = Rgb::new(255, 255, 255);
+        let text = if contrast_ratio(dominant, BLACK) >= contrast_ratio(dominant, WHITE) { BLACK } else { WHITE };
+
+        Ok(Self { dominant, vibrant, muted, text })
+    }
+}
+
+/// Hue (in degrees), saturation and lightness; the latter two from zero to one.
+fn hsl(color: Rgb) -> (f32, f32, f32) {
+    let [r, g, b] = [color.r, color.g, color.b].map(|channel| channel as f32 / 255.);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.;
+    let delta = max - min;
+    if delta == 0. { return (0., 0., lightness) }
+
+    let saturation = delta / (1. - (2. * lightness - 1.).abs());
+    let hue = if max == r {
+        60. * ((g - b) / delta).rem_euclid(6.)
+    } else if max == g {
+        60. * ((b - r) / delta + 2.)
+    } else {
+        60. * ((r - g) / delta + 4.)
+    };
+    (hue, saturation, lightness)
+}
+
+/// <https://www.w3.org/TR/WCAG21/#dfn-relative-luminance>
+fn relative_luminance(color: Rgb) -> f32 {
+    let [r, g, b] = [color.r, color.g, color.b].map(|channel| {
+        let channel = channel as f32 / 255.;
+        if channel <= 0.03928 { channel / 12.92 } else { ((channel + 0.055) / 1.055).powf(2.4) }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// <https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio>
+pub fn contrast_ratio(a: Rgb, b: Rgb) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Rgb = Rgb::new(0, 0, 0);
+    const WHITE: Rgb = Rgb::new(255, 255, 255);
+
+    /// A PNG where each of `regions` fills the given number of rows; sized so that it isn't resampled.
+    fn png(regions: &[(Rgb, u32)]) -> Vec<u8> {
+        let height = regions.iter().map(|(_, rows)| rows).sum();
+        let mut image = image::RgbaImage::new(SAMPLE_EDGE, height);
+        let mut y = 0;
+        for (color, rows) in regions {
+            for row in y..y + rows {
+                for x in 0..SAMPLE_EDGE {
+                    image.put_pixel(x, row, image::Rgba([color.r, color.g, color.b, 255]));
+                }
+            }
+            y += rows;
+        }
+        let mut data = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn hsl_of_primaries_and_grays() {
+        assert_eq!(hsl(Rgb::new(255, 0, 0)), (0., 1., 0.5));
+        assert_eq!(hsl(Rgb::new(0, 255, 0)), (120., 1., 0.5));
+        assert_eq!(hsl(Rgb::new(0, 0, 255)), (240., 1., 0.5));
+        assert_eq!(hsl(BLACK), (0., 0., 0.));
+        assert_eq!(hsl(WHITE), (0., 0., 1.));
+        let (_, saturation, _) = hsl(Rgb::new(128, 128, 128));
+        assert_eq!(saturation, 0.);
+    }
+
+    #[test]
+    fn contrast_ratios() {
+        assert!((contrast_ratio(BLACK, WHITE) - 21.).abs() < 0.01);
+        assert_eq!(contrast_ratio(WHITE, BLACK), contrast_ratio(BLACK, WHITE));
+        assert_eq!(contrast_ratio(Rgb::new(10, 20, 30), Rgb::new(10, 20, 30)), 1.);
+        assert!(relative_luminance(WHITE) > relative_luminance(Rgb::new(255, 255, 0)));
+    }
+
+    #[test]
+    fn palette_of_artwork() {
+        let navy = Rgb::new(20, 30, 70);
+        let orange = Rgb::new(220, 100, 40);
+        let gray = Rgb::new(120, 120, 125);
+        let palette = Palette::from_image_data(&png(&[(navy, 40), (orange, 16), (gray, 8)])).unwrap();
+        assert_eq!(palette.dominant, navy);
+        assert_eq!(palette.vibrant, Some(orange));
+        assert_eq!(palette.muted, Some(gray));
+        assert_eq!(palette.text, WHITE);
+
+        let pale = Palette::from_image_data(&png(&[(Rgb::new(240, 240, 230), SAMPLE_EDGE)])).unwrap();
+        assert_eq!(pale.text, BLACK);
+        assert_eq!(pale.vibrant, None);
+        assert_eq!(pale.muted, None);
+    }
+
+    #[test]
+    fn rejects_unusable_artwork() {
+        assert!(matches!(Palette::from_image_data(b"not an image"), Err(PaletteError::Decode(_))));
+
+        let transparent = image::RgbaImage::new(4, 4);
+        let mut data = std::io::Cursor::new(Vec::new());
+        transparent.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        assert!(matches!(Palette::from_image_data(&data.into_inner()), Err(PaletteError::Empty)));
+    }
+}
@@ -1,26 +1,54 @@
+pub mod preprocessing;
 pub mod services;
 
-use components::{image::TrackImageUrlPack, Component, ComponentSolicitation};
-use services::{artworkd::{get_artwork, StoredArtwork}, itunes::ITunesStoreSong};
+use components::{image::TrackImageUrlPack, palette::Palette, Component, ComponentSolicitation};
+use services::{artworkd::{get_artwork, StoredArtwork}, itunes::ITunesStoreSong, musicbrainz::RecordingIds};
 
 use crate::util::fallback_to_default_and_log_error;
 
 pub mod components;
 
-#[derive(Debug)]
+/// The size of artwork taken from online sources when the library has none, in pixels.
+const FALLBACK_ARTWORK_SIZE: u16 = 500;
+
+#[derive(Debug, Default)]
 pub struct AdditionalTrackData {
     pub itunes: Option<ITunesStoreSong>,
-    pub images: TrackImageUrlPack
+    pub images: TrackImageUrlPack,
+    pub palette: Option<Palette>,
+    pub musicbrainz: Option<RecordingIds>,
+    pub lastfm_correction: Option<lastfm::track::Correction>,
 }
 impl AdditionalTrackData {
     pub async fn from_solicitation(
         solicitation: ComponentSolicitation,
         track: &osa_apple_music::track::Track,
         musicdb: Option<&musicdb::MusicDB<'_>>,
-        host: Option<&mut Box<dyn crate::data_fetching::services::custom_artwork_host::CustomArtworkHost>>,
+        mut host: Option<&mut crate::data_fetching::services::custom_artwork_host::ArtworkUploader>,
     ) -> Self {
         let mut itunes: Option<ITunesStoreSong> = None;
         let mut images = TrackImageUrlPack::none();
+        let mut palette = None;
+        let mut musicbrainz = None;
+        let mut lastfm_correction = None;
+
+        if let Some(host) = host.as_deref_mut() {
+            host.forget_current();
+        }
+
+        if solicitation.list.contains(&Component::ITunesData) {
+            itunes = fallback_to_default_and_log_error!(services::itunes::find_track(track).await);
+        }
+
+        let wants_musicbrainz = solicitation.list.contains(&Component::MusicBrainzIds);
+        if wants_musicbrainz {
+            musicbrainz = fallback_to_default_and_log_error!(services::musicbrainz::find_recording(track).await);
+        }
+
+        #[cfg(feature = "lastfm")]
+        if solicitation.list.contains(&Component::LastFmCorrection) {
+            lastfm_correction = fallback_to_default_and_log_error!(services::lastfm::find_correction(track).await);
+        }
 
         if solicitation.list.contains(&Component::ArtistImage) {
             if let Some(db) = musicdb {
@@ -37,34 +65,114 @@ impl AdditionalTrackData {
                         }
                     });
             }
-        }
-
-        if solicitation.list.contains(&Component::AlbumImage) {
-            images.track = match crate::util::fallback_to_default_and_log_error!(get_artwork(&track.persistent_id)) {
-                None => None,
-                Some(artwork) => match artwork {
-                    StoredArtwork::Remote { url } => Some(url),
-                    StoredArtwork::Local { path } => {
-                        if let Some(host) = host  {
-                            host.for_track(track, &path).await.inspect_err(|err| {
-                                tracing::error!(?err, "failed to upload custom artwork");
-                            }).ok()
-                        } else {
-                            None
-                        }
-                    },
+            #[cfg(feature = "lastfm")]
+            if images.artist.is_none() {
+                if let Some(artist) = track.artist.as_deref() {
+                    images.artist = fallback_to_default_and_log_error!(services::lastfm::find_artist_image(artist).await);
                 }
-            };
+            }
         }
 
-        if solicitation.list.contains(&Component::ITunesData) {
-            itunes = fallback_to_default_and_log_error!(services::itunes::find_track(track).await);
+        let wants_album_image = solicitation.list.contains(&Component::AlbumImage);
+        let wants_palette = solicitation.list.contains(&Component::Palette);
+        if wants_album_image || wants_palette {
+            let mut artwork = crate::util::fallback_to_default_and_log_error!(get_artwork(&track.persistent_id));
+            if artwork.is_none() {
+                artwork = Self::fallback_artwork(track, itunes.as_ref(), &mut musicbrainz, wants_musicbrainz).await
+                    .map(|url| StoredArtwork::Remote { url });
+            }
+
+            if wants_album_image {
+                images.track = match &artwork {
+                    None => None,
+                    Some(artwork) => match artwork {
+                        StoredArtwork::Remote { url } => Some(url.clone()),
+                        StoredArtwork::Local { path } => {
+                            if let Some(host) = host  {
+                                host.for_path(path).await.inspect_err(|err| {
+                                    tracing::error!(?err, "failed to upload custom artwork");
+                                }).ok()
+                            } else {
+                                None
+                            }
+                        },
+                    }
+                };
+            }
+
+            if wants_palette {
+                if let Some(artwork) = &artwork {
+                    palette = Self::palette_for(artwork).await;
+                }
+            }
         }
 
         Self {
             itunes,
-            images
+            images,
+            palette,
+            musicbrainz,
+            lastfm_correction,
+        }
+    }
+
+    /// For tracks without artwork in the library, such as local rips and obscure releases.
+    async fn fallback_artwork(
+        track: &osa_apple_music::track::Track,
+        itunes: Option<&ITunesStoreSong>,
+        musicbrainz: &mut Option<RecordingIds>,
+        searched_musicbrainz: bool,
+    ) -> Option<String> {
+        if let Some(song) = itunes {
+            return Some(song.get_artwork_url_at_resolution(FALLBACK_ARTWORK_SIZE));
         }
+        #[cfg(feature = "lastfm")]
+        if let Some(url) = fallback_to_default_and_log_error!(services::lastfm::find_artwork(track).await) {
+            return Some(url);
+        }
+        if let Some(url) = services::cover_art_archive::cached_artwork(&track.persistent_id).await {
+            return url;
+        }
+        if !searched_musicbrainz {
+            match services::musicbrainz::find_recording(track).await {
+                Ok(found) => *musicbrainz = found,
+                Err(error) => {
+                    tracing::error!(?error, "failed to search musicbrainz for artwork");
+                    return None;
+                }
+            }
+        } else if musicbrainz.is_none() {
+            // the search may have failed, so it's not known that there's no artwork
+            return None;
+        }
+        let url = match musicbrainz.as_ref() {
+            None => None,
+            Some(ids) => match services::cover_art_archive::find_front_artwork(ids, FALLBACK_ARTWORK_SIZE).await {
+                Ok(url) => url,
+                Err(error) => {
+                    tracing::error!(?error, "failed to get artwork from the cover art archive");
+                    return None;
+                }
+            },
+        };
+        services::cover_art_archive::remember_artwork(&track.persistent_id, url.clone()).await;
+        url
+    }
+
+    async fn palette_for(artwork: &StoredArtwork) -> Option<Palette> {
+        let data = match artwork {
+            StoredArtwork::Local { path } => tokio::fs::read(path).await
+                .inspect_err(|error| tracing::error!(?error, "failed to read artwork for palette"))
+                .ok()?,
+            StoredArtwork::Remote { url } => async { reqwest::get(url).await?.error_for_status()?.bytes().await }.await
+                .inspect_err(|error| tracing::error!(?error, "failed to download artwork for palette"))
+                .ok()?
+                .to_vec(),
+        };
+
+        tokio::task::spawn_blocking(move || Palette::from_image_data(&data)).await.ok()?
+            .inspect_err(|error| tracing::error!(?error, "failed to compute artwork palette"))
+            .ok()
     }
 
 }
@@ -0,0 +1,150 @@
+//! Shrinks user artwork before it's uploaded, since the originals can be multi-megabyte PNG or TIFF files
+//! while presences only display them at a few hundred pixels.
+//!
+//! Re-encoding also drops any metadata embedded in the original.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    #[default]
+    Jpeg,
+    /// Always lossless, as there's no lossy WebP encoder written in Rust.
+    Webp,
+}
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub enabled: bool,
+    /// The longest the width or height may be, in pixels; larger artwork is scaled down to fit.
+    pub max_edge: u32,
+    pub format: OutputFormat,
+    /// From 1 to 100; only used for JPEG.
+    pub quality: u8,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_edge: 512,
+            format: OutputFormat::Jpeg,
+            quality: 85,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PreprocessingError {
+    #[error("could not decode artwork: {0}")]
+    Decode(#[source] image::ImageError),
+    #[error("could not encode artwork: {0}")]
+    Encode(#[source] image::ImageError),
+}
+
+impl Config {
+    /// Distinguishes the output of these settings from that of others, for caching.
+    pub fn fingerprint(&self) -> String {
+        format!("{}px-q{}.{}", self.max_edge, self.quality, self.format.extension())
+    }
+
+    /// Decodes, downscales and re-encodes the image; this is CPU-bound, so it shouldn't be run directly on the async runtime.
+    pub fn process(&self, data: &[u8]) -> Result<Vec<u8>, PreprocessingError> {
+        use image::{imageops::FilterType, DynamicImage};
+
+        let mut image = image::load_from_memory(data).map_err(PreprocessingError::Decode)?;
+        if image.width().max(image.height()) > self.max_edge {
+            image = image.resize(self.max_edge, self.max_edge, FilterType::Lanczos3);
+        }
+
+        let mut out = Vec::new();
+        match self.format {
+            OutputFormat::Jpeg => {
+                let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, self.quality.clamp(1, 100));
+                DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)
+            },
+            OutputFormat::Webp => {
+                let encoder = image::codecs::webp::WebPEncoder::new_lossless(&mut out);
+                DynamicImage::ImageRgba8(image.to_rgba8()).write_with_encoder(encoder)
+            },
+        }.map_err(PreprocessingError::Encode)?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbaImage::from_fn(width, height, |x, y| image::Rgba([(x % 256) as u8, (y % 256) as u8, 128, 255]));
+        let mut data = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        data.into_inner()
+    }
+
+    fn processed(config: Config, data: &[u8]) -> (image::ImageFormat, image::DynamicImage) {
+        let out = config.process(data).unwrap();
+        (image::guess_format(&out).unwrap(), image::load_from_memory(&out).unwrap())
+    }
+
+    #[test]
+    fn downscales_keeping_the_aspect_ratio() {
+        let config = Config { max_edge: 100, ..Config::default() };
+        let (format, image) = processed(config, &png(400, 200));
+        assert_eq!(format, image::ImageFormat::Jpeg);
+        assert_eq!((image.width(), image.height()), (100, 50));
+
+        let (_, image) = processed(config, &png(150, 300));
+        assert_eq!((image.width(), image.height()), (50, 100));
+    }
+
+    #[test]
+    fn never_upscales() {
+        let (_, image) = processed(Config { max_edge: 100, ..Config::default() }, &png(64, 32));
+        assert_eq!((image.width(), image.height()), (64, 32));
+    }
+
+    #[test]
+    fn encodes_webp() {
+        let config = Config { max_edge: 100, format: OutputFormat::Webp, ..Config::default() };
+        let (format, image) = processed(config, &png(200, 200));
+        assert_eq!(format, image::ImageFormat::WebP);
+        assert_eq!((image.width(), image.height()), (100, 100));
+    }
+
+    #[test]
+    fn rejects_what_isnt_an_image() {
+        assert!(matches!(Config::default().process(b"not an image"), Err(PreprocessingError::Decode(_))));
+    }
+
+    #[test]
+    fn fingerprint_changes_with_the_output() {
+        let config = Config::default();
+        let fingerprints = [
+            config,
+            Config { max_edge: 1024, ..config },
+            Config { quality: 90, ..config },
+            Config { format: OutputFormat::Webp, ..config },
+        ].map(|config| config.fingerprint());
+        for (index, fingerprint) in fingerprints.iter().enumerate() {
+            assert!(!fingerprints[index + 1..].contains(fingerprint), "{fingerprint} isn't unique");
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::{collections::HashMap, path::PathBuf, sync::LazyLock};
+
+use brainz::art::ListingError;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::util::HOME;
+
+use super::musicbrainz::{RecordingIds, PROGRAM_INFO};
+
+static CLIENT: LazyLock<brainz::art::Client> = LazyLock::new(|| brainz::art::Client::new(&PROGRAM_INFO));
+
+/// The front cover of the release the recording was matched to, falling back to that of its release group.
+pub async fn find_front_artwork(ids: &RecordingIds, size: u16) -> Result<Option<String>, ListingError> {
+    if let Some(release) = ids.release {
+        if let Some(front) = CLIENT.release(release).await?.as_ref().and_then(brainz::art::Listing::front) {
+            return Ok(Some(front.url_at(size)));
+        }
+    }
+    if let Some(group) = ids.release_group {
+        if let Some(front) = CLIENT.release_group(group).await?.as_ref().and_then(brainz::art::Listing::front) {
+            return Ok(Some(front.url_at(size)));
+        }
+    }
+    Ok(None)
+}
+
+pub static CACHE_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+    HOME.join("Library/Caches/am-osx-status/cover-art-archive.json")
+});
+
+/// How long found artwork is remembered; archive URLs only change if the artwork is replaced.
+const TTL: TimeDelta = TimeDelta::days(30);
+/// How long it's remembered that there's no artwork, since it may be uploaded later.
+const NEGATIVE_TTL: TimeDelta = TimeDelta::days(1);
+
+/// Loaded from disk on first use.
+static CACHE: LazyLock<Mutex<ArtworkCache>> = LazyLock::new(|| Mutex::new(ArtworkCache::load(CACHE_PATH.clone())));
+
+/// `Some(None)` means it's known that the track has no artwork in the archive.
+pub async fn cached_artwork(persistent_id: &str) -> Option<Option<String>> {
+    CACHE.lock().await.get(persistent_id)
+}
+
+/// Remembers the outcome of a lookup, so a track isn't searched for again every time it's played.
+pub async fn remember_artwork(persistent_id: &str, url: Option<String>) {
+    let snapshot = CACHE.lock().await.insert(persistent_id, url);
+    if let Some(data) = snapshot {
+        save(&CACHE_PATH, data).await;
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    /// `None` if there's no artwork.
+    url: Option<String>,
+    cached_at: DateTime<Utc>,
+}
+
+/// Fallback artwork, keyed by persistent ID.
+struct ArtworkCache {
+    entries: HashMap<String, CacheEntry>,
+}
+impl ArtworkCache {
+    fn load(path: PathBuf) -> Self {
+        let entries = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|error| {
+                tracing::warn!(?error, ?path, "cover art archive cache is corrupt; starting over");
+                HashMap::new()
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => {
+                tracing::warn!(?error, ?path, "could not read cover art archive cache");
+                HashMap::new()
+            }
+        };
+        let mut cache = Self { entries };
+        cache.prune();
+        cache
+    }
+
+    fn is_fresh(entry: &CacheEntry) -> bool {
+        let ttl = if entry.url.is_some() { TTL } else { NEGATIVE_TTL };
+        Utc::now() - entry.cached_at < ttl
+    }
+
+    fn get(&self, persistent_id: &str) -> Option<Option<String>> {
+        self.entries.get(persistent_id)
+            .filter(|entry| Self::is_fresh(entry))
+            .map(|entry| entry.url.clone())
+    }
+
+    /// Returns what should be written to disk, so that it can be written once the cache is unlocked.
+    fn insert(&mut self, persistent_id: &str, url: Option<String>) -> Option<Vec<u8>> {
+        self.prune();
+        self.entries.insert(persistent_id.to_owned(), CacheEntry { url, cached_at: Utc::now() });
+        serde_json::to_vec(&self.entries)
+            .inspect_err(|error| tracing::warn!(?error, "could not serialize cover art archive cache"))
+            .ok()
+    }
+
+    fn prune(&mut self) {
+        self.entries.retain(|_, entry| Self::is_fresh(entry));
+    }
+}
+
+async fn save(path: &std::path::Path, data: Vec<u8>) {
+    let result = async {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, data).await
+    }.await;
+    if let Err(error) = result {
+        tracing::warn!(?error, ?path, "could not save cover art archive cache");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn misses_expire_before_found_artwork() {
+        let mut cache = ArtworkCache { entries: HashMap::new() };
+        cache.insert("found", Some("https://example.com/front.jpg".to_owned()));
+        cache.insert("missing", None);
+        assert_eq!(cache.get("found"), Some(Some("https://example.com/front.jpg".to_owned())));
+        assert_eq!(cache.get("missing"), Some(None));
+
+        let two_days_ago = Utc::now() - TimeDelta::days(2);
+        cache.entries.values_mut().for_each(|entry| entry.cached_at = two_days_ago);
+        assert_eq!(cache.get("found"), Some(Some("https://example.com/front.jpg".to_owned())));
+        assert_eq!(cache.get("missing"), None);
+        assert_eq!(cache.get("unknown"), None);
+
+        cache.insert("other", None);
+        assert!(!cache.entries.contains_key("missing"));
+    }
+}
@@ -0,0 +1,150 @@
+//! Remembers uploaded artwork across restarts, keyed by the contents of the artwork rather than the album it belongs to,
+//! so that the same image is only uploaded once per host until its URL expires.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::util::HOME;
+
+pub static DEFAULT_PATH: std::sync::LazyLock<PathBuf> = std::sync::LazyLock::new(|| {
+    HOME.join("Library/Caches/am-osx-status/artwork-uploads.json")
+});
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    /// `None` if the host keeps uploads around indefinitely.
+    expires_at: Option<DateTime<Utc>>,
+}
+impl Entry {
+    fn expires_within(&self, margin: TimeDelta) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at < Utc::now() + margin)
+    }
+}
+
+#[derive(Debug)]
+pub struct UploadCache {
+    path: PathBuf,
+    /// Keyed by `{host id}/{artwork hash}`.
+    entries: HashMap<String, Entry>,
+}
+impl UploadCache {
+    fn key(host: &str, hash: &str) -> String {
+        format!("{host}/{hash}")
+    }
+
+    /// Reads the cache from disk, starting over if it's missing or unreadable.
+    pub async fn load(path: PathBuf) -> Self {
+        let entries = match tokio::fs::read(&path).await {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|error| {
+                tracing::warn!(?error, ?path, "artwork upload cache is corrupt; starting over");
+                HashMap::new()
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => {
+                tracing::warn!(?error, ?path, "could not read artwork upload cache");
+                HashMap::new()
+            }
+        };
+        let mut cache = Self { path, entries };
+        cache.prune();
+        cache
+    }
+
+    async fn save(&self) {
+        let result = async {
+            if let Some(parent) = self.path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&self.path, serde_json::to_vec(&self.entries)?).await
+        }.await;
+        if let Err(error) = result {
+            tracing::warn!(?error, path = ?self.path, "could not save artwork upload cache");
+        }
+    }
+
+    /// Removes entries which have already expired.
+    fn prune(&mut self) {
+        self.entries.retain(|_, entry| !entry.expires_within(TimeDelta::zero()));
+    }
+
+    /// The URL of the artwork, unless it isn't cached or expires within `margin`.
+    pub fn get(&self, host: &str, hash: &str, margin: TimeDelta) -> Option<&str> {
+        self.entries.get(&Self::key(host, hash))
+            .filter(|entry| !entry.expires_within(margin))
+            .map(|entry| entry.url.as_str())
+    }
+
+    pub async fn insert(&mut self, host: &str, hash: &str, url: String, expires_in: Option<TimeDelta>) {
+        self.prune();
+        let expires_at = expires_in.map(|expires_in| Utc::now() + expires_in);
+        self.entries.insert(Self::key(host, hash), Entry { url, expires_at });
+        self.save().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("am-osx-status-upload-cache-{}-{name}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn urls_are_not_handed_out_close_to_expiry() {
+        let path = path("margin");
+        let mut cache = UploadCache::load(path.clone()).await;
+        cache.insert("catbox", "abc", "https://example.com/abc.jpg".to_owned(), Some(TimeDelta::minutes(10))).await;
+        cache.insert("http", "abc", "https://files.example.com/abc.jpg".to_owned(), None).await;
+
+        assert_eq!(cache.get("catbox", "abc", TimeDelta::seconds(5)), Some("https://example.com/abc.jpg"));
+        assert_eq!(cache.get("catbox", "abc", TimeDelta::minutes(11)), None);
+        // never expires
+        assert_eq!(cache.get("http", "abc", TimeDelta::days(365)), Some("https://files.example.com/abc.jpg"));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn entries_are_kept_per_host() {
+        let path = path("hosts");
+        let mut cache = UploadCache::load(path.clone()).await;
+        cache.insert("s3:a", "abc", "https://a.example.com/abc.jpg".to_owned(), None).await;
+
+        assert_eq!(cache.get("s3:a", "abc", TimeDelta::zero()), Some("https://a.example.com/abc.jpg"));
+        assert_eq!(cache.get("s3:b", "abc", TimeDelta::zero()), None);
+        assert_eq!(cache.get("s3:a", "def", TimeDelta::zero()), None);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn expired_entries_are_pruned() {
+        let path = path("prune");
+        let mut cache = UploadCache::load(path.clone()).await;
+        cache.insert("catbox", "old", "https://example.com/old.jpg".to_owned(), Some(TimeDelta::seconds(-1))).await;
+        assert!(cache.entries.contains_key(&UploadCache::key("catbox", "old")));
+
+        cache.insert("catbox", "new", "https://example.com/new.jpg".to_owned(), Some(TimeDelta::hours(1))).await;
+        assert!(!cache.entries.contains_key(&UploadCache::key("catbox", "old")));
+        assert_eq!(cache.entries.len(), 1);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn saved_entries_are_loaded_again() {
+        let path = path("reload");
+        let mut cache = UploadCache::load(path.clone()).await;
+        cache.insert("catbox", "live", "https://example.com/live.jpg".to_owned(), Some(TimeDelta::hours(1))).await;
+        cache.insert("catbox", "dead", "https://example.com/dead.jpg".to_owned(), Some(TimeDelta::seconds(-1))).await;
+
+        let loaded = UploadCache::load(path.clone()).await;
+        assert_eq!(loaded.get("catbox", "live", TimeDelta::zero()), Some("https://example.com/live.jpg"));
+        // already expired when it was saved, and not loaded again
+        assert!(!loaded.entries.contains_key(&UploadCache::key("catbox", "dead")));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,167 @@
+//! Uploads to any server that accepts files over HTTP, such as a self-hosted file server.
+//!
+//! In the URL and response templates, `{name}` is replaced with the name of the file, which is
+//! derived from its contents (so re-uploading the same artwork overwrites the same file).
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Method {
+    #[default]
+    Put,
+    Post,
+}
+
+/// Where to find the URL of the uploaded artwork.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseUrl {
+    /// The whole response body.
+    #[default]
+    Body,
+    /// A [JSON pointer](https://datatracker.ietf.org/doc/html/rfc6901) into the response body, e.g. `/data/url`.
+    Json(String),
+    /// A response header, e.g. `Location`.
+    Header(String),
+    /// Not in the response at all; the URL is known ahead of time, e.g. `https://files.example.com/artwork/{name}`.
+    Template(String),
+}
+
+impl ResponseUrl {
+    /// Finds the URL of the artwork named `name` in the response to its upload.
+    fn extract(&self, headers: &reqwest::header::HeaderMap, body: &str, name: &str) -> Result<String, super::UploadError> {
+        let url = match self {
+            Self::Body => body.trim().to_owned(),
+            Self::Json(pointer) => serde_json::from_str::<serde_json::Value>(body).ok()
+                .and_then(|value| value.pointer(pointer).and_then(serde_json::Value::as_str).map(str::to_owned))
+                .ok_or_else(|| super::UploadError::BadResponse(format!("no string at `{pointer}` in `{body}`")))?,
+            Self::Header(header) => headers.get(header)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+                .ok_or_else(|| super::UploadError::BadResponse(format!("no `{header}` header")))?,
+            Self::Template(template) => template.replace("{name}", name),
+        };
+        if url.is_empty() {
+            return Err(super::UploadError::BadResponse("empty url".to_owned()));
+        }
+        Ok(url)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Config {
+    /// Where to send the upload, e.g. `https://files.example.com/artwork/{name}`.
+    pub url: String,
+    #[serde(default)]
+    pub method: Method,
+    /// Sent with every upload; for authentication, usually.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// If set, the file is sent as a `multipart/form-data` field with this name, instead of as the raw body.
+    #[serde(default)]
+    pub form_field: Option<String>,
+    #[serde(default)]
+    pub response: ResponseUrl,
+    /// How long the server keeps uploads, in seconds; leave unset if it keeps them indefinitely.
+    #[serde(default)]
+    pub expires_after: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct HttpHost {
+    config: Config,
+    client: reqwest::Client,
+}
+impl HttpHost {
+    pub fn new(config: Config) -> Self {
+        Self { config, client: reqwest::Client::new() }
+    }
+}
+#[async_trait::async_trait]
+impl super::CustomArtworkHost for HttpHost {
+    fn id(&self) -> String {
+        format!("http:{}", self.config.url)
+    }
+
+    async fn upload(&mut self, artwork: &super::Artwork) -> Result<super::Uploaded, super::UploadError> {
+        let method = match self.config.method {
+            Method::Put => reqwest::Method::PUT,
+            Method::Post => reqwest::Method::POST,
+        };
+
+        let mut request = self.client.request(method, self.config.url.replace("{name}", &artwork.name));
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value);
+        }
+        request = match &self.config.form_field {
+            Some(field) => {
+                let part = reqwest::multipart::Part::bytes(artwork.data.clone())
+                    .file_name(artwork.name.clone())
+                    .mime_str(artwork.content_type)?;
+                request.multipart(reqwest::multipart::Form::new().part(field.clone(), part))
+            },
+            None => request.header(reqwest::header::CONTENT_TYPE, artwork.content_type).body(artwork.data.clone()),
+        };
+
+        let response = request.send().await?.error_for_status()?;
+        let headers = response.headers().clone();
+        let body = response.text().await?;
+        let url = self.config.response.extract(&headers, &body, &artwork.name)?;
+
+        let expires_in = self.config.expires_after.map(|seconds| chrono::Duration::seconds(seconds as i64));
+        Ok(super::Uploaded { url, expires_in })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_fetching::services::custom_artwork_host::UploadError;
+
+    const NAME: &str = "0123abcd.jpg";
+
+    fn extract(response: ResponseUrl, headers: &[(&'static str, &'static str)], body: &str) -> Result<String, UploadError> {
+        let headers = headers.iter()
+            .map(|(name, value)| (reqwest::header::HeaderName::from_static(name), reqwest::header::HeaderValue::from_static(value)))
+            .collect();
+        response.extract(&headers, body, NAME)
+    }
+
+    #[test]
+    fn reads_the_body() {
+        assert_eq!(extract(ResponseUrl::Body, &[], " https://files.example.com/a.jpg\n").unwrap(), "https://files.example.com/a.jpg");
+        assert!(matches!(extract(ResponseUrl::Body, &[], " \n"), Err(UploadError::BadResponse(_))));
+    }
+
+    #[test]
+    fn reads_json() {
+        let pointer = ResponseUrl::Json("/data/url".to_owned());
+        assert_eq!(
+            extract(pointer.clone(), &[], r#"{"data":{"url":"https://files.example.com/a.jpg"}}"#).unwrap(),
+            "https://files.example.com/a.jpg",
+        );
+        assert!(matches!(extract(pointer.clone(), &[], r#"{"data":{}}"#), Err(UploadError::BadResponse(_))));
+        assert!(matches!(extract(pointer.clone(), &[], r#"{"data":{"url":1}}"#), Err(UploadError::BadResponse(_))));
+        assert!(matches!(extract(pointer.clone(), &[], "not json"), Err(UploadError::BadResponse(_))));
+        assert!(matches!(extract(pointer, &[], r#"{"data":{"url":""}}"#), Err(UploadError::BadResponse(_))));
+    }
+
+    #[test]
+    fn reads_a_header() {
+        let header = ResponseUrl::Header("Location".to_owned());
+        assert_eq!(
+            extract(header.clone(), &[("location", "https://files.example.com/a.jpg")], "ignored").unwrap(),
+            "https://files.example.com/a.jpg",
+        );
+        assert!(matches!(extract(header, &[("etag", "x")], ""), Err(UploadError::BadResponse(_))));
+    }
+
+    #[test]
+    fn fills_in_the_template() {
+        let template = ResponseUrl::Template("https://files.example.com/artwork/{name}".to_owned());
+        assert_eq!(extract(template, &[], "ignored").unwrap(), format!("https://files.example.com/artwork/{NAME}"));
+    }
+}
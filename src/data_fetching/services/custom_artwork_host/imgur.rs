@@ -0,0 +1,58 @@
+//! - <https://apidocs.imgur.com/#c85c9dfc-7487-4de2-9ecd-66f727cf3139>
+
+use serde::{Deserialize, Serialize};
+
+const UPLOAD_URL: &str = "https://api.imgur.com/3/image";
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Config {
+    /// From registering an application at <https://api.imgur.com/oauth2/addclient>.
+    pub client_id: String,
+}
+
+#[derive(Deserialize)]
+struct UploadResponse {
+    data: UploadedImage,
+}
+#[derive(Deserialize)]
+struct UploadedImage {
+    link: String,
+}
+
+/// Anonymous uploads, which Imgur keeps indefinitely.
+#[derive(Debug)]
+pub struct ImgurHost {
+    config: Config,
+    client: reqwest::Client,
+}
+impl ImgurHost {
+    pub fn new(config: Config) -> Self {
+        Self { config, client: reqwest::Client::new() }
+    }
+}
+#[async_trait::async_trait]
+impl super::CustomArtworkHost for ImgurHost {
+    fn id(&self) -> String {
+        "imgur".to_owned()
+    }
+
+    async fn upload(&mut self, artwork: &super::Artwork) -> Result<super::Uploaded, super::UploadError> {
+        let part = reqwest::multipart::Part::bytes(artwork.data.clone())
+            .file_name(artwork.name.clone())
+            .mime_str(artwork.content_type)?;
+        let form = reqwest::multipart::Form::new()
+            .text("type", "file")
+            .part("image", part);
+
+        let body = self.client.post(UPLOAD_URL)
+            .header(reqwest::header::AUTHORIZATION, format!("Client-ID {}", self.config.client_id))
+            .multipart(form)
+            .send().await?
+            .error_for_status()?
+            .text().await?;
+        let response: UploadResponse = serde_json::from_str(&body)
+            .map_err(|error| super::UploadError::BadResponse(error.to_string()))?;
+
+        Ok(super::Uploaded { url: response.data.link, expires_in: None })
+    }
+}
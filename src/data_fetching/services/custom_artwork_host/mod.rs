@@ -1,34 +1,245 @@
+pub mod cache;
 pub mod catbox;
+pub mod http;
+pub mod imgur;
+pub mod s3;
+
+use serde::{Deserialize, Serialize};
+use sha2::Digest as _;
+
+use cache::UploadCache;
+
+use crate::{data_fetching::preprocessing, util::HOME};
+
+/// Where preprocessed artwork is written to, since some hosts can only upload files.
+/// Each file is removed once it's been uploaded.
+static PREPROCESSED_DIRECTORY: std::sync::LazyLock<std::path::PathBuf> = std::sync::LazyLock::new(|| {
+    HOME.join("Library/Caches/am-osx-status/artwork")
+});
 
 #[derive(thiserror::Error, Debug)]
 pub enum UploadError {
     #[error("an unknown error occurred while uploading the custom track artwork")]
     UnknownError,
+    #[error("could not read the custom track artwork: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("request failure: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("could not find the url of the uploaded artwork in the response: {0}")]
+    BadResponse(String),
 }
 
-#[derive(thiserror::Error, Debug)]
-pub enum RetrievalError {
-    #[error("an unknown error occurred while retrieving the custom track artwork url")]
-    UnknownError,
+/// Where an upload ended up.
+#[derive(Debug)]
+pub struct Uploaded {
+    pub url: String,
+    /// `None` if the host keeps uploads around indefinitely.
+    pub expires_in: Option<chrono::Duration>,
 }
 
+#[async_trait::async_trait]
+pub trait CustomArtworkHost: core::fmt::Debug + Send {
+    /// Identifies where uploads go, so that URLs cached for one host (or bucket, server, etc.) are never used for another.
+    fn id(&self) -> String;
+    async fn upload(&mut self, artwork: &Artwork) -> Result<Uploaded, UploadError>;
+}
 
-#[derive(thiserror::Error, Debug)]
-pub enum CustomArtworkHostError {
-    #[error("{0}")]
-    UploadError(#[from] UploadError),
-    #[error("{0}")]
-    RetrievalError(#[from] RetrievalError)
+/// Where artwork that isn't available online (i.e. added by the user) is uploaded to, so backends can link to it.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Host {
+    /// Never upload custom artwork.
+    Disabled,
+    /// Anonymous uploads to Litterbox, which expire after an hour.
+    #[default]
+    Catbox,
+    /// A self-hosted server which accepts uploads over HTTP.
+    Http(http::Config),
+    /// An S3-compatible bucket, linked to with presigned URLs.
+    S3(s3::Config),
+    Imgur(imgur::Config),
 }
 
-#[async_trait::async_trait]
-pub trait CustomArtworkHost: core::fmt::Debug + Send {
-    async fn get_for_track(&self, track: &osa_apple_music::track::Track) -> Result<Option<String>, RetrievalError>;
-    async fn upload_for_track(&mut self, track: &osa_apple_music::track::Track, path: &str) -> Result<String, UploadError>;
-    async fn for_track(&mut self, track: &osa_apple_music::track::Track, path: &str) -> Result<String, CustomArtworkHostError> {
-        match self.get_for_track(track).await.map_err(CustomArtworkHostError::RetrievalError)? {
-            Some(url) => Ok(url),
-            None => self.upload_for_track(track, path).await.map_err(CustomArtworkHostError::UploadError)
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Config {
+    #[serde(flatten)]
+    pub host: Host,
+    /// Upload the artwork of the current track again shortly before its URL expires, so it doesn't break while playing.
+    #[serde(default = "crate::config::ret_true")]
+    pub refresh_before_expiry: bool,
+    #[serde(default)]
+    pub preprocessing: preprocessing::Config,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self { host: Host::default(), refresh_before_expiry: true, preprocessing: Default::default() }
+    }
+}
+impl Config {
+    pub async fn build(&self) -> Option<ArtworkUploader> {
+        let host: Box<dyn CustomArtworkHost> = match &self.host {
+            Host::Disabled => return None,
+            Host::Catbox => Box::new(catbox::CatboxHost::new()),
+            Host::Http(config) => Box::new(http::HttpHost::new(config.clone())),
+            Host::S3(config) => Box::new(s3::S3Host::new(config.clone())),
+            Host::Imgur(config) => Box::new(imgur::ImgurHost::new(config.clone())),
+        };
+        // left behind by uploads that were interrupted
+        if let Err(error) = tokio::fs::remove_dir_all(&*PREPROCESSED_DIRECTORY).await {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!(?error, "could not remove old preprocessed artwork");
+            }
+        }
+        Some(ArtworkUploader {
+            host,
+            cache: UploadCache::load(cache::DEFAULT_PATH.clone()).await,
+            refresh_before_expiry: self.refresh_before_expiry,
+            preprocessing: self.preprocessing,
+            current: None,
+        })
+    }
+}
+
+/// The contents of an artwork file, ready to be uploaded.
+#[derive(Debug)]
+pub struct Artwork {
+    pub path: String,
+    pub data: Vec<u8>,
+    /// Hex-encoded SHA-256 of the contents.
+    pub hash: String,
+    /// Derived from the contents, so the same artwork always gets the same name.
+    pub name: String,
+    pub content_type: &'static str,
+}
+impl Artwork {
+    async fn read(path: &str) -> Result<Self, std::io::Error> {
+        let data = tokio::fs::read(path).await?;
+        let extension = std::path::Path::new(path).extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let (extension, content_type) = match extension.as_deref() {
+            Some("png") => ("png", "image/png"),
+            Some("gif") => ("gif", "image/gif"),
+            Some("webp") => ("webp", "image/webp"),
+            Some("heic") => ("heic", "image/heic"),
+            _ => ("jpg", "image/jpeg"),
+        };
+        let hash = hex::encode(sha2::Sha256::digest(&data));
+        let name = format!("{hash}.{extension}");
+        Ok(Self { path: path.to_owned(), data, hash, name, content_type })
+    }
+
+    /// A preprocessed version of the artwork, written to disk for hosts which upload from a path.
+    async fn preprocessed(&self, config: preprocessing::Config) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let original = self.data.clone();
+        let data = tokio::task::spawn_blocking(move || config.process(&original)).await??;
+
+        let hash = hex::encode(sha2::Sha256::digest(&data));
+        let name = format!("{hash}.{}", config.format.extension());
+        let path = PREPROCESSED_DIRECTORY.join(&name);
+        tokio::fs::create_dir_all(&*PREPROCESSED_DIRECTORY).await?;
+        tokio::fs::write(&path, &data).await?;
+
+        Ok(Self {
+            path: path.to_string_lossy().into_owned(),
+            data,
+            hash,
+            name,
+            content_type: config.format.content_type(),
+        })
+    }
+}
+
+/// The artwork of the track that's currently playing.
+#[derive(Debug)]
+struct CurrentArtwork {
+    path: String,
+    hash: String,
+}
+
+/// Uploads artwork to the configured host, reusing earlier uploads of the same image while they're still available.
+#[derive(Debug)]
+pub struct ArtworkUploader {
+    host: Box<dyn CustomArtworkHost>,
+    cache: UploadCache,
+    refresh_before_expiry: bool,
+    preprocessing: preprocessing::Config,
+    current: Option<CurrentArtwork>,
+}
+impl ArtworkUploader {
+    /// URLs that expire sooner than this aren't handed out, since whoever receives them also needs time to fetch them.
+    const EXTERNAL_ACCESS_DELAY: chrono::Duration = chrono::Duration::seconds(5);
+    /// How long before the current artwork expires to upload it again.
+    const REFRESH_MARGIN: chrono::Duration = chrono::Duration::minutes(2);
+
+    /// The cache key of the artwork, which accounts for the preprocessing settings so changing them leads to a new upload.
+    fn cache_hash(&self, original: &Artwork) -> String {
+        if self.preprocessing.enabled {
+            format!("{}-{}", original.hash, self.preprocessing.fingerprint())
+        } else {
+            original.hash.clone()
+        }
+    }
+
+    /// Returns the URL of the artwork at `path`, uploading it if needed; it becomes the current artwork.
+    pub async fn for_path(&mut self, path: &str) -> Result<String, UploadError> {
+        let artwork = Artwork::read(path).await?;
+        let hash = self.cache_hash(&artwork);
+        self.current = Some(CurrentArtwork { path: path.to_owned(), hash: hash.clone() });
+
+        if let Some(url) = self.cache.get(&self.host.id(), &hash, Self::EXTERNAL_ACCESS_DELAY) {
+            return Ok(url.to_owned());
+        }
+        self.upload(artwork).await
+    }
+
+    async fn upload(&mut self, original: Artwork) -> Result<String, UploadError> {
+        let hash = self.cache_hash(&original);
+        let original_path = original.path.clone();
+        let artwork = if self.preprocessing.enabled {
+            original.preprocessed(self.preprocessing).await.unwrap_or_else(|error| {
+                tracing::warn!(?error, path = original.path, "couldn't preprocess artwork; uploading the original");
+                original
+            })
+        } else {
+            original
+        };
+        let uploaded = self.host.upload(&artwork).await;
+        if artwork.path != original_path {
+            if let Err(error) = tokio::fs::remove_file(&artwork.path).await {
+                tracing::warn!(?error, path = artwork.path, "could not remove preprocessed artwork");
+            }
+        }
+        let uploaded = uploaded?;
+        self.cache.insert(&self.host.id(), &hash, uploaded.url.clone(), uploaded.expires_in).await;
+        Ok(uploaded.url)
+    }
+
+    /// Called when the track changes, since the new one might not have custom artwork.
+    pub fn forget_current(&mut self) {
+        self.current = None;
+    }
+
+    /// If refreshing is enabled and the current artwork is about to expire, uploads it again and returns the new URL.
+    pub async fn refresh_current(&mut self) -> Option<String> {
+        if !self.refresh_before_expiry { return None }
+        let current = self.current.as_ref()?;
+        if self.cache.get(&self.host.id(), &current.hash, Self::REFRESH_MARGIN).is_some() { return None }
+
+        tracing::debug!(path = current.path, "current artwork is about to expire; uploading it again");
+        let path = current.path.clone();
+        let result = async {
+            let artwork = Artwork::read(&path).await?;
+            self.upload(artwork).await
+        }.await;
+        match result {
+            Ok(url) => Some(url),
+            Err(error) => {
+                tracing::error!(?error, "failed to upload custom artwork again");
+                // don't retry on every poll
+                self.current = None;
+                None
+            }
         }
     }
 }
@@ -1,8 +1,14 @@
 #![allow(unused)]
-use serde::Deserialize;
+use std::{collections::HashMap, path::PathBuf, sync::LazyLock};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
 
 use unicode_normalization::UnicodeNormalization;
 
+use crate::util::HOME;
+
 const ITUNES_SEARCH_BASE_URL: &str = "https://itunes.apple.com/search";
 
 fn normalize(string: &str) -> String {
@@ -38,28 +44,183 @@ async fn search_songs(query: &str) -> Result<Vec<ITunesStoreSong>, SongSearchErr
         .map_err(SongSearchError::Deserialization)
 }
 
-// TODO: Rank with numeric. With Levenshtein; after removing parentheses, ignoring album, stuff like that.
-fn does_track_match_search(track: &osa_apple_music::track::Track, found: &ITunesStoreSong) -> bool {
-    let name = normalize(&track.name);
-    let artist = normalize(&track.artist.clone().unwrap_or_default());
-    let collection = normalize(&track.album.name.clone().unwrap_or_default());
-
-    (
-        normalize(&found.name) == name ||
-        normalize(&found.name_censored) == name
-    )
-        && (normalize(&found.artist_name) == artist)
-        && (normalize(&found.collection_name) == collection)
+/// The song that best matches the track, preferring whichever of its censored and uncensored names is closer.
+fn best_match(track: &osa_apple_music::track::Track, songs: Vec<ITunesStoreSong>) -> Option<ITunesStoreSong> {
+    let query = metadata_match::Metadata {
+        title: &track.name,
+        artist: track.artist.as_deref(),
+        album: track.album.name.as_deref(),
+        duration: track.duration,
+    };
+    let matcher = metadata_match::Matcher::default();
+    matcher.best_by(songs, |song| {
+        let candidate = |title| metadata_match::Metadata {
+            title,
+            artist: Some(&song.artist_name),
+            album: Some(&song.collection_name),
+            duration: song.duration_millis.map(|millis| millis as f32 / 1000.),
+        };
+        matcher.score(&query, &candidate(&song.name)).max(matcher.score(&query, &candidate(&song.name_censored)))
+    }).map(|(song, score)| {
+        tracing::trace!(score, name = song.name, "itunes match");
+        song
+    })
 }
 
+/// Looks the track up in the cache, falling back to searching iTunes.
 pub async fn find_track(track: &osa_apple_music::track::Track) -> Result<Option<ITunesStoreSong>, SongSearchError> {
+    let keys = SearchCache::keys(track);
+    if let Some(cached) = CACHE.lock().await.get(&keys) {
+        tracing::trace!(found = cached.is_some(), "itunes cache hit");
+        return Ok(cached);
+    }
+
+    let found = search_track(track).await?;
+    let snapshot = CACHE.lock().await.insert(&keys, found.clone());
+    if let Some(snapshot) = snapshot {
+        snapshot.save().await;
+    }
+    Ok(found)
+}
+
+async fn search_track(track: &osa_apple_music::track::Track) -> Result<Option<ITunesStoreSong>, SongSearchError> {
     let query = format!("{} {}", track.artist.clone().unwrap_or_default(), track.name);
-    let songs = search_songs(&query).await?;
-    if songs.len() == 1 { return Ok(songs.into_iter().next()) }
-    Ok(songs.into_iter().find(|result| does_track_match_search(track, result)))
+    Ok(best_match(track, search_songs(&query).await?))
+}
+
+fn default_ttl() -> u64 {
+    7 * 24 * 60 * 60
+}
+fn default_negative_ttl() -> u64 {
+    24 * 60 * 60
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CacheConfig {
+    /// How long a found song is remembered, in seconds.
+    #[serde(default = "default_ttl")]
+    pub ttl: u64,
+    /// How long it's remembered that a song couldn't be found, in seconds.
+    #[serde(default = "default_negative_ttl")]
+    pub negative_ttl: u64,
+}
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { ttl: default_ttl(), negative_ttl: default_negative_ttl() }
+    }
+}
+
+pub static CACHE_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+    HOME.join("Library/Caches/am-osx-status/itunes.json")
+});
+
+/// Shared by everything that looks up songs, and loaded from disk on first use.
+static CACHE: LazyLock<Mutex<SearchCache>> = LazyLock::new(|| Mutex::new(SearchCache::load(CACHE_PATH.clone())));
+
+pub async fn configure_cache(config: CacheConfig) {
+    CACHE.lock().await.config = config;
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    /// `None` if the search didn't find the song.
+    song: Option<ITunesStoreSong>,
+    cached_at: DateTime<Utc>,
+}
+
+/// Search results, keyed both by persistent ID and by normalized metadata, so that the same song
+/// is found again even if it's re-added to the library (or played from somewhere else).
+#[derive(Debug)]
+struct SearchCache {
+    path: PathBuf,
+    config: CacheConfig,
+    entries: HashMap<String, CacheEntry>,
+}
+impl SearchCache {
+    fn load(path: PathBuf) -> Self {
+        let entries = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|error| {
+                tracing::warn!(?error, ?path, "itunes cache is corrupt; starting over");
+                HashMap::new()
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => {
+                tracing::warn!(?error, ?path, "could not read itunes cache");
+                HashMap::new()
+            }
+        };
+        // not pruned until the configured TTLs are known
+        Self { path, config: CacheConfig::default(), entries }
+    }
+
+    /// Taken while the cache is locked, and written once it's unlocked.
+    fn snapshot(&self) -> Option<CacheSnapshot> {
+        serde_json::to_vec(&self.entries)
+            .inspect_err(|error| tracing::warn!(?error, "could not serialize itunes cache"))
+            .ok()
+            .map(|data| CacheSnapshot { path: self.path.clone(), data })
+    }
+
+    fn keys(track: &osa_apple_music::track::Track) -> [String; 2] {
+        [
+            format!("id:{}", track.persistent_id),
+            format!("meta:{}\u{1f}{}\u{1f}{}",
+                normalize(track.artist.as_deref().unwrap_or_default()),
+                normalize(&track.name),
+                normalize(track.album.name.as_deref().unwrap_or_default()),
+            ),
+        ]
+    }
+
+    fn is_fresh(&self, entry: &CacheEntry) -> bool {
+        let ttl = if entry.song.is_some() { self.config.ttl } else { self.config.negative_ttl };
+        Utc::now() - entry.cached_at < TimeDelta::seconds(ttl as i64)
+    }
+
+    /// `Some(None)` means it's known that the song can't be found.
+    fn get(&self, keys: &[String]) -> Option<Option<ITunesStoreSong>> {
+        keys.iter()
+            .filter_map(|key| self.entries.get(key))
+            .find(|entry| self.is_fresh(entry))
+            .map(|entry| entry.song.clone())
+    }
+
+    /// Returns what should be written to disk.
+    #[must_use]
+    fn insert(&mut self, keys: &[String], song: Option<ITunesStoreSong>) -> Option<CacheSnapshot> {
+        self.prune();
+        let cached_at = Utc::now();
+        for key in keys {
+            self.entries.insert(key.clone(), CacheEntry { song: song.clone(), cached_at });
+        }
+        self.snapshot()
+    }
+
+    fn prune(&mut self) {
+        let entries = std::mem::take(&mut self.entries);
+        self.entries = entries.into_iter().filter(|(_, entry)| self.is_fresh(entry)).collect();
+    }
 }
 
-#[derive(Deserialize, Debug)]
+struct CacheSnapshot {
+    path: PathBuf,
+    data: Vec<u8>,
+}
+impl CacheSnapshot {
+    async fn save(self) {
+        let result = async {
+            if let Some(parent) = self.path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&self.path, self.data).await
+        }.await;
+        if let Err(error) = result {
+            tracing::warn!(?error, path = ?self.path, "could not save itunes cache");
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ITunesStoreSong {
     #[serde(rename = "artistViewUrl")]
@@ -80,6 +241,9 @@ pub struct ITunesStoreSong {
     #[serde(rename = "collectionCensoredName")]
     pub collection_name_censored: String,
     pub collection_name: String,
+
+    #[serde(rename = "trackTimeMillis", default)]
+    pub duration_millis: Option<u64>,
 }
 impl ITunesStoreSong {
     pub fn get_artwork_url_at_resolution(&self, resolution: u16) -> String {
@@ -87,3 +251,75 @@ impl ITunesStoreSong {
         self.artwork_preview_url.replace("100x100", &replacement)
     } 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song() -> ITunesStoreSong {
+        ITunesStoreSong {
+            artist_apple_music_url: None,
+            artist_name: "Artist".to_owned(),
+            name_censored: "Song".to_owned(),
+            name: "Song".to_owned(),
+            artwork_preview_url: "https://example.com/100x100bb.jpg".to_owned(),
+            apple_music_url: "https://music.apple.com/song".to_owned(),
+            collection_name_censored: "Album".to_owned(),
+            collection_name: "Album".to_owned(),
+            duration_millis: None,
+        }
+    }
+
+    fn cache(name: &str) -> SearchCache {
+        let path = std::env::temp_dir().join(format!("am-osx-status-itunes-{}-{name}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut cache = SearchCache::load(path);
+        cache.config = CacheConfig { ttl: 60 * 60, negative_ttl: 60 };
+        cache
+    }
+
+    fn age(cache: &mut SearchCache, seconds: i64) {
+        for entry in cache.entries.values_mut() {
+            entry.cached_at -= TimeDelta::seconds(seconds);
+        }
+    }
+
+    #[test]
+    fn found_songs_expire_after_the_ttl() {
+        let mut cache = cache("ttl");
+        let keys = ["id:found".to_owned(), "meta:found".to_owned()];
+        let _ = cache.insert(&keys, Some(song()));
+        assert!(matches!(cache.get(&keys), Some(Some(_))));
+
+        age(&mut cache, 30 * 60);
+        assert!(matches!(cache.get(&keys), Some(Some(_))));
+        age(&mut cache, 31 * 60);
+        assert!(cache.get(&keys).is_none());
+    }
+
+    #[test]
+    fn misses_expire_after_the_negative_ttl() {
+        let mut cache = cache("negative-ttl");
+        let keys = ["id:missing".to_owned(), "meta:missing".to_owned()];
+        let _ = cache.insert(&keys, None);
+        assert!(matches!(cache.get(&keys), Some(None)));
+
+        age(&mut cache, 61);
+        assert!(cache.get(&keys).is_none());
+
+        let _ = cache.insert(&["id:other".to_owned()], None);
+        assert!(!cache.entries.contains_key("id:missing"));
+    }
+
+    #[tokio::test]
+    async fn saved_entries_are_loaded_again() {
+        let mut cache = cache("save");
+        let keys = ["id:found".to_owned()];
+        cache.insert(&keys, Some(song())).expect("no snapshot").save().await;
+
+        let mut loaded = SearchCache::load(cache.path.clone());
+        loaded.config = cache.config;
+        assert_eq!(loaded.get(&keys).flatten().map(|song| song.name), Some("Song".to_owned()));
+        let _ = std::fs::remove_file(&cache.path);
+    }
+}
@@ -0,0 +1,67 @@
+//! Metadata from Last.fm, which knows many tracks that searching the iTunes store doesn't find.
+
+use std::sync::{Arc, RwLock};
+
+use lastfm::{auth::state::Unauthorized, track::Correction, Error, GeneralError, InfoQuery};
+
+use crate::normalization::ArtistCredit;
+
+/// Only set while the Last.fm backend is enabled, since every lookup tells the service what's playing.
+static CLIENT: RwLock<Option<Arc<lastfm::Client<Unauthorized>>>> = RwLock::new(None);
+
+/// Uses the identity of the backend, so lookups go to the same service that scrobbles are submitted to.
+pub fn configure(backend: Option<&crate::status_backend::lastfm::Config>) {
+    *CLIENT.write().expect("last.fm client poisoned") = backend
+        .filter(|backend| backend.enabled)
+        .map(|backend| Arc::new(lastfm::Client::new(backend.identity.clone())));
+}
+
+fn client() -> Option<Arc<lastfm::Client<Unauthorized>>> {
+    CLIENT.read().expect("last.fm client poisoned").clone()
+}
+
+const AUTOCORRECT: InfoQuery<'static> = InfoQuery { autocorrect: true, username: None };
+
+/// Last.fm reports unknown tracks, albums and artists with one of these, rather than with an empty response.
+fn none_if_unknown<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::General(GeneralError::MissingParameter | GeneralError::InvalidResource)) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// The cover of the track's album, or failing that, of the album Last.fm thinks the track is on.
+pub async fn find_artwork(track: &osa_apple_music::track::Track) -> Result<Option<String>, Error> {
+    let Some(client) = client() else { return Ok(None) };
+    let Some(artist) = track.artist.as_deref() else { return Ok(None) };
+
+    if let Some(album) = track.album.name.as_deref() {
+        let album_artist = track.album.artist.as_deref().unwrap_or(artist);
+        let album = crate::normalization::rules().album(album);
+        let info = none_if_unknown(client.get_album_info(album_artist, &album, AUTOCORRECT).await)?;
+        if let Some(url) = info.as_ref().and_then(|info| lastfm::image::largest(&info.images)) {
+            return Ok(Some(url.to_owned()));
+        }
+    }
+
+    let info = none_if_unknown(client.get_track_info(artist, &track.name, AUTOCORRECT).await)?;
+    Ok(info.as_ref()
+        .and_then(|info| info.album.as_ref())
+        .and_then(|album| lastfm::image::largest(&album.images))
+        .map(str::to_owned))
+}
+
+/// Last.fm mostly lists a placeholder for artists nowadays, in which case this is `None`.
+pub async fn find_artist_image(artist: &str) -> Result<Option<String>, Error> {
+    let Some(client) = client() else { return Ok(None) };
+    let info = none_if_unknown(client.get_artist_info(artist, AUTOCORRECT).await)?;
+    Ok(info.as_ref().and_then(|info| lastfm::image::largest(&info.images)).map(str::to_owned))
+}
+
+/// The canonical names of the track (by its primary artist, after normalization), if Last.fm knows its names as a misspelling.
+pub async fn find_correction(track: &osa_apple_music::track::Track) -> Result<Option<Correction>, Error> {
+    let Some(client) = client() else { return Ok(None) };
+    let Some(names) = crate::normalization::rules().track_names(track, ArtistCredit::Primary) else { return Ok(None) };
+    Ok(none_if_unknown(client.get_track_correction(&names.artist, &names.track).await)?.flatten())
+}
@@ -4,3 +4,7 @@ pub mod spotify;
 pub mod artworkd;
 pub mod musicdb;
 pub mod custom_artwork_host;
+pub mod musicbrainz;
+pub mod cover_art_archive;
+#[cfg(feature = "lastfm")]
+pub mod lastfm;
@@ -0,0 +1,81 @@
+use std::sync::LazyLock;
+
+use brainz::music::{request_client::ProgramInfo, search::{RecordingQuery, SearchError}, Artist, Id, Recording, Release, ReleaseGroup};
+
+use crate::util::REPOSITORY_URL;
+
+/// Identifies requests to MetaBrainz services, which they require.
+pub static PROGRAM_INFO: ProgramInfo<&str> = ProgramInfo {
+    name: clap::crate_name!(),
+    version: Some(clap::crate_version!()),
+    contact: REPOSITORY_URL,
+};
+
+static CLIENT: LazyLock<brainz::music::Client<&'static str>> = LazyLock::new(|| brainz::music::Client::new(PROGRAM_INFO.clone()));
+
+/// The MusicBrainz entities a track was matched to.
+#[derive(Debug, Clone)]
+pub struct RecordingIds {
+    pub recording: Id<Recording>,
+    /// `None` if the recording isn't on any release with a title similar to the track's album.
+    pub release: Option<Id<Release>>,
+    pub release_group: Option<Id<ReleaseGroup>>,
+    pub artists: Vec<Id<Artist>>,
+}
+
+/// Searches MusicBrainz for the recording of the track, and picks the release that best matches its album.
+pub async fn find_recording(track: &osa_apple_music::track::Track) -> Result<Option<RecordingIds>, SearchError> {
+    find_recording_of(metadata_match::Metadata {
+        title: &track.name,
+        artist: track.artist.as_deref(),
+        album: track.album.name.as_deref(),
+        duration: track.duration,
+    }).await
+}
+
+/// Like [`find_recording`], for tracks that aren't playing (e.g. those read from the library in bulk).
+pub async fn find_recording_of(wanted: metadata_match::Metadata<'_>) -> Result<Option<RecordingIds>, SearchError> {
+    let Some(artist) = wanted.artist else { return Ok(None) };
+    // decorations like "(Remastered 2011)" are rarely part of the title on MusicBrainz, and would fail the phrase search
+    let title = metadata_match::strip_decorations(wanted.title);
+    let album = wanted.album.map(metadata_match::strip_release_suffix).map(metadata_match::strip_decorations);
+    let rules = crate::normalization::rules();
+    let query = RecordingQuery {
+        title: &title,
+        artist: rules.artist(artist, crate::normalization::ArtistCredit::Primary),
+        release: album.as_deref(),
+        duration: wanted.duration.map(core::time::Duration::from_secs_f32),
+        isrc: None, // not exposed by Apple Music
+    };
+    let recordings = CLIENT.search_recordings(&query, 25).await?;
+
+    let credits = recordings.iter().map(|recording| recording.artist_credit_name()).collect::<Vec<_>>();
+    // every release of every recording is a candidate, as well as every recording on its own
+    let candidates = recordings.iter().enumerate().flat_map(|(index, recording)| {
+        recording.releases.iter().map(Some).chain([None]).map(move |release| (index, release))
+    });
+    let matcher = metadata_match::Matcher::default();
+    let best = matcher.best_by(candidates, |(index, release)| {
+        let recording = &recordings[*index];
+        let found = metadata_match::Metadata {
+            title: &recording.title,
+            artist: Some(&credits[*index]),
+            album: release.map(|release| release.title.as_str()),
+            duration: recording.length.map(|millis| millis as f32 / 1000.),
+        };
+        let score = matcher.score(&wanted, &found);
+        // without a release, the album can't count against the recording, so it mustn't count for it either
+        if release.is_none() { score.min(matcher.threshold) } else { score }
+    });
+
+    Ok(best.map(|((index, release), score)| {
+        let recording = &recordings[index];
+        tracing::trace!(score, id = %recording.id, "musicbrainz match");
+        RecordingIds {
+            recording: recording.id,
+            release: release.map(|release| release.id),
+            release_group: release.and_then(|release| release.release_group.as_ref()).map(|group| group.id),
+            artists: recording.artist_ids(),
+        }
+    }))
+}
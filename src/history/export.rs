@@ -0,0 +1,169 @@
+//! Writing the local history out in formats other tools understand.
+
+use serde::Serialize;
+
+use super::store::HistoryEntry;
+use crate::cli::ExportFormat;
+
+const CSV_HEADER: [&str; 9] = ["started_at", "ended_at", "artist", "track", "album", "album_artist", "genre", "duration", "heard"];
+
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        std::borrow::Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        std::borrow::Cow::Borrowed(value)
+    }
+}
+
+fn to_csv(entries: &[&HistoryEntry]) -> String {
+    let mut out = CSV_HEADER.join(",");
+    out.push('\n');
+    for entry in entries {
+        let row = [
+            entry.started_at.to_rfc3339(),
+            entry.ended_at.to_rfc3339(),
+            entry.artist.clone().unwrap_or_default(),
+            entry.track.clone(),
+            entry.album.clone().unwrap_or_default(),
+            entry.album_artist.clone().unwrap_or_default(),
+            entry.genre.clone().unwrap_or_default(),
+            entry.duration.map(|duration| duration.to_string()).unwrap_or_default(),
+            entry.heard.to_string(),
+        ];
+        out.push_str(&row.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// - <https://listenbrainz.readthedocs.io/en/latest/users/json.html>
+#[derive(Serialize)]
+struct ListenBrainzListen<'a> {
+    listened_at: i64,
+    track_metadata: ListenBrainzTrackMetadata<'a>,
+}
+
+#[derive(Serialize)]
+struct ListenBrainzTrackMetadata<'a> {
+    artist_name: &'a str,
+    track_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release_name: Option<&'a str>,
+    additional_info: ListenBrainzAdditionalInfo<'a>,
+}
+
+#[derive(Serialize)]
+struct ListenBrainzAdditionalInfo<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    media_player: &'static str,
+    music_service: &'static str,
+    submission_client: &'static str,
+    submission_client_version: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<[&'a str; 1]>,
+}
+
+/// Entries without an artist are left out, since ListenBrainz requires one, and so are skips,
+/// since ListenBrainz imports everything in the dump as a listen.
+fn to_listenbrainz(entries: &[&HistoryEntry]) -> String {
+    let listens = entries.iter().filter(|entry| entry.is_listen()).filter_map(|entry| Some(ListenBrainzListen {
+        listened_at: entry.started_at.timestamp(),
+        track_metadata: ListenBrainzTrackMetadata {
+            artist_name: entry.artist.as_deref()?,
+            track_name: &entry.track,
+            release_name: entry.album.as_deref(),
+            additional_info: ListenBrainzAdditionalInfo {
+                duration_ms: entry.duration.map(|duration| (duration * 1000.) as u64),
+                media_player: "Apple Music",
+                music_service: "music.apple.com",
+                submission_client: clap::crate_name!(),
+                submission_client_version: clap::crate_version!(),
+                tags: entry.genre.as_deref().map(|genre| [genre]),
+            },
+        },
+    })).collect::<Vec<_>>();
+    serde_json::to_string_pretty(&listens).expect("listens are always serializable")
+}
+
+pub fn export(entries: &[&HistoryEntry], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Csv => to_csv(entries),
+        ExportFormat::Json => serde_json::to_string_pretty(entries).expect("history entries are always serializable"),
+        ExportFormat::Jsonl => entries.iter()
+            .map(|entry| serde_json::to_string(entry).expect("history entries are always serializable") + "\n")
+            .collect(),
+        ExportFormat::ListenBrainz => to_listenbrainz(entries),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(track: &str, artist: Option<&str>, heard: f32) -> HistoryEntry {
+        HistoryEntry {
+            started_at: "2024-05-01T12:00:00Z".parse().unwrap(),
+            ended_at: "2024-05-01T12:03:20Z".parse().unwrap(),
+            persistent_id: "0123456789ABCDEF".to_owned(),
+            track: track.to_owned(),
+            artist: artist.map(str::to_owned),
+            album: Some("Album".to_owned()),
+            album_artist: None,
+            genre: Some("Rock".to_owned()),
+            duration: Some(200.),
+            heard,
+            covered: None,
+        }
+    }
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("12\" Mix"), "\"12\"\" Mix\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn writes_csv() {
+        let entry = entry("Song, Part 2", Some("Artist"), 200.);
+        let csv = export(&[&entry], ExportFormat::Csv);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("started_at,ended_at,artist,track,album,album_artist,genre,duration,heard"));
+        assert_eq!(
+            lines.next(),
+            Some("2024-05-01T12:00:00+00:00,2024-05-01T12:03:20+00:00,Artist,\"Song, Part 2\",Album,,Rock,200,200"),
+        );
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn writes_listenbrainz_listens() {
+        let listen = entry("Song", Some("Artist"), 200.);
+        let no_artist = entry("Song", None, 200.);
+        let skip = entry("Skipped", Some("Artist"), 10.);
+        let json: serde_json::Value = serde_json::from_str(&export(&[&listen, &no_artist, &skip], ExportFormat::ListenBrainz)).unwrap();
+
+        let listens = json.as_array().unwrap();
+        assert_eq!(listens.len(), 1);
+        assert_eq!(listens[0]["listened_at"], 1714564800);
+        let metadata = &listens[0]["track_metadata"];
+        assert_eq!(metadata["artist_name"], "Artist");
+        assert_eq!(metadata["track_name"], "Song");
+        assert_eq!(metadata["release_name"], "Album");
+        assert_eq!(metadata["additional_info"]["duration_ms"], 200000);
+        assert_eq!(metadata["additional_info"]["tags"], serde_json::json!(["Rock"]));
+        assert_eq!(metadata["additional_info"]["submission_client"], clap::crate_name!());
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let entries = [entry("One", Some("Artist"), 200.), entry("Two", Some("Artist"), 10.)];
+        let jsonl = export(&entries.iter().collect::<Vec<_>>(), ExportFormat::Jsonl);
+        let tracks = jsonl.lines()
+            .map(|line| serde_json::from_str::<HistoryEntry>(line).unwrap().track)
+            .collect::<Vec<_>>();
+        assert_eq!(tracks, ["One", "Two"]);
+    }
+}
@@ -0,0 +1,327 @@
+//! Backfilling plays that the Apple Music library already knows about.
+//!
+//! The library only keeps a play count and the date of the *most recent* play for each track,
+//! so only that latest play can be imported; earlier plays are counted but have to be skipped.
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{cli::HistoryService, config::Config, util::{ferror, HOME}};
+
+/// The number of pending plays shown per service when previewing an import.
+const PREVIEW_LIMIT: usize = 20;
+
+/// How far apart (on top of the track length) an already-recorded listen can be from a library play
+/// while still being considered the same play.
+///
+/// The library records when a play *finished*, whereas live submissions are timestamped when the track
+/// started (ListenBrainz) or when it was found to be finished (Last.fm), so an exact match can't be expected.
+const DUPLICATE_LEEWAY: TimeDelta = TimeDelta::minutes(2);
+
+/// Used in place of the track length when the library doesn't know it.
+const ASSUMED_LENGTH: TimeDelta = TimeDelta::minutes(10);
+
+fn seconds(secs: f32) -> TimeDelta {
+    TimeDelta::milliseconds((secs * 1000.) as i64)
+}
+
+#[derive(Debug)]
+struct DatedPlay {
+    artist: String,
+    track: String,
+    album: Option<String>,
+    album_artist: Option<String>,
+    duration: Option<f32>,
+    /// When the play started; derived from when the library says it was last played.
+    started_at: DateTime<Utc>,
+}
+impl DatedPlay {
+    fn length(&self) -> TimeDelta {
+        self.duration.map(seconds).unwrap_or(ASSUMED_LENGTH)
+    }
+
+    /// Whether a listen that has already been recorded is plausibly this same play.
+    /// The track name may have been recorded as it is in the library, or as it was after normalization.
+    fn is_recorded_as(&self, normalization: &crate::normalization::Config, track: &str, at: DateTime<Utc>) -> bool {
+        let track = track.to_lowercase();
+        (self.track.to_lowercase() == track || normalization.title(&self.track).to_lowercase() == track) &&
+        (at - self.started_at).abs() <= self.length() + DUPLICATE_LEEWAY
+    }
+
+    /// The names to submit, after normalization and any remembered corrections.
+    async fn names(&self, normalization: &crate::normalization::Config, credit: crate::normalization::ArtistCredit, corrected: bool) -> crate::corrections::TrackNames {
+        let mut names = normalization.names(&self.artist, &self.track, self.album.as_deref(), self.album_artist.as_deref(), credit);
+        if corrected {
+            crate::corrections::apply(&mut names).await;
+        }
+        names
+    }
+}
+
+struct LibraryHistory {
+    /// Sorted from oldest to newest.
+    plays: Vec<DatedPlay>,
+    /// Plays that happened before the latest play of their track, which have no recorded date.
+    undated: u64,
+    /// Plays of tracks that are missing data required for submission (the artist name or last played date).
+    incomplete: u64,
+}
+impl LibraryHistory {
+    async fn read() -> Self {
+        let mut session = osa_apple_music::Session::new(
+            HOME.join("Library/Application Support/am-osx-status/osa-history-socket")
+        ).await.unwrap_or_else(|error| ferror!("failed to create `osa_apple_music` session: {error}"));
+
+        let tracks = session.library_play_history().await
+            .unwrap_or_else(|error| ferror!("could not read library play history: {error}"));
+
+        let mut history = Self { plays: Vec::new(), undated: 0, incomplete: 0 };
+        for track in tracks {
+            if track.played.times == 0 { continue }
+            let (Some(artist), Some(last)) = (track.artist, track.played.last) else {
+                history.incomplete += track.played.times as u64;
+                continue;
+            };
+
+            history.undated += (track.played.times - 1) as u64;
+            history.plays.push(DatedPlay {
+                artist,
+                track: track.name,
+                album: track.album,
+                album_artist: track.album_artist,
+                duration: track.duration,
+                started_at: last - track.duration.map(seconds).unwrap_or_default(),
+            });
+        }
+
+        history.plays.sort_by_key(|play| play.started_at);
+        history
+    }
+
+    /// The plays from `since` onwards which haven't already been recorded, along with how many were considered.
+    fn pending<'a>(&'a self, normalization: &crate::normalization::Config, since: DateTime<Utc>, recorded: &[(DateTime<Utc>, String)]) -> (Vec<&'a DatedPlay>, usize) {
+        let considered = self.plays.iter().filter(|play| play.started_at >= since).collect::<Vec<_>>();
+        let count = considered.len();
+        let pending = considered.into_iter()
+            .filter(|play| !recorded.iter().any(|(at, track)| play.is_recorded_as(normalization, track, *at)))
+            .collect();
+        (pending, count)
+    }
+
+    /// The range of time that already-recorded listens need to be retrieved from, to find duplicates of plays from `since` onwards.
+    fn lookup_range(&self, since: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let first = self.plays.iter().find(|play| play.started_at >= since)?;
+        let last = self.plays.last()?;
+        Some((
+            first.started_at - ASSUMED_LENGTH - DUPLICATE_LEEWAY,
+            last.started_at + ASSUMED_LENGTH + DUPLICATE_LEEWAY
+        ))
+    }
+}
+
+fn preview(service: &str, pending: &[&DatedPlay], considered: usize, dry_run: bool) {
+    println!("{service}: {} of {considered} eligible plays have not been recorded yet", pending.len());
+    if !dry_run { return }
+    for play in pending.iter().take(PREVIEW_LIMIT) {
+        println!(
+            "  {}  {} — {}",
+            play.started_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+            play.artist,
+            play.track
+        );
+    }
+    if pending.len() > PREVIEW_LIMIT {
+        println!("  … and {} more", pending.len() - PREVIEW_LIMIT);
+    }
+}
+
+/// The requested services, or every enabled service if none were requested; exits if that leaves none.
+pub fn resolve_services(config: &Config<'_>, requested: &[HistoryService]) -> Vec<HistoryService> {
+    let services = if requested.is_empty() {
+        let mut enabled = Vec::with_capacity(2);
+        #[cfg(feature = "listenbrainz")]
+        if config.backends.listenbrainz.as_ref().is_some_and(|backend| backend.enabled) {
+            enabled.push(HistoryService::ListenBrainz);
+        }
+        #[cfg(feature = "lastfm")]
+        if config.backends.lastfm.as_ref().is_some_and(|backend| backend.enabled) {
+            enabled.push(HistoryService::LastFM);
+        }
+        enabled
+    } else {
+        requested.to_vec()
+    };
+
+    if services.is_empty() {
+        ferror!("there are no enabled services to use")
+    }
+    services
+}
+
+pub async fn run(config: &Config<'_>, dry_run: bool, services: &[HistoryService]) {
+    let services = resolve_services(config, services);
+
+    let history = LibraryHistory::read().await;
+    println!(
+        "Found {} dated plays in the library; {} earlier plays have no recorded date and {} are missing required data, so they will be skipped.",
+        history.plays.len(),
+        history.undated,
+        history.incomplete,
+    );
+
+    for service in services {
+        match service {
+            #[cfg(feature = "listenbrainz")]
+            HistoryService::ListenBrainz => import_into_listenbrainz(config, &history, dry_run).await,
+            #[cfg(feature = "lastfm")]
+            HistoryService::LastFM => import_into_lastfm(config, &history, dry_run).await,
+        }
+    }
+}
+
+/// A client for the configured ListenBrainz account, and the name of that account; exits if there isn't a usable one.
+#[cfg(feature = "listenbrainz")]
+pub async fn listenbrainz_client(config: &Config<'_>) -> (brainz::listen::v1::Client<maybe_owned_string::MaybeOwnedStringDeserializeToOwned<'static>>, String) {
+    use brainz::listen::v1::{self as listenbrainz, token_validity::TokenValidity};
+
+    let Some(backend) = config.backends.listenbrainz.as_ref().filter(|backend| backend.enabled) else {
+        ferror!("ListenBrainz is not enabled")
+    };
+    let Some(token) = backend.user_token.clone() else {
+        ferror!("ListenBrainz has no user token configured")
+    };
+    let username = match listenbrainz::UserToken::check_validity(&token).await {
+        Ok(TokenValidity::Valid { username }) => username,
+        Ok(TokenValidity::Invalid) => ferror!("the configured ListenBrainz user token is no longer valid"),
+        Err(error) => ferror!("could not validate ListenBrainz user token: {error}"),
+    };
+
+    (listenbrainz::Client::new(backend.program_info.clone(), Some(token)), username)
+}
+
+#[cfg(feature = "listenbrainz")]
+async fn import_into_listenbrainz(config: &Config<'_>, history: &LibraryHistory, dry_run: bool) {
+    use brainz::listen::{constants::LISTEN_MINIMUM_DATE, v1::submit_listens::{additional_info::*, BasicTrackMetadata, ImportedListen}};
+
+    let (client, username) = listenbrainz_client(config).await;
+
+    let mut recorded = Vec::new();
+    if let Some((min_ts, max_ts)) = history.lookup_range(LISTEN_MINIMUM_DATE) {
+        let listens = client.get_all_listens(&username, Some(min_ts), Some(max_ts)).await
+            .unwrap_or_else(|error| ferror!("could not retrieve existing ListenBrainz listens: {error}"));
+        recorded.extend(listens.into_iter().filter_map(|listen| Some((listen.listened_at()?, listen.track_metadata.track_name))));
+    }
+
+    let (pending, considered) = history.pending(&config.normalization, LISTEN_MINIMUM_DATE, &recorded);
+    preview("ListenBrainz", &pending, considered, dry_run);
+    if dry_run || pending.is_empty() { return }
+
+    let backend = config.backends.listenbrainz.as_ref().expect("client was created from the backend configuration");
+    let mut names = Vec::with_capacity(pending.len());
+    for play in &pending {
+        names.push(play.names(&config.normalization, backend.artists, backend.apply_corrections).await);
+    }
+    let listens = pending.iter().zip(&names).map(|(play, names)| ImportedListen {
+        track: BasicTrackMetadata {
+            artist: &names.artist,
+            track: &names.track,
+            release: names.album.as_deref(),
+        },
+        listened_at: play.started_at,
+        extra: Some(AdditionalInfo {
+            duration: play.duration.map(core::time::Duration::from_secs_f32),
+            submission_client: Some(client.get_program_info()),
+            music_service: Some(MusicService::Domain("music.apple.com")),
+            media_player: Some(MediaPlayer { name: "Apple Music", version: None }),
+            ..Default::default()
+        }),
+    });
+
+    match client.import_listens(listens).await {
+        Ok(count) => println!("ListenBrainz: imported {count} listens"),
+        Err(error) => ferror!("ListenBrainz import failed: {error}"),
+    }
+}
+
+/// A client for the configured Last.fm account, and the name of that account; exits if there isn't a usable one.
+#[cfg(feature = "lastfm")]
+pub async fn lastfm_client(config: &Config<'_>) -> (lastfm::Client<lastfm::auth::state::Authorized>, String) {
+    let Some(backend) = config.backends.lastfm.as_ref().filter(|backend| backend.enabled) else {
+        ferror!("Last.fm is not enabled")
+    };
+    let Some(session_key) = backend.session_key.clone() else {
+        ferror!("Last.fm has no session key configured")
+    };
+
+    let client = lastfm::Client::authorized(backend.identity.clone(), session_key);
+    let username = client.get_authenticated_username().await
+        .unwrap_or_else(|error| ferror!("could not retrieve Last.fm username: {error}"));
+
+    (client, username)
+}
+
+#[cfg(feature = "lastfm")]
+async fn import_into_lastfm(config: &Config<'_>, history: &LibraryHistory, dry_run: bool) {
+    use lastfm::{scrobble::{HeardTrackInfo, Scrobble, MAX_SCROBBLES_PER_REQUEST, MAX_SCROBBLE_AGE}, user::{RecentTracksQuery, MAX_RECENT_TRACKS_PER_PAGE}};
+
+    let (client, username) = lastfm_client(config).await;
+
+    // Leave some room for the time it takes to finish importing.
+    let since = Utc::now() - MAX_SCROBBLE_AGE + TimeDelta::hours(1);
+
+    let mut recorded = Vec::new();
+    if let Some((from, to)) = history.lookup_range(since) {
+        let mut page = 1;
+        loop {
+            let response = client.get_recent_tracks(&username, RecentTracksQuery {
+                from: Some(from),
+                to: Some(to),
+                page: Some(page),
+                limit: Some(MAX_RECENT_TRACKS_PER_PAGE),
+            }).await.unwrap_or_else(|error| ferror!("could not retrieve existing Last.fm scrobbles: {error}"));
+
+            recorded.extend(response.tracks.into_iter().filter_map(|track| Some((track.scrobbled_at?, track.track))));
+            if page >= response.total_pages { break }
+            page += 1;
+        }
+    }
+
+    let (pending, considered) = history.pending(&config.normalization, since, &recorded);
+    preview("Last.fm", &pending, considered, dry_run);
+    if dry_run || pending.is_empty() { return }
+
+    let backend = config.backends.lastfm.as_ref().expect("client was created from the backend configuration");
+    let mut names = Vec::with_capacity(pending.len());
+    for play in &pending {
+        let mut play_names = play.names(&config.normalization, backend.artists, true).await;
+        // only sent if != track artist
+        if play.album_artist.as_ref() == Some(&play.artist) { play_names.album_artist = None }
+        names.push(play_names);
+    }
+
+    let (mut accepted, mut ignored) = (0, 0);
+    for chunk in pending.iter().zip(&names).collect::<Vec<_>>().chunks(MAX_SCROBBLES_PER_REQUEST) {
+        let scrobbles = chunk.iter().map(|(play, names)| Scrobble {
+            info: HeardTrackInfo {
+                artist: &names.artist,
+                track: &names.track,
+                album: names.album.as_deref(),
+                album_artist: names.album_artist.as_deref(),
+                duration_in_seconds: play.duration.map(|duration| duration as u32),
+                track_number: None,
+                mbid: None,
+            },
+            timestamp: play.started_at,
+            chosen_by_user: None,
+        }).collect::<Vec<_>>();
+
+        match client.scrobble(&scrobbles).await {
+            Ok(response) => {
+                accepted += response.counts.accepted;
+                ignored += response.counts.ignored;
+            },
+            Err(error) => ferror!("Last.fm import failed after {accepted} scrobbles: {error}"),
+        }
+    }
+
+    println!("Last.fm: imported {accepted} scrobbles ({ignored} ignored)");
+}
@@ -0,0 +1,8 @@
+//! Working with listening history outside of the live polling loop.
+
+pub mod import;
+pub mod store;
+pub mod export;
+pub mod stats;
+#[cfg(feature = "listenbrainz")]
+pub mod remote_stats;
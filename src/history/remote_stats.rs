@@ -0,0 +1,43 @@
+//! Statistics computed by the services listens are submitted to, which (unlike the local history) include plays from other devices.
+
+use brainz::listen::v1::{listens::ListenRetrievalError, stats::{Ranking, StatsQuery, StatsRange}};
+
+use crate::{config::Config, util::ferror};
+
+fn print_ranking<T>(heading: &str, ranking: Option<Ranking<T>>, describe: impl Fn(&T) -> (String, u64)) {
+    let Some(ranking) = ranking else { return };
+    if ranking.entries.is_empty() { return }
+    println!("\n{heading} ({} in total)", ranking.total);
+    for (index, entry) in ranking.entries.iter().enumerate() {
+        let (name, listens) = describe(entry);
+        println!("{:>4}. {name} ({listens} listens)", ranking.offset as usize + index + 1);
+    }
+}
+
+pub async fn print_listenbrainz(config: &Config<'_>, range: StatsRange, limit: usize) {
+    let (client, username) = super::import::listenbrainz_client(config).await;
+    let query = StatsQuery { range, count: Some(limit.min(u16::MAX as usize) as u16), offset: None };
+
+    fn fail<T>(error: ListenRetrievalError) -> T {
+        ferror!("could not retrieve ListenBrainz statistics: {error}")
+    }
+    let count = client.get_listen_count(&username).await.unwrap_or_else(fail);
+    let playing_now = client.get_playing_now(&username).await.unwrap_or_else(fail);
+    let artists = client.get_top_artists(&username, query).await.unwrap_or_else(fail);
+    let releases = client.get_top_releases(&username, query).await.unwrap_or_else(fail);
+    let recordings = client.get_top_recordings(&username, query).await.unwrap_or_else(fail);
+
+    println!("{username} on ListenBrainz ({})", range.to_str());
+    println!("{count} listens in total");
+    if let Some(track) = playing_now {
+        println!("Playing now: {} by {}", track.track_name, track.artist_name);
+    }
+
+    if artists.is_none() && releases.is_none() && recordings.is_none() {
+        println!("\nListenBrainz hasn't computed statistics for this range yet");
+        return;
+    }
+    print_ranking("Top Artists", artists, |artist| (artist.artist_name.clone(), artist.listen_count));
+    print_ranking("Top Releases", releases, |release| (format!("{} by {}", release.release_name, release.artist_name), release.listen_count));
+    print_ranking("Top Recordings", recordings, |recording| (format!("{} by {}", recording.track_name, recording.artist_name), recording.listen_count));
+}
@@ -0,0 +1,405 @@
+//! Summaries of the local history.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike as _, Local, NaiveDate, TimeDelta, Timelike as _, Utc};
+
+use super::store::HistoryEntry;
+
+/// The fraction of a track that has to be heard for its play to count as completed.
+const COMPLETION_THRESHOLD: f32 = 0.9;
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+#[derive(Debug, Clone)]
+pub struct Ranked {
+    pub name: String,
+    pub plays: usize,
+    pub time: TimeDelta,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Streak {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+impl Streak {
+    pub fn days(&self) -> i64 {
+        (self.end - self.start).num_days() + 1
+    }
+}
+
+/// How plays ended, judged by how much of the track was covered.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Completion {
+    /// At least [`COMPLETION_THRESHOLD`] of the track was heard.
+    pub completed: usize,
+    /// Counted as a listen, but not heard to completion.
+    pub partial: usize,
+    /// Stopped before counting as a listen.
+    pub skipped: usize,
+}
+impl Completion {
+    pub fn total(&self) -> usize {
+        self.completed + self.partial + self.skipped
+    }
+
+    pub fn skip_rate(&self) -> f32 {
+        if self.total() == 0 { return 0. }
+        self.skipped as f32 / self.total() as f32
+    }
+}
+
+#[derive(Debug)]
+pub struct Statistics {
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+    /// Plays that counted as a listen.
+    pub listens: usize,
+    /// Time spent listening, including skipped plays.
+    pub time: TimeDelta,
+    pub top_artists: Vec<Ranked>,
+    pub top_albums: Vec<Ranked>,
+    pub top_tracks: Vec<Ranked>,
+    pub top_genres: Vec<Ranked>,
+    /// Time spent listening per hour of the day, in local time.
+    pub by_hour: [TimeDelta; 24],
+    /// Time spent listening per day of the week (starting on Monday), in local time.
+    pub by_weekday: [TimeDelta; 7],
+    /// The longest run of consecutive days with at least one listen.
+    pub longest_streak: Option<Streak>,
+    pub completion: Completion,
+}
+impl Statistics {
+    /// Rankings only consider plays that counted as a listen; everything else considers all plays.
+    pub fn compute(entries: &[&HistoryEntry], limit: usize) -> Self {
+        let mut artists = HashMap::<String, Ranked>::new();
+        let mut albums = HashMap::<String, Ranked>::new();
+        let mut tracks = HashMap::<String, Ranked>::new();
+        let mut genres = HashMap::<String, Ranked>::new();
+        let mut by_hour = [TimeDelta::zero(); 24];
+        let mut by_weekday = [TimeDelta::zero(); 7];
+        let mut days = Vec::new();
+        let mut completion = Completion::default();
+        let mut time = TimeDelta::zero();
+        let mut listens = 0;
+
+        fn tally(map: &mut HashMap<String, Ranked>, name: String, heard: TimeDelta) {
+            let ranked = map.entry(name.to_lowercase()).or_insert_with(|| Ranked { name, plays: 0, time: TimeDelta::zero() });
+            ranked.plays += 1;
+            ranked.time += heard;
+        }
+
+        for entry in entries {
+            let heard = entry.heard();
+            let local = entry.started_at.with_timezone(&Local);
+            time += heard;
+            by_hour[local.hour() as usize] += heard;
+            by_weekday[local.weekday().num_days_from_monday() as usize] += heard;
+
+            if !entry.is_listen() {
+                completion.skipped += 1;
+                continue;
+            }
+
+            listens += 1;
+            if entry.coverage().is_some_and(|coverage| coverage >= COMPLETION_THRESHOLD) {
+                completion.completed += 1;
+            } else {
+                completion.partial += 1;
+            }
+
+            days.push(local.date_naive());
+            let artist = entry.artist.clone().unwrap_or_else(|| "Unknown Artist".to_owned());
+            tally(&mut tracks, format!("{artist} — {}", entry.track), heard);
+            if let Some(album) = &entry.album {
+                tally(&mut albums, format!("{} — {album}", entry.album_artist.as_ref().unwrap_or(&artist)), heard);
+            }
+            if let Some(genre) = &entry.genre {
+                tally(&mut genres, genre.clone(), heard);
+            }
+            tally(&mut artists, artist, heard);
+        }
+
+        fn top(map: HashMap<String, Ranked>, limit: usize) -> Vec<Ranked> {
+            let mut ranked = map.into_values().collect::<Vec<_>>();
+            ranked.sort_by(|a, b| b.plays.cmp(&a.plays).then(b.time.cmp(&a.time)).then_with(|| a.name.cmp(&b.name)));
+            ranked.truncate(limit);
+            ranked
+        }
+
+        days.sort_unstable();
+        days.dedup();
+        let mut longest_streak: Option<Streak> = None;
+        let mut current: Option<Streak> = None;
+        for day in days {
+            current = match current {
+                Some(streak) if streak.end.succ_opt() == Some(day) => Some(Streak { start: streak.start, end: day }),
+                _ => Some(Streak { start: day, end: day }),
+            };
+            if longest_streak.is_none_or(|longest| current.unwrap().days() > longest.days()) {
+                longest_streak = current;
+            }
+        }
+
+        Self {
+            first: entries.iter().map(|entry| entry.started_at).min(),
+            last: entries.iter().map(|entry| entry.ended_at).max(),
+            listens,
+            time,
+            top_artists: top(artists, limit),
+            top_albums: top(albums, limit),
+            top_tracks: top(tracks, limit),
+            top_genres: top(genres, limit),
+            by_hour,
+            by_weekday,
+            longest_streak,
+            completion,
+        }
+    }
+
+    fn rankings(&self) -> [(&'static str, &[Ranked]); 4] {
+        [
+            ("Top Artists", &self.top_artists),
+            ("Top Albums", &self.top_albums),
+            ("Top Tracks", &self.top_tracks),
+            ("Top Genres", &self.top_genres),
+        ]
+    }
+
+    fn period(&self) -> String {
+        match (self.first, self.last) {
+            (Some(first), Some(last)) => format!(
+                "{} to {}",
+                first.with_timezone(&Local).format("%Y-%m-%d"),
+                last.with_timezone(&Local).format("%Y-%m-%d")
+            ),
+            _ => "no listening history".to_owned(),
+        }
+    }
+
+    pub fn to_html(&self, title: &str) -> String {
+        use std::fmt::Write as _;
+
+        let mut html = String::new();
+        let _ = write!(html, concat!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n",
+            "<style>\n",
+            "body {{ font-family: -apple-system, sans-serif; max-width: 48rem; margin: 2rem auto; padding: 0 1rem; color: #1d1d1f; }}\n",
+            "h1 {{ margin-bottom: 0; }} .period {{ color: #6e6e73; margin-top: 0.25rem; }}\n",
+            ".summary {{ display: flex; gap: 2rem; }} .summary div {{ font-size: 1.5rem; font-weight: 600; }} .summary span {{ display: block; font-size: 0.8rem; font-weight: 400; color: #6e6e73; }}\n",
+            "ol {{ padding-left: 1.5rem; }} li span {{ color: #6e6e73; }}\n",
+            ".bars {{ display: flex; align-items: flex-end; gap: 2px; height: 6rem; }} .bars div {{ flex: 1; background: #fa243c; min-height: 1px; }}\n",
+            ".labels {{ display: flex; gap: 2px; font-size: 0.7rem; color: #6e6e73; }} .labels span {{ flex: 1; text-align: center; }}\n",
+            "</style>\n</head>\n<body>\n"
+        ), title = escape(title));
+
+        let _ = writeln!(html, "<h1>{}</h1>\n<p class=\"period\">{}</p>", escape(title), escape(&self.period()));
+        let _ = writeln!(
+            html,
+            "<section class=\"summary\"><div>{}<span>listens</span></div><div>{}<span>listened</span></div><div>{}<span>longest streak</span></div><div>{:.0}%<span>skipped</span></div></section>",
+            self.listens,
+            format_duration(self.time),
+            self.longest_streak.map(|streak| format!("{} days", streak.days())).unwrap_or_else(|| "—".to_owned()),
+            self.completion.skip_rate() * 100.,
+        );
+
+        for (heading, ranked) in self.rankings() {
+            if ranked.is_empty() { continue }
+            let _ = writeln!(html, "<h2>{heading}</h2>\n<ol>");
+            for entry in ranked {
+                let _ = writeln!(html, "<li>{} <span>{} plays · {}</span></li>", escape(&entry.name), entry.plays, format_duration(entry.time));
+            }
+            let _ = writeln!(html, "</ol>");
+        }
+
+        fn bars(html: &mut String, heading: &str, values: &[TimeDelta], labels: impl Iterator<Item = String>) {
+            let max = values.iter().max().copied().unwrap_or_default().num_seconds().max(1) as f64;
+            let _ = writeln!(html, "<h2>{heading}</h2>\n<div class=\"bars\">");
+            for value in values {
+                let _ = writeln!(html, "<div style=\"height: {:.1}%\" title=\"{}\"></div>", value.num_seconds() as f64 / max * 100., format_duration(*value));
+            }
+            let _ = writeln!(html, "</div>\n<div class=\"labels\">");
+            for label in labels {
+                let _ = writeln!(html, "<span>{label}</span>");
+            }
+            let _ = writeln!(html, "</div>");
+        }
+
+        bars(&mut html, "By Hour", &self.by_hour, (0..24).map(|hour| hour.to_string()));
+        bars(&mut html, "By Weekday", &self.by_weekday, WEEKDAYS.iter().map(|day| day.to_string()));
+
+        let _ = writeln!(
+            html,
+            "<h2>Completion</h2>\n<p>{} completed · {} partially heard · {} skipped</p>\n</body>\n</html>",
+            self.completion.completed,
+            self.completion.partial,
+            self.completion.skipped,
+        );
+        html
+    }
+}
+impl core::fmt::Display for Statistics {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "{}", self.period())?;
+        writeln!(f, "{} listens, {} listened", self.listens, format_duration(self.time))?;
+
+        for (heading, ranked) in self.rankings() {
+            if ranked.is_empty() { continue }
+            writeln!(f, "\n{heading}")?;
+            for (index, entry) in ranked.iter().enumerate() {
+                writeln!(f, "{:>4}. {} ({} plays, {})", index + 1, entry.name, entry.plays, format_duration(entry.time))?;
+            }
+        }
+
+        fn bars(f: &mut core::fmt::Formatter<'_>, heading: &str, values: &[TimeDelta], labels: impl Iterator<Item = String>) -> core::fmt::Result {
+            const WIDTH: f64 = 30.;
+            let max = values.iter().max().copied().unwrap_or_default().num_seconds().max(1) as f64;
+            writeln!(f, "\n{heading}")?;
+            for (value, label) in values.iter().zip(labels) {
+                let bar = "█".repeat((value.num_seconds() as f64 / max * WIDTH).round() as usize);
+                writeln!(f, "{label:>5} {bar} {}", format_duration(*value))?;
+            }
+            Ok(())
+        }
+
+        bars(f, "By Hour", &self.by_hour, (0..24).map(|hour| format!("{hour:02}:00")))?;
+        bars(f, "By Weekday", &self.by_weekday, WEEKDAYS.iter().map(|day| day.to_string()))?;
+
+        writeln!(f, "\nStreaks")?;
+        match self.longest_streak {
+            Some(streak) => writeln!(f, "  longest: {} days ({} to {})", streak.days(), streak.start, streak.end)?,
+            None => writeln!(f, "  none yet")?,
+        }
+
+        writeln!(f, "\nCompletion")?;
+        writeln!(
+            f,
+            "  {} completed, {} partially heard, {} skipped ({:.0}% skip rate)",
+            self.completion.completed,
+            self.completion.partial,
+            self.completion.skipped,
+            self.completion.skip_rate() * 100.,
+        )
+    }
+}
+
+fn format_duration(delta: TimeDelta) -> String {
+    let minutes = delta.num_minutes();
+    if minutes >= 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    /// A play of a 200 second track on the given day of May 2024, at noon so it's the same day in any local time zone.
+    fn play(day: u32, artist: &str, track: &str, heard: f32) -> HistoryEntry {
+        let started_at = Utc.with_ymd_and_hms(2024, 5, day, 12, 0, 0).unwrap();
+        HistoryEntry {
+            started_at,
+            ended_at: started_at + TimeDelta::seconds(heard as i64),
+            persistent_id: format!("{artist}-{track}"),
+            track: track.to_owned(),
+            artist: Some(artist.to_owned()),
+            album: None,
+            album_artist: None,
+            genre: None,
+            duration: Some(200.),
+            heard,
+            covered: None,
+        }
+    }
+
+    fn compute(entries: &[HistoryEntry]) -> Statistics {
+        Statistics::compute(&entries.iter().collect::<Vec<_>>(), 10)
+    }
+
+    #[test]
+    fn splits_plays_by_completion() {
+        let stats = compute(&[
+            play(1, "A", "Completed", 190.),
+            play(1, "A", "Partial", 120.),
+            play(1, "A", "Skipped", 30.),
+            play(1, "A", "Skipped Again", 10.),
+        ]);
+        assert_eq!(stats.listens, 2);
+        assert_eq!((stats.completion.completed, stats.completion.partial, stats.completion.skipped), (1, 1, 2));
+        assert_eq!(stats.completion.skip_rate(), 0.5);
+        assert_eq!(stats.time, TimeDelta::seconds(350));
+        // skips count towards time, but not towards rankings
+        assert_eq!(stats.top_tracks.len(), 2);
+    }
+
+    #[test]
+    fn merges_names_case_insensitively() {
+        let stats = compute(&[
+            play(1, "Queen", "Bohemian Rhapsody", 200.),
+            play(1, "QUEEN", "bohemian rhapsody", 200.),
+        ]);
+        assert_eq!(stats.top_artists.len(), 1);
+        assert_eq!(stats.top_artists[0].name, "Queen");
+        assert_eq!(stats.top_artists[0].plays, 2);
+        assert_eq!(stats.top_tracks.len(), 1);
+        assert_eq!(stats.top_tracks[0].name, "Queen — Bohemian Rhapsody");
+    }
+
+    #[test]
+    fn ranks_by_plays_then_time_then_name() {
+        let stats = compute(&[
+            play(1, "Most Plays", "1", 100.),
+            play(1, "Most Plays", "2", 100.),
+            play(1, "B", "1", 200.),
+            play(1, "A", "1", 200.),
+            play(1, "Less Time", "1", 150.),
+        ]);
+        let names = stats.top_artists.iter().map(|ranked| ranked.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["Most Plays", "A", "B", "Less Time"]);
+
+        let limited = Statistics::compute(&[&play(1, "A", "1", 200.), &play(1, "B", "1", 200.)], 1);
+        assert_eq!(limited.top_artists.len(), 1);
+    }
+
+    #[test]
+    fn finds_the_longest_streak() {
+        let stats = compute(&[
+            play(1, "A", "1", 200.),
+            play(2, "A", "1", 200.),
+            play(5, "A", "1", 200.),
+            play(6, "A", "1", 200.),
+            play(6, "A", "2", 200.),
+            play(7, "A", "1", 200.),
+            // skips don't keep a streak going
+            play(8, "A", "1", 10.),
+            play(9, "A", "1", 200.),
+        ]);
+        let streak = stats.longest_streak.unwrap();
+        assert_eq!(streak.days(), 3);
+        assert_eq!((streak.start.day(), streak.end.day()), (5, 7));
+
+        assert!(compute(&[play(1, "A", "1", 10.)]).longest_streak.is_none());
+    }
+
+    #[test]
+    fn escapes_html() {
+        assert_eq!(escape(r#"<b>"Tom & Jerry"</b>"#), "&lt;b&gt;&quot;Tom &amp; Jerry&quot;&lt;/b&gt;");
+
+        let html = compute(&[play(1, "<script>", "1", 200.)]).to_html("Me & You");
+        assert!(html.contains("<title>Me &amp; You</title>"));
+        assert!(html.contains("&lt;script&gt; <span>"));
+        assert!(!html.contains("<script>"));
+    }
+}
@@ -0,0 +1,219 @@
+//! The local record of everything that has been listened to, kept as JSON lines so it's trivial to inspect or back up.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt as _;
+
+use crate::util::HOME;
+
+pub static DEFAULT_PATH: std::sync::LazyLock<PathBuf> = std::sync::LazyLock::new(|| {
+    HOME.join("Library/Application Support/am-osx-status/history.jsonl")
+});
+
+/// A single play of a track, regardless of how much of it was heard.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the track started playing.
+    pub started_at: DateTime<Utc>,
+    /// When the track stopped playing (or was replaced by another).
+    pub ended_at: DateTime<Utc>,
+    pub persistent_id: String,
+    pub track: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub genre: Option<String>,
+    /// The length of the track, in seconds.
+    pub duration: Option<f32>,
+    /// How much time was spent listening to the track, in seconds.
+    pub heard: f32,
+    /// How much of the track was covered, in seconds; parts heard more than once (from seeking back) are only counted once.
+    #[serde(default)]
+    pub covered: Option<f32>,
+}
+impl HistoryEntry {
+    /// Whether the play was substantial enough to count as a listen, using the same rules as ListenBrainz and Last.fm:
+    /// at least half of the track or four minutes of it, whichever comes first.
+    pub fn is_listen(&self) -> bool {
+        const FOUR_MINUTES: f32 = 4. * 60.;
+        self.heard >= FOUR_MINUTES || self.duration.is_some_and(|duration| self.heard >= duration / 2.)
+    }
+
+    /// The fraction of the track that was heard, if its length is known.
+    pub fn coverage(&self) -> Option<f32> {
+        let duration = self.duration.filter(|duration| *duration > 0.)?;
+        Some((self.covered.unwrap_or(self.heard) / duration).min(1.))
+    }
+
+    pub fn heard(&self) -> TimeDelta {
+        TimeDelta::milliseconds((self.heard * 1000.) as i64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    path: PathBuf,
+}
+impl Default for HistoryStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_PATH.clone())
+    }
+}
+impl HistoryStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    pub async fn append(&self, entry: &HistoryEntry) -> Result<(), std::io::Error> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut line = serde_json::to_vec(entry).expect("history entries are always serializable");
+        line.push(b'\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path).await?;
+        file.write_all(&line).await
+    }
+
+    /// Reads every entry, oldest first.
+    /// Lines that can't be understood (such as one cut off by a crash mid-write) are logged and skipped.
+    pub async fn read_all(&self) -> Result<Vec<HistoryEntry>, std::io::Error> {
+        let data = match tokio::fs::read_to_string(&self.path).await {
+            Ok(data) => data,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error),
+        };
+
+        let mut entries = data.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(index, line)| match serde_json::from_str::<HistoryEntry>(line) {
+                Ok(entry) => Some(entry),
+                Err(error) => {
+                    tracing::warn!(?error, line = index + 1, "skipping malformed history entry");
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        entries.sort_by_key(|entry| entry.started_at);
+        Ok(entries)
+    }
+}
+
+/// Narrows down which entries of the history are of interest.
+#[derive(Debug, Default, Clone)]
+pub struct HistoryFilter {
+    /// Only entries that started at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only entries that started before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Only entries by any of these artists (case-insensitive); all artists if empty.
+    pub artists: Vec<String>,
+    /// Also include entries that don't count as a listen (see [`HistoryEntry::is_listen`]).
+    pub include_skips: bool,
+}
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        self.since.is_none_or(|since| entry.started_at >= since) &&
+        self.until.is_none_or(|until| entry.started_at < until) &&
+        (self.artists.is_empty() || entry.artist.as_ref().is_some_and(|artist| {
+            self.artists.iter().any(|wanted| wanted.to_lowercase() == artist.to_lowercase())
+        })) &&
+        (self.include_skips || entry.is_listen())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(started_at: &str, artist: Option<&str>, duration: Option<f32>, heard: f32) -> HistoryEntry {
+        let started_at: DateTime<Utc> = started_at.parse().unwrap();
+        HistoryEntry {
+            started_at,
+            ended_at: started_at + TimeDelta::seconds(heard as i64),
+            persistent_id: "0123456789ABCDEF".to_owned(),
+            track: "Song".to_owned(),
+            artist: artist.map(str::to_owned),
+            album: None,
+            album_artist: None,
+            genre: None,
+            duration,
+            heard,
+            covered: None,
+        }
+    }
+
+    #[test]
+    fn counts_listens_like_scrobblers() {
+        assert!(entry("2024-05-01T12:00:00Z", None, Some(200.), 100.).is_listen());
+        assert!(!entry("2024-05-01T12:00:00Z", None, Some(200.), 99.).is_listen());
+        // four minutes is enough for long tracks
+        assert!(entry("2024-05-01T12:00:00Z", None, Some(3600.), 240.).is_listen());
+        assert!(!entry("2024-05-01T12:00:00Z", None, None, 239.).is_listen());
+        assert!(entry("2024-05-01T12:00:00Z", None, None, 240.).is_listen());
+    }
+
+    #[test]
+    fn measures_coverage() {
+        assert_eq!(entry("2024-05-01T12:00:00Z", None, Some(200.), 50.).coverage(), Some(0.25));
+        assert_eq!(entry("2024-05-01T12:00:00Z", None, None, 50.).coverage(), None);
+        assert_eq!(entry("2024-05-01T12:00:00Z", None, Some(0.), 50.).coverage(), None);
+
+        let mut replayed = entry("2024-05-01T12:00:00Z", None, Some(200.), 300.);
+        assert_eq!(replayed.coverage(), Some(1.));
+        replayed.covered = Some(150.);
+        assert_eq!(replayed.coverage(), Some(0.75));
+    }
+
+    #[test]
+    fn filters_entries() {
+        let listen = entry("2024-05-01T12:00:00Z", Some("Queen"), Some(200.), 200.);
+        let skip = entry("2024-05-01T12:00:00Z", Some("Queen"), Some(200.), 10.);
+        assert!(HistoryFilter::default().matches(&listen));
+        assert!(!HistoryFilter::default().matches(&skip));
+        assert!(HistoryFilter { include_skips: true, ..Default::default() }.matches(&skip));
+
+        let may = HistoryFilter {
+            since: Some("2024-05-01T00:00:00Z".parse().unwrap()),
+            until: Some("2024-05-01T12:00:00Z".parse().unwrap()),
+            ..Default::default()
+        };
+        assert!(!may.matches(&listen), "the end is exclusive");
+        assert!(HistoryFilter { until: None, ..may }.matches(&listen));
+
+        let artists = HistoryFilter { artists: vec!["queen".to_owned(), "Muse".to_owned()], ..Default::default() };
+        assert!(artists.matches(&listen));
+        assert!(!artists.matches(&entry("2024-05-01T12:00:00Z", Some("Queens of the Stone Age"), Some(200.), 200.)));
+        assert!(!artists.matches(&entry("2024-05-01T12:00:00Z", None, Some(200.), 200.)));
+    }
+
+    #[tokio::test]
+    async fn reads_back_what_was_appended() {
+        let path = std::env::temp_dir().join(format!("am-osx-status-history-{}-round-trip/history.jsonl", std::process::id()));
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+        let store = HistoryStore::new(path.clone());
+        assert!(store.read_all().await.unwrap().is_empty());
+
+        store.append(&entry("2024-05-02T12:00:00Z", Some("Later"), Some(200.), 200.)).await.unwrap();
+        store.append(&entry("2024-05-01T12:00:00Z", Some("Earlier"), Some(200.), 200.)).await.unwrap();
+        // cut off by a crash mid-write
+        std::io::Write::write_all(&mut std::fs::OpenOptions::new().append(true).open(&path).unwrap(), b"{\"started_at\":\"2024-05-03").unwrap();
+
+        let entries = store.read_all().await.unwrap();
+        let artists = entries.iter().map(|entry| entry.artist.as_deref().unwrap()).collect::<Vec<_>>();
+        assert_eq!(artists, ["Earlier", "Later"]);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
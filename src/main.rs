@@ -14,6 +14,10 @@ mod debugging;
 mod data_fetching;
 mod service;
 mod config;
+mod history;
+mod sync;
+mod corrections;
+mod normalization;
 mod cli;
 mod util;
 
@@ -203,12 +207,145 @@ async fn main() -> ExitCode {
                 ConfigurationAction::Discord { action } => {
                     let mut config = get_config_or_error!();
                     match action {
-                        DiscordConfigurationAction::Enable => config.backends.discord = true,
-                        DiscordConfigurationAction::Disable => config.backends.discord = false
+                        DiscordConfigurationAction::Enable => config.backends.discord.enabled = true,
+                        DiscordConfigurationAction::Disable => config.backends.discord.enabled = false,
+                        DiscordConfigurationAction::Preview => {
+                            use status_backend::discord::template::Placeholders;
+                            normalization::configure(config.normalization.clone());
+
+                            let mut jxa = osa_apple_music::Session::new(
+                                crate::util::HOME.join("Library/Application Support/am-osx-status/osa-preview-socket")
+                            ).await.ok();
+                            let track = match jxa.as_mut() {
+                                Some(jxa) => jxa.now_playing().await.ok().flatten(),
+                                None => None,
+                            };
+
+                            let placeholders = match &track {
+                                Some(track) => {
+                                    println!("Previewing with the current track, \"{}\":\n", track.name);
+                                    Placeholders::from_track(track, None)
+                                },
+                                None => {
+                                    println!("Nothing is playing; previewing with a sample track:\n");
+                                    Placeholders::sample()
+                                }
+                            };
+
+                            match config.backends.discord.template.render(&placeholders) {
+                                Ok(rendered) => print!("{rendered}"),
+                                Err(error) => ferror!("the discord presence template is invalid: {error}"),
+                            }
+                            if !config.backends.discord.enabled {
+                                println!("\n(the Discord presence is currently disabled)");
+                            }
+                            return ExitCode::SUCCESS;
+                        }
                     };
                     config.save_to_disk().await;
                 }
             }
+        },
+        Command::Sync { ref action } => {
+            tokio::spawn(async {
+                pending_term.await;
+                std::process::exit(1);
+            });
+
+            use cli::SyncAction;
+
+            match action {
+                SyncAction::Feedback { dry_run, services } => {
+                    let config = get_config_or_error!();
+                    sync::feedback(&config, *dry_run, services).await;
+                }
+            }
+        },
+        Command::Corrections { ref action } => {
+            use cli::CorrectionsAction;
+
+            match action {
+                CorrectionsAction::List { field } => corrections::list(*field),
+                CorrectionsAction::Set { field, from, to, artist } => corrections::set(*field, artist.as_deref(), from, to).await,
+                CorrectionsAction::Remove { field, from, artist } => corrections::remove(*field, artist.as_deref(), from).await,
+            }
+        },
+        Command::History { ref action } => {
+            tokio::spawn(async {
+                pending_term.await;
+                std::process::exit(1);
+            });
+
+            use cli::HistoryAction;
+
+            match action {
+                HistoryAction::Import { dry_run, services } => {
+                    let config = get_config_or_error!();
+                    history::import::run(&config, *dry_run, services).await;
+                }
+                HistoryAction::Export { format, output, filter, include_skips } => {
+                    let entries = history::store::HistoryStore::default().read_all().await
+                        .unwrap_or_else(|error| ferror!("could not read listening history: {error}"));
+                    let filter = filter.to_filter(*include_skips);
+                    let entries = entries.iter().filter(|entry| filter.matches(entry)).collect::<Vec<_>>();
+                    let exported = history::export::export(&entries, *format);
+                    match output {
+                        Some(path) => {
+                            tokio::fs::write(path, exported).await.unwrap_or_else(|error| ferror!("could not write export: {error}"));
+                            eprintln!("Exported {} plays to {}", entries.len(), path.to_string_lossy());
+                        },
+                        None => print!("{exported}"),
+                    }
+                }
+            }
+        },
+        Command::Stats { ref action, ref filter, limit } => {
+            use cli::{RecapPeriod, StatsAction};
+            use history::{stats::Statistics, store::HistoryStore};
+
+            let read_entries = || async {
+                HistoryStore::default().read_all().await
+                    .unwrap_or_else(|error| ferror!("could not read listening history: {error}"))
+            };
+
+            match action {
+                None => {
+                    let entries = read_entries().await;
+                    let filter = filter.to_filter(true);
+                    let entries = entries.iter().filter(|entry| filter.matches(entry)).collect::<Vec<_>>();
+                    print!("{}", Statistics::compute(&entries, limit));
+                },
+                Some(StatsAction::Report { period, ending, html, output, artists, limit }) => {
+                    let ending = ending.unwrap_or_else(chrono::Utc::now);
+                    let (since, title) = match period {
+                        RecapPeriod::Week => (ending - chrono::TimeDelta::weeks(1), "Weekly Recap"),
+                        RecapPeriod::Month => (ending.checked_sub_months(chrono::Months::new(1)).expect("date out of range"), "Monthly Recap"),
+                    };
+                    let filter = history::store::HistoryFilter {
+                        since: Some(since),
+                        until: Some(ending),
+                        artists: artists.clone(),
+                        include_skips: true,
+                    };
+
+                    let entries = read_entries().await;
+                    let entries = entries.iter().filter(|entry| filter.matches(entry)).collect::<Vec<_>>();
+                    let statistics = Statistics::compute(&entries, *limit);
+                    let report = if *html { statistics.to_html(title) } else { format!("{title}\n{statistics}") };
+                    match output {
+                        Some(path) => {
+                            tokio::fs::write(path, report).await.unwrap_or_else(|error| ferror!("could not write report: {error}"));
+                            eprintln!("Saved report to {}", path.to_string_lossy());
+                        },
+                        None => print!("{report}"),
+                    }
+                },
+                #[cfg(feature = "listenbrainz")]
+                Some(StatsAction::ListenBrainz { range, limit }) => {
+                    let config = get_config_or_error!();
+                    history::remote_stats::print_listenbrainz(&config, (*range).into(), *limit).await;
+                },
+            }
         }
     }
 
@@ -220,27 +357,37 @@ struct PollingContext<'a> {
     terminating: Arc<AtomicBool>,
     backends: status_backend::StatusBackends,
     pub last_track: Option<Arc<osa_apple_music::track::Track>>,
+    /// What was fetched for [`Self::last_track`] when it started, for backends to use once it ends.
+    last_track_data: Arc<data_fetching::AdditionalTrackData>,
     pub listened: Arc<Mutex<Listened>>,
-    custom_artwork_host: Option<Box<dyn data_fetching::services::custom_artwork_host::CustomArtworkHost>>,
+    custom_artwork_host: Option<data_fetching::services::custom_artwork_host::ArtworkUploader>,
     musicdb: Option<musicdb::MusicDB<'a>>,
     jxa: osa_apple_music::Session,
     /// The number of polls.
     /// A value of one means the first poll is ongoing; it's not zero-based because it's incremented at the start of the poll function.
     polls: u64,
+    /// Whether backends have been told that the player is paused.
+    paused: bool,
     /// Sequential `PlayerState::Paused` occurrences.
     /// Used to detect when the state is *actually* considered paused, since sometimes the paused state is returned during buffer.
     sequential_pause_states: u64
 }
 impl PollingContext<'_> {
     async fn from_config(config: &config::Config<'_>, terminating: Arc<AtomicBool>) -> Self {
+        data_fetching::services::itunes::configure_cache(config.itunes_cache).await;
+        normalization::configure(config.normalization.clone());
+        #[cfg(feature = "lastfm")]
+        data_fetching::services::lastfm::configure(config.backends.lastfm.as_ref());
         Self {
             terminating,
             backends: status_backend::StatusBackends::new(config).await,
             last_track: None,
+            last_track_data: Default::default(),
             listened: Arc::new(Mutex::new(Listened::new())),
-            custom_artwork_host: Some(Box::new(data_fetching::services::custom_artwork_host::catbox::CatboxHost::new())),
+            custom_artwork_host: config.artwork_host.build().await,
             musicdb: Some(tracing::trace_span!("musicdb read").in_scope(MusicDB::default)),
             polls: 0,
+            paused: false,
             sequential_pause_states: 0,
             jxa: osa_apple_music::Session::new(
                 crate::util::HOME.join("Library/Application Support/am-osx-status/osa-socket")
@@ -250,6 +397,11 @@ impl PollingContext<'_> {
 
     async fn reload_from_config(&mut self, config: &config::Config<'_>) {
         self.backends = status_backend::StatusBackends::new(config).await;;
+        self.custom_artwork_host = config.artwork_host.build().await;
+        data_fetching::services::itunes::configure_cache(config.itunes_cache).await;
+        normalization::configure(config.normalization.clone());
+        #[cfg(feature = "lastfm")]
+        data_fetching::services::lastfm::configure(config.backends.lastfm.as_ref());
     }
 
     pub fn is_terminating(&self) -> bool {
@@ -290,13 +442,11 @@ async fn proc_once(mut context: Arc<Mutex<PollingContext<'_>>>) {
     match app.state {
         PlayerState::FastForwarding | PlayerState::Rewinding => unimplemented!(),
         PlayerState::Stopped => {
-            #[cfg(feature = "discord")]
-            if let Some(presence) = context.backends.discord.clone() {
-                if let Err(error) = presence.lock().await.clear().await {
-                    tracing::error!(?error, "unable to clear discord status")
-                }
+            if context.last_track.is_some() || context.paused {
+                context.backends.dispatch_stopped().await;
             }
-            
+            context.paused = false;
+
             context.listened.lock().await.flush_current();
             
             if let Some(previous) = context.last_track.clone() {
@@ -307,7 +457,7 @@ async fn proc_once(mut context: Arc<Mutex<PollingContext<'_>>>) {
                     listened,
                     track: previous,
                     app: app.clone(),
-                    data: ().into(),
+                    data: std::mem::take(&mut context.last_track_data),
                 }).await;
             }
         }
@@ -317,14 +467,14 @@ async fn proc_once(mut context: Arc<Mutex<PollingContext<'_>>>) {
             const THRESHOLD_CONSIDER_TRULY_PAUSED: u64 = 3;
 
             if context.sequential_pause_states >= THRESHOLD_CONSIDER_TRULY_PAUSED {
-                #[cfg(feature = "discord")]
-                if let Some(presence) = context.backends.discord.clone() {
-                    if let Err(error) = presence.lock().await.clear().await {
-                        tracing::error!(?error, "unable to clear discord status")
+                context.listened.lock().await.flush_current();
+
+                if !context.paused {
+                    context.paused = true;
+                    if context.last_track.is_some() {
+                        context.backends.dispatch_paused().await;
                     }
                 }
-
-                context.listened.lock().await.flush_current();
             }
         },
 
@@ -356,7 +506,21 @@ async fn proc_once(mut context: Arc<Mutex<PollingContext<'_>>>) {
                 return;
             }
 
+            // favoriting or disliking the current track doesn't make it a new one, so it's checked for separately
+            if let Some(last) = context.last_track.as_ref().filter(|last| last.persistent_id == track.persistent_id) {
+                if last.favorited != track.favorited || last.disliked != track.disliked {
+                    context.last_track = Some(track.clone());
+                    context.backends.dispatch_feedback_changed(BackendContext {
+                        app: app.clone(),
+                        track: track.clone(),
+                        listened: context.listened.clone(),
+                        data: context.last_track_data.clone(),
+                    }).await;
+                }
+            }
+
             let previous = context.last_track.as_ref().map(|v| &v.persistent_id);
+            let resumed = std::mem::take(&mut context.paused);
             if previous != Some(&track.persistent_id) {
                 tracing::trace!(?track, "new track");
                 
@@ -368,7 +532,7 @@ async fn proc_once(mut context: Arc<Mutex<PollingContext<'_>>>) {
                         app: app.clone(),
                         track: previous,
                         listened: context.listened.clone(),
-                        data: ().into(),
+                        data: context.last_track_data.clone(),
                     }).instrument(tracing::trace_span!("song end dispatch"));
 
                     async move { 
@@ -385,11 +549,23 @@ async fn proc_once(mut context: Arc<Mutex<PollingContext<'_>>>) {
                 let listened = Arc::new(Mutex::new(Listened::new_with_current(app.position.or(track.playable_range.as_ref().map(|r| r.start)).unwrap_or(0.))));
                 context.listened = listened.clone();
                 context.last_track = Some(track.clone());
-                context.backends.dispatch_track_started(BackendContext { app, listened, track, data: Arc::new(additional_data) }).await;
+                context.last_track_data = Arc::new(additional_data);
+                context.backends.dispatch_track_started(BackendContext { app, listened, track, data: context.last_track_data.clone() }).await;
             } else if let Some(position) = app.position {
                 let mut listened = context.listened.lock().await;
                 match listened.current.as_ref() {
-                    None => listened.set_new_current(position),
+                    None => {
+                        listened.set_new_current(position);
+                        drop(listened); // give up lock
+                        if resumed {
+                            context.backends.dispatch_resumed(BackendContext {
+                                track: track.clone(),
+                                app: app.clone(),
+                                data: ().into(),
+                                listened: context.listened.clone()
+                            }).await;
+                        }
+                    },
                     Some(current) => {
                         let expected = current.get_expected_song_position();
                         if (expected - position).abs() >= 2. {
@@ -406,6 +582,12 @@ async fn proc_once(mut context: Arc<Mutex<PollingContext<'_>>>) {
                     }
                 }
             }
+
+            if let Some(host) = context.custom_artwork_host.as_mut() {
+                if let Some(url) = host.refresh_current().await {
+                    context.backends.dispatch_artwork_refreshed(url).await;
+                }
+            }
         }
     }
 }
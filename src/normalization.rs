@@ -0,0 +1,121 @@
+//! Cleanup of names before they're submitted, shared by every backend so that they all agree on what a track is called.
+//!
+//! The local history keeps names exactly as they are in the library; the rules are applied when it's imported elsewhere.
+
+use std::sync::{Arc, LazyLock, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::corrections::TrackNames;
+
+/// Which of several credited artists a backend submits.
+/// Each backend has its own default, based on how the service credits tracks.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtistCredit {
+    /// Only the first artist, e.g. `Queen` for `Queen & David Bowie`.
+    Primary,
+    /// The artists exactly as credited.
+    All,
+}
+impl ArtistCredit {
+    pub fn primary() -> Self {
+        Self::Primary
+    }
+
+    pub fn all() -> Self {
+        Self::All
+    }
+}
+
+/// A regular expression, written in the configuration as a string.
+#[derive(Clone, Debug)]
+pub struct Pattern(regex::Regex);
+impl Pattern {
+    fn new(pattern: &str) -> Self {
+        Self(regex::Regex::new(pattern).expect("bad built-in pattern"))
+    }
+}
+impl Serialize for Pattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        regex::Regex::new(&pattern).map(Self).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Config {
+    /// Where a credit is split into several artists; matched case-insensitively.
+    pub artist_separators: Vec<String>,
+    /// Artists whose names contain a separator, but which are a single artist.
+    pub artist_exceptions: Vec<String>,
+    /// Matches are removed from track names.
+    pub title_patterns: Vec<Pattern>,
+    /// Matches are removed from album names.
+    pub album_patterns: Vec<Pattern>,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            artist_separators: metadata_match::DEFAULT_ARTIST_SEPARATORS.iter().copied().map(str::to_owned).collect(),
+            artist_exceptions: metadata_match::DEFAULT_ARTIST_EXCEPTIONS.iter().copied().map(str::to_owned).collect(),
+            title_patterns: metadata_match::DEFAULT_TITLE_PATTERNS.iter().copied().map(Pattern::new).collect(),
+            album_patterns: metadata_match::DEFAULT_ALBUM_PATTERNS.iter().copied().map(Pattern::new).collect(),
+        }
+    }
+}
+impl Config {
+    pub fn artists<'a>(&self, artist: &'a str) -> Vec<&'a str> {
+        metadata_match::split_artists(artist, &self.artist_separators, &self.artist_exceptions)
+    }
+
+    pub fn artist<'a>(&self, artist: &'a str, credit: ArtistCredit) -> &'a str {
+        match credit {
+            ArtistCredit::Primary => metadata_match::primary_artist(artist, &self.artist_separators, &self.artist_exceptions),
+            ArtistCredit::All => artist.trim(),
+        }
+    }
+
+    fn clean(patterns: &[Pattern], name: &str) -> String {
+        metadata_match::remove_patterns(name, patterns.iter().map(|Pattern(pattern)| pattern))
+    }
+
+    pub fn title(&self, title: &str) -> String {
+        Self::clean(&self.title_patterns, title)
+    }
+
+    pub fn album(&self, album: &str) -> String {
+        Self::clean(&self.album_patterns, album)
+    }
+
+    pub fn names(&self, artist: &str, track: &str, album: Option<&str>, album_artist: Option<&str>, credit: ArtistCredit) -> TrackNames {
+        TrackNames {
+            artist: self.artist(artist, credit).to_owned(),
+            track: self.title(track),
+            album: album.map(|album| self.album(album)),
+            album_artist: album_artist.map(|artist| self.artist(artist, credit).to_owned()),
+        }
+    }
+
+    /// Returns `None` if the track has no artist.
+    pub fn track_names(&self, track: &osa_apple_music::track::Track, credit: ArtistCredit) -> Option<TrackNames> {
+        Some(self.names(track.artist.as_deref()?, &track.name, track.album.name.as_deref(), track.album.artist.as_deref(), credit))
+    }
+}
+
+/// The rules in use by the backends; replaced whenever the configuration is (re)loaded.
+static RULES: LazyLock<RwLock<Arc<Config>>> = LazyLock::new(Default::default);
+
+pub fn configure(config: Config) {
+    *RULES.write().expect("normalization rules poisoned") = Arc::new(config);
+}
+
+pub fn rules() -> Arc<Config> {
+    RULES.read().expect("normalization rules poisoned").clone()
+}
@@ -0,0 +1,621 @@
+use std::{fmt::Debug, path::{Path, PathBuf}, sync::{Arc, Weak}, time::Duration};
+use discord_ipc::activity::{Activity, ActivityType, Assets, Button, Timestamps};
+use tokio::sync::Mutex;
+use tracing::instrument;
+
+use crate::{data_fetching::components::{Component, ComponentSolicitation}, util::fallback_to_default_and_log_absence};
+
+use super::{Listened, StatusBackend};
+
+pub mod template;
+use template::{Placeholders, RenderedPresence, Template, TemplateActivityType};
+
+/// What's needed to build an activity, kept around so it can be rebuilt when the progress or paused state changes.
+#[derive(Debug, Clone)]
+struct PresenceContent {
+    rendered: RenderedPresence,
+    large_image: Option<String>,
+    small_image: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct PausedPresence {
+    /// Whether to keep showing the track while paused, instead of clearing the presence right away.
+    pub enabled: bool,
+    /// How long to keep showing the paused track before clearing the presence, in seconds.
+    pub idle_timeout: u64,
+    /// Shown in place of the small text while paused.
+    pub label: String,
+}
+impl Default for PausedPresence {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_timeout: 10 * 60,
+            label: "Paused".to_owned(),
+        }
+    }
+}
+
+const APPLICATION_ID: u64 = 1286481105410588672; // "Apple Music"
+
+/// The `discord` entry of the configuration, which can also just be `true` or `false` to use the defaults.
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(from = "ConfigRepresentation", into = "ConfigRepresentation")]
+pub struct Config {
+    pub enabled: bool,
+    pub template: Template,
+    pub paused: PausedPresence,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self { enabled: true, template: Template::default(), paused: PausedPresence::default() }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum ConfigRepresentation {
+    Toggle(bool),
+    Full {
+        enabled: bool,
+        #[serde(default)]
+        template: Template,
+        #[serde(default)]
+        paused: PausedPresence,
+    },
+}
+impl From<ConfigRepresentation> for Config {
+    fn from(value: ConfigRepresentation) -> Self {
+        match value {
+            ConfigRepresentation::Toggle(enabled) => Self { enabled, ..Default::default() },
+            ConfigRepresentation::Full { enabled, template, paused } => Self { enabled, template, paused },
+        }
+    }
+}
+impl From<Config> for ConfigRepresentation {
+    fn from(value: Config) -> Self {
+        if value.template == Template::default() && value.paused == PausedPresence::default() {
+            Self::Toggle(value.enabled)
+        } else {
+            Self::Full { enabled: value.enabled, template: value.template, paused: value.paused }
+        }
+    }
+}
+
+/// How urgently a pending activity update has to be sent.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum UpdatePriority {
+    /// Only corrects the timestamps, so it's never allowed to use the last update of the ratelimit window;
+    /// that one is saved for the next track.
+    Progress,
+    /// A new track, or the presence being paused, resumed, or cleared.
+    Change,
+}
+
+const TRY_AGAIN_DEBOUNCE: Duration = Duration::from_secs(7);
+
+pub struct DiscordPresence {
+    client: Option<discord_ipc::Client>,
+    /// Connects to this socket instead of searching for Discord's.
+    socket_path: Option<PathBuf>,
+    auto_reconnect_task_handle: Option<tokio::task::JoinHandle<()>>,
+    reconnect_interval: Duration,
+    /// Whether something should be shown; restored after reconnecting.
+    has_content: bool,
+    content: Option<PresenceContent>,
+    /// Computed when the progress is recorded, so that delayed updates still show the right time.
+    timestamps: Option<Timestamps>,
+    /// Coalesces updates that couldn't be sent yet; whatever is current gets sent once the ratelimit allows.
+    pending_update: Option<UpdatePriority>,
+    flush_task_handle: Option<tokio::task::JoinHandle<()>>,
+    template: Template,
+    paused_presence: PausedPresence,
+    paused: bool,
+    idle_clear_task_handle: Option<tokio::task::JoinHandle<()>>,
+    this: Weak<Mutex<Self>>,
+}
+impl Debug for DiscordPresence {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DiscordPresence").finish()
+    }
+}
+impl Default for DiscordPresence {
+    fn default() -> Self {
+        Self::disconnected()
+    }
+}
+impl DiscordPresence {
+    #[tracing::instrument(level = "debug", skip(config))]
+    pub async fn new(config: &Config) -> Self {
+        let mut instance = Self::disconnected();
+        instance.set_config(config);
+        if !instance.connect().await {
+            tracing::warn!("couldn't connect; assuming Discord isn't open");
+        }
+        instance
+    }
+
+    pub fn disconnected() -> Self {
+        Self {
+            client: None,
+            socket_path: None,
+            auto_reconnect_task_handle: None,
+            reconnect_interval: TRY_AGAIN_DEBOUNCE,
+            has_content: false,
+            content: None,
+            timestamps: None,
+            pending_update: None,
+            flush_task_handle: None,
+            template: Template::default(),
+            paused_presence: PausedPresence::default(),
+            paused: false,
+            idle_clear_task_handle: None,
+            this: Weak::new(),
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    async fn open_client(socket_path: Option<&Path>) -> Option<discord_ipc::Client> {
+        let result = match socket_path {
+            Some(path) => discord_ipc::Client::connect_to(path, APPLICATION_ID).await,
+            None => discord_ipc::Client::connect(APPLICATION_ID).await,
+        };
+        match result {
+            Ok(client) => {
+                tracing::debug!(user = ?client.user(), "connected");
+                Some(client)
+            },
+            Err(error) => {
+                tracing::debug!(?error, "couldn't connect");
+                None
+            }
+        }
+    }
+
+    /// Returns whether a connection was made.
+    #[tracing::instrument(skip(self), level = "debug")]
+    pub async fn connect(&mut self) -> bool {
+        let Some(client) = Self::open_client(self.socket_path.as_deref()).await else { return false };
+        self.client = Some(client);
+        true
+    }
+
+    /// While disconnected, periodically tries to connect again, restoring the presence once connected.
+    pub async fn enable_auto_reconnect(instance: Weak<Mutex<Self>>) {
+        let Some(strong) = instance.upgrade() else { return };
+        let mut lock = strong.lock().await;
+
+        if let Some(old_handle) = lock.auto_reconnect_task_handle.take() {
+            old_handle.abort();
+        }
+
+        let interval = lock.reconnect_interval;
+        lock.auto_reconnect_task_handle = Some(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(instance) = instance.upgrade() else { break };
+                let socket_path = {
+                    let instance = instance.lock().await;
+                    if instance.is_connected() { continue }
+                    instance.socket_path.clone()
+                };
+
+                // Connecting can take a while, and the presence has to stay usable in the meantime.
+                tracing::debug!("disconnected; attempting to reconnect");
+                let Some(client) = Self::open_client(socket_path.as_deref()).await else { continue };
+
+                let mut instance = instance.lock().await;
+                if instance.is_connected() { continue }
+                instance.client = Some(client);
+                if instance.has_content {
+                    instance.request_update(UpdatePriority::Change).await;
+                }
+            }
+        }));
+    }
+
+    pub fn set_config(&mut self, config: &Config) {
+        self.template = config.template.clone();
+        self.paused_presence = config.paused.clone();
+    }
+
+    /// Needed for delayed updates to be sent, and for the paused presence to be cleared after it has been idle for a while.
+    pub async fn register_self_reference(instance: &Arc<Mutex<Self>>) {
+        instance.lock().await.this = Arc::downgrade(instance);
+    }
+
+    /// Drops the client if the connection can't be used anymore, leaving it to the auto-reconnect task.
+    fn handle_request_error(&mut self, error: &discord_ipc::RequestError) {
+        if error.is_fatal() {
+            tracing::debug!(?error, "connection lost");
+            self.client = None;
+        }
+    }
+
+    /// Returns whether the status was cleared.
+    /// (If the status was already empty, it will return false.)
+    #[tracing::instrument(skip(self), level = "debug")]
+    pub async fn clear(&mut self) -> bool {
+        if !self.has_content { return false }
+        self.has_content = false;
+        self.request_update(UpdatePriority::Change).await;
+        true
+    }
+
+    fn record_progress(&mut self, position: Option<f32>, duration: Option<f32>) {
+        self.timestamps = position.map(|position| {
+            let start = chrono::Utc::now().timestamp() as u64 - position as u64;
+            Timestamps {
+                start: Some(start),
+                end: duration.map(|duration| start + duration as u64),
+            }
+        });
+    }
+
+    fn build_activity(&self, content: &PresenceContent) -> Activity {
+        fn make_minimum_length(mut s: String) -> String {
+            if s.len() < 2 {
+                s += "  "; // two spaces
+            }
+            s
+        }
+
+        let rendered = &content.rendered;
+        let paused = self.paused && self.paused_presence.enabled;
+
+        let details = if paused {
+            Some(format!("⏸ {}", rendered.details.as_deref().unwrap_or(&self.paused_presence.label)))
+        } else {
+            rendered.details.clone()
+        };
+
+        Activity {
+            kind: Some(match rendered.activity_type {
+                TemplateActivityType::Automatic |
+                TemplateActivityType::Listening => ActivityType::Listening,
+                TemplateActivityType::Watching => ActivityType::Watching,
+                TemplateActivityType::Playing => ActivityType::Playing,
+                TemplateActivityType::Competing => ActivityType::Competing,
+            }),
+            details: details.map(make_minimum_length),
+            state: rendered.state.clone().map(make_minimum_length),
+            timestamps: self.timestamps.clone().filter(|_| rendered.timestamps && !paused),
+            assets: Some(Assets {
+                large_text: rendered.large_text.clone().map(make_minimum_length),
+                large_image: content.large_image.clone(),
+                small_image: content.small_image.clone().filter(|_| rendered.artist_image),
+                small_text: if paused {
+                    Some(make_minimum_length(self.paused_presence.label.clone()))
+                } else {
+                    rendered.small_text.clone().filter(|_| rendered.artist_image).map(make_minimum_length)
+                },
+            }),
+            buttons: rendered.buttons.iter()
+                .map(|button| Button { label: button.label.clone(), url: button.url.clone() })
+                .collect(),
+        }
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn dispatch(&mut self, priority: UpdatePriority) {
+        if self.content.is_none() {
+            tracing::warn!("cannot dispatch without set activity");
+            return
+        }
+        self.has_content = true;
+        self.request_update(priority).await;
+    }
+
+    async fn request_update(&mut self, priority: UpdatePriority) {
+        self.pending_update = self.pending_update.max(Some(priority));
+        self.flush().await;
+    }
+
+    /// Sends the pending update if the ratelimit allows it, and otherwise schedules it for when it will.
+    #[instrument(skip(self), level = "debug")]
+    async fn flush(&mut self) {
+        let Some(priority) = self.pending_update else { return };
+        let Some(client) = self.client.as_mut() else {
+            tracing::debug!("cannot dispatch without client; will dispatch once reconnected");
+            return
+        };
+
+        let needed = match priority {
+            UpdatePriority::Progress => 2,
+            UpdatePriority::Change => 1,
+        };
+        if let Some(wait) = client.activity_updates_available_in(needed) {
+            tracing::debug!(?priority, ?wait, "ratelimited; delaying update");
+            self.schedule_flush(wait);
+            return
+        }
+
+        self.pending_update = None;
+        if let Some(handle) = self.flush_task_handle.take() {
+            handle.abort();
+        }
+
+        let activity = self.content.as_ref()
+            .filter(|_| self.has_content)
+            .map(|content| self.build_activity(content));
+        let Some(client) = self.client.as_mut() else { return };
+        match client.set_activity(activity.as_ref()).await {
+            Ok(()) => {},
+            Err(discord_ipc::RequestError::Ratelimited { retry_after }) => {
+                self.pending_update = Some(priority);
+                self.schedule_flush(retry_after);
+            },
+            Err(error) => {
+                tracing::error!(?error, "activity dispatch failure");
+                self.handle_request_error(&error);
+            }
+        }
+    }
+
+    fn schedule_flush(&mut self, wait: Duration) {
+        if let Some(handle) = self.flush_task_handle.take() {
+            handle.abort();
+        }
+        let this = self.this.clone();
+        self.flush_task_handle = Some(tokio::spawn(async move {
+            tokio::time::sleep(wait).await;
+            let Some(instance) = this.upgrade() else { return };
+            let mut instance = instance.lock().await;
+            // Taken so that flushing doesn't abort this task.
+            instance.flush_task_handle.take();
+            instance.flush().await;
+        }));
+    }
+
+    fn cancel_idle_clear(&mut self) {
+        if let Some(handle) = self.idle_clear_task_handle.take() {
+            handle.abort();
+        }
+    }
+
+    /// Clears the presence once it has stayed paused for the configured idle timeout.
+    fn schedule_idle_clear(&mut self) {
+        self.cancel_idle_clear();
+        let this = self.this.clone();
+        let timeout = Duration::from_secs(self.paused_presence.idle_timeout);
+        self.idle_clear_task_handle = Some(tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            let Some(instance) = this.upgrade() else { return };
+            let mut instance = instance.lock().await;
+            if instance.paused {
+                tracing::debug!("paused for too long; clearing presence");
+                instance.clear().await;
+            }
+        }));
+    }
+
+    async fn record_progress_from_context<T>(&mut self, context: &super::BackendContext<T>) {
+        let position = context.listened.lock().await.current.as_ref().map(|c| c.get_expected_song_position());
+        self.record_progress(position, context.track.duration);
+    }
+}
+impl Drop for DiscordPresence {
+    fn drop(&mut self) {
+        if let Some(handle) = self.auto_reconnect_task_handle.as_ref() {
+            handle.abort();
+        }
+        self.cancel_idle_clear();
+        if let Some(handle) = self.flush_task_handle.as_ref() {
+            handle.abort();
+        }
+        // Dropping the client closes the socket, which makes Discord clear the activity.
+    }
+}
+#[async_trait::async_trait]
+impl StatusBackend for DiscordPresence {
+    async fn get_additional_data_solicitation(&self) -> ComponentSolicitation {
+        let mut solicitation: ComponentSolicitation = ComponentSolicitation::default();
+        solicitation.list.insert(Component::ITunesData);
+        solicitation.list.insert(Component::AlbumImage);
+        solicitation.list.insert(Component::ArtistImage);
+        if template::COLOR_PLACEHOLDERS.iter().any(|placeholder| self.template.uses(placeholder)) {
+            solicitation.list.insert(Component::Palette);
+        }
+        solicitation
+    }
+
+    async fn record_as_listened(&self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) {
+        // no-op
+    }
+
+    async fn check_eligibility(&self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) -> bool {
+        false
+    }
+
+    #[tracing::instrument(skip(self), level = "debug")]
+    async fn pause(&mut self) {
+        self.paused = true;
+        if self.paused_presence.enabled && self.content.is_some() {
+            self.dispatch(UpdatePriority::Change).await;
+            self.schedule_idle_clear();
+        } else {
+            self.clear().await;
+        }
+    }
+
+    #[tracing::instrument(skip(self, context), level = "debug")]
+    async fn resume(&mut self, context: super::BackendContext<()>) {
+        self.paused = false;
+        self.cancel_idle_clear();
+        self.record_progress_from_context(&context).await;
+        if self.content.is_some() {
+            self.dispatch(UpdatePriority::Change).await;
+        }
+    }
+
+    #[tracing::instrument(skip(self), level = "debug")]
+    async fn stop(&mut self) {
+        self.paused = false;
+        self.cancel_idle_clear();
+        self.clear().await;
+    }
+
+    #[tracing::instrument(skip(self), level = "debug")]
+    async fn update_artwork(&mut self, url: String) {
+        let Some(content) = self.content.as_mut() else { return };
+        content.large_image = Some(url);
+        if self.has_content {
+            self.dispatch(UpdatePriority::Progress).await;
+        }
+    }
+
+    #[tracing::instrument(skip(self, context), level = "debug")]
+    async fn update_progress(&mut self, context: super::BackendContext<()>) {
+        self.record_progress_from_context(&context).await;
+        self.dispatch(UpdatePriority::Progress).await;
+    }
+
+    #[tracing::instrument(skip(self, context), level = "debug")]
+    async fn set_now_listening(&mut self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) {
+        let super::BackendContext { track, app, listened, data: additional_info, .. } = context;
+        let position = listened.lock().await.current.as_ref().map(|position| position.get_expected_song_position());
+        self.record_progress(position, track.duration);
+
+        let rendered = match self.template.render(&Placeholders::from_track(&track, Some(&additional_info))) {
+            Ok(rendered) => rendered,
+            Err(error) => {
+                tracing::error!(?error, "invalid discord presence template; using the default");
+                Template::default().render(&Placeholders::from_track(&track, Some(&additional_info))).expect("default template is valid")
+            }
+        };
+
+        self.paused = false;
+        self.cancel_idle_clear();
+        self.content = Some(PresenceContent {
+            rendered,
+            large_image: additional_info.images.track.clone(),
+            small_image: additional_info.images.artist.clone(),
+        });
+        self.dispatch(UpdatePriority::Change).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use discord_ipc::{mock::{MockServer, Received}, ACTIVITY_UPDATES_PER_WINDOW};
+
+    use super::*;
+
+    fn socket_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("am-osx-status-discord-test-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    /// A presence connected to a mock server, with a track to show that hasn't been sent yet.
+    async fn connected(name: &str, paused_presence: PausedPresence) -> (MockServer, Arc<Mutex<DiscordPresence>>) {
+        let mut server = MockServer::bind(socket_path(name)).unwrap();
+        let mut presence = DiscordPresence::disconnected();
+        presence.socket_path = Some(server.path().to_owned());
+        presence.reconnect_interval = Duration::from_millis(100);
+        presence.paused_presence = paused_presence;
+        assert!(presence.connect().await);
+        assert!(matches!(server.next().await, Some(Received::Handshake { .. })));
+
+        presence.record_progress(Some(30.), Some(180.));
+        presence.content = Some(PresenceContent {
+            rendered: Template::default().render(&Placeholders::default()).unwrap(),
+            large_image: Some("https://example.com/cover.jpg".to_owned()),
+            small_image: None,
+        });
+        let presence = Arc::new(Mutex::new(presence));
+        DiscordPresence::register_self_reference(&presence).await;
+        (server, presence)
+    }
+
+    /// The activity the server was sent next, or `None` if the presence was cleared.
+    async fn next_activity(server: &mut MockServer) -> Option<serde_json::Value> {
+        match server.next().await {
+            Some(Received::Command { cmd, args }) if cmd == "SET_ACTIVITY" => Some(args["activity"].clone()).filter(|activity| !activity.is_null()),
+            other => panic!("expected an activity update, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn pausing_clears_the_presence() {
+        let (mut server, presence) = connected("pause", PausedPresence::default()).await;
+        let mut presence = presence.lock().await;
+        presence.dispatch(UpdatePriority::Change).await;
+        assert!(next_activity(&mut server).await.is_some());
+
+        presence.pause().await;
+        assert_eq!(next_activity(&mut server).await, None);
+
+        // Already cleared, so stopping doesn't send anything.
+        presence.stop().await;
+        assert_eq!(server.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn paused_presence_is_cleared_once_idle() {
+        let paused_presence = PausedPresence { enabled: true, idle_timeout: 1, ..Default::default() };
+        let (mut server, presence) = connected("pause-idle", paused_presence).await;
+        {
+            let mut presence = presence.lock().await;
+            presence.dispatch(UpdatePriority::Change).await;
+            let playing = next_activity(&mut server).await.unwrap();
+            assert!(playing["timestamps"].is_object());
+
+            presence.pause().await;
+        }
+        let paused = next_activity(&mut server).await.expect("the paused track should be shown");
+        assert_eq!(paused["assets"]["small_text"], "Paused");
+        assert!(paused["timestamps"].is_null());
+
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        assert_eq!(next_activity(&mut server).await, None);
+    }
+
+    #[tokio::test]
+    async fn updates_are_held_back_and_coalesced_by_the_ratelimit() {
+        let (mut server, presence) = connected("ratelimit", PausedPresence::default()).await;
+        let mut presence = presence.lock().await;
+        for _ in 1..ACTIVITY_UPDATES_PER_WINDOW {
+            presence.dispatch(UpdatePriority::Change).await;
+            assert!(next_activity(&mut server).await.is_some());
+        }
+
+        // The last update of the window is saved for a change.
+        presence.dispatch(UpdatePriority::Progress).await;
+        assert_eq!(presence.pending_update, Some(UpdatePriority::Progress));
+        assert!(presence.flush_task_handle.is_some());
+        presence.dispatch(UpdatePriority::Change).await;
+        assert!(next_activity(&mut server).await.is_some());
+        assert_eq!(presence.pending_update, None);
+        assert!(presence.flush_task_handle.is_none());
+
+        // With the window used up, everything waits for a single delayed update.
+        presence.dispatch(UpdatePriority::Progress).await;
+        presence.dispatch(UpdatePriority::Change).await;
+        presence.dispatch(UpdatePriority::Progress).await;
+        assert_eq!(presence.pending_update, Some(UpdatePriority::Change));
+        assert!(presence.flush_task_handle.is_some());
+        assert_eq!(server.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn presence_is_restored_after_reconnecting() {
+        let (mut server, presence) = connected("reconnect", PausedPresence::default()).await;
+        presence.lock().await.dispatch(UpdatePriority::Change).await;
+        let before = next_activity(&mut server).await.unwrap();
+
+        server.disconnect().await;
+        presence.lock().await.dispatch(UpdatePriority::Progress).await;
+        assert!(!presence.lock().await.is_connected());
+
+        DiscordPresence::enable_auto_reconnect(Arc::downgrade(&presence)).await;
+        assert!(matches!(server.next().await, Some(Received::Handshake { .. })));
+        assert_eq!(next_activity(&mut server).await, Some(before));
+        assert!(presence.lock().await.is_connected());
+    }
+}
@@ -0,0 +1,409 @@
+//! User-configurable layout of the presence.
+//!
+//! Text fields are templates where `{placeholder}` is substituted with information about the track,
+//! and `{placeholder|fallback}` uses the fallback text when the track doesn't have that information.
+//! Fields which render to nothing are left out of the presence entirely.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// The names that can be used inside of `{}` in a template.
+pub const PLACEHOLDERS: &[&str] = &[
+    "track",
+    "clean_track",
+    "artist",
+    "primary_artist",
+    "album",
+    "clean_album",
+    "album_artist",
+    "year",
+    "genre",
+    "composer",
+    "work",
+    "movement",
+    "movement_number",
+    "apple_music_url",
+    "dominant_color",
+    "vibrant_color",
+    "muted_color",
+    "text_color",
+];
+
+/// Placeholders filled in from the artwork's [palette](crate::data_fetching::components::palette::Palette),
+/// as `#RRGGBB`; the palette is only computed if the template uses one of them.
+pub const COLOR_PLACEHOLDERS: &[&str] = &["dominant_color", "vibrant_color", "muted_color", "text_color"];
+
+/// Discord doesn't display more than this many buttons.
+pub const MAX_BUTTONS: usize = 2;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateActivityType {
+    /// "Watching" for music videos, "Listening" for everything else.
+    #[default]
+    Automatic,
+    Listening,
+    Watching,
+    Playing,
+    Competing,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct TemplateButton {
+    pub label: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct Template {
+    pub details: String,
+    pub state: String,
+    pub large_text: String,
+    pub small_text: String,
+    /// Whether to show the image of the artist in the corner of the album art.
+    pub artist_image: bool,
+    /// Whether to show the elapsed and remaining time of the track.
+    pub timestamps: bool,
+    pub activity_type: TemplateActivityType,
+    /// Buttons which aren't shown if any placeholder in them can't be filled.
+    pub buttons: Vec<TemplateButton>,
+}
+impl Default for Template {
+    fn default() -> Self {
+        Self {
+            details: "{track}".to_owned(),
+            state: "{artist|Unknown Artist}".to_owned(),
+            large_text: "{album}".to_owned(),
+            small_text: "{artist}".to_owned(),
+            artist_image: true,
+            timestamps: true,
+            activity_type: TemplateActivityType::Automatic,
+            buttons: vec![TemplateButton {
+                label: "Listen on Apple Music".to_owned(),
+                url: "{apple_music_url}".to_owned(),
+            }],
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("unknown placeholder `{{{0}}}`")]
+    UnknownPlaceholder(String),
+    #[error("unclosed `{{` in `{0}`")]
+    Unclosed(String),
+}
+
+/// The values available to a template.
+#[derive(Debug, Default, Clone)]
+pub struct Placeholders {
+    values: HashMap<&'static str, String>,
+    is_video: bool,
+}
+impl Placeholders {
+    pub fn from_track(track: &osa_apple_music::track::Track, additional: Option<&crate::data_fetching::AdditionalTrackData>) -> Self {
+        let mut placeholders = Self {
+            values: HashMap::new(),
+            is_video: matches!(track.media_kind, osa_apple_music::track::MediaKind::MusicVideo),
+        };
+
+        // the clean names and primary artist are what the other services are sent
+        let rules = crate::normalization::rules();
+        placeholders.set("track", Some(track.name.clone()));
+        placeholders.set("clean_track", Some(rules.title(&track.name)));
+        placeholders.set("artist", track.artist.clone());
+        placeholders.set("primary_artist", track.artist.as_deref().map(|artist| rules.artist(artist, crate::normalization::ArtistCredit::Primary).to_owned()));
+        placeholders.set("album", track.album.name.clone());
+        placeholders.set("clean_album", track.album.name.as_deref().map(|album| rules.album(album)));
+        placeholders.set("album_artist", track.album.artist.clone());
+        placeholders.set("year", track.year.map(|year| year.to_string()));
+        placeholders.set("genre", track.genre.clone());
+        placeholders.set("composer", track.composer.clone());
+        placeholders.set("work", track.work.clone());
+        placeholders.set("movement", track.movement.as_ref().map(|movement| movement.name.clone()));
+        placeholders.set("movement_number", track.movement.as_ref().map(|movement| movement.index.to_string()));
+        placeholders.set("apple_music_url", additional.and_then(|data| data.itunes.as_ref()).map(|itunes| itunes.apple_music_url.clone()));
+        if let Some(palette) = additional.and_then(|data| data.palette.as_ref()) {
+            let hex = |color: mzstatic::image::effect::Rgb| format!("#{color:X}");
+            placeholders.set("dominant_color", Some(hex(palette.dominant)));
+            placeholders.set("vibrant_color", palette.vibrant.map(hex));
+            placeholders.set("muted_color", palette.muted.map(hex));
+            placeholders.set("text_color", Some(hex(palette.text)));
+        }
+        placeholders
+    }
+
+    /// Made-up values, used to preview a template when nothing is playing.
+    pub fn sample() -> Self {
+        let mut placeholders = Self::default();
+        placeholders.set("track", Some("Symphony No. 9 in D Minor, Op. 125: IV. Presto".to_owned()));
+        placeholders.set("clean_track", Some("Symphony No. 9 in D Minor, Op. 125: IV. Presto".to_owned()));
+        placeholders.set("artist", Some("Example Philharmonic".to_owned()));
+        placeholders.set("primary_artist", Some("Example Philharmonic".to_owned()));
+        placeholders.set("album", Some("Beethoven: Symphony No. 9".to_owned()));
+        placeholders.set("clean_album", Some("Beethoven: Symphony No. 9".to_owned()));
+        placeholders.set("album_artist", Some("Example Philharmonic & Chorus".to_owned()));
+        placeholders.set("year", Some("1999".to_owned()));
+        placeholders.set("genre", Some("Classical".to_owned()));
+        placeholders.set("composer", Some("Ludwig van Beethoven".to_owned()));
+        placeholders.set("work", Some("Symphony No. 9 in D Minor, Op. 125".to_owned()));
+        placeholders.set("movement", Some("Presto".to_owned()));
+        placeholders.set("movement_number", Some("4".to_owned()));
+        placeholders.set("apple_music_url", Some("https://music.apple.com/us/album/1".to_owned()));
+        placeholders.set("dominant_color", Some("#1F2A44".to_owned()));
+        placeholders.set("vibrant_color", Some("#C8553D".to_owned()));
+        placeholders.set("muted_color", Some("#6B7A8F".to_owned()));
+        placeholders.set("text_color", Some("#FFFFFF".to_owned()));
+        placeholders
+    }
+
+    fn set(&mut self, name: &'static str, value: Option<String>) {
+        debug_assert!(PLACEHOLDERS.contains(&name));
+        if let Some(value) = value.filter(|value| !value.trim().is_empty()) {
+            self.values.insert(name, value);
+        }
+    }
+}
+
+/// Substitutes placeholders in `template`.
+///
+/// When a placeholder without a fallback has no value, `strict` decides whether the whole
+/// template fails to render (returning `None`) or that placeholder just renders as nothing.
+fn substitute(template: &str, placeholders: &Placeholders, strict: bool) -> Result<Option<String>, TemplateError> {
+    let mut out = String::with_capacity(template.len());
+    // the rest is still checked, so that unknown placeholders after a missing one are reported
+    let mut missing = false;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or_else(|| TemplateError::Unclosed(template.to_owned()))? + start;
+        let inner = &rest[start + 1..end];
+        let (name, fallback) = match inner.split_once('|') {
+            Some((name, fallback)) => (name.trim(), Some(fallback)),
+            None => (inner.trim(), None),
+        };
+
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(TemplateError::UnknownPlaceholder(name.to_owned()));
+        }
+
+        match placeholders.values.get(name).map(String::as_str).or(fallback) {
+            Some(value) => out.push_str(value),
+            None => missing = true,
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(if missing && strict { None } else { Some(out) })
+}
+
+/// The names of the placeholders in `template`, known or not.
+fn placeholder_names(template: &str) -> impl Iterator<Item = &str> {
+    template.split('{').skip(1)
+        .filter_map(|part| part.split_once('}'))
+        .map(|(inner, _)| inner.split('|').next().unwrap_or_default().trim())
+}
+
+/// Substitutes placeholders in a text field, returning `None` if the result is blank.
+fn render(template: &str, placeholders: &Placeholders) -> Result<Option<String>, TemplateError> {
+    Ok(substitute(template, placeholders, false)?.filter(|out| !out.trim().is_empty()))
+}
+
+/// A template with all of its placeholders filled in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedPresence {
+    pub details: Option<String>,
+    pub state: Option<String>,
+    pub large_text: Option<String>,
+    pub small_text: Option<String>,
+    pub artist_image: bool,
+    pub timestamps: bool,
+    /// Never [`TemplateActivityType::Automatic`].
+    pub activity_type: TemplateActivityType,
+    pub buttons: Vec<TemplateButton>,
+}
+impl core::fmt::Display for RenderedPresence {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let none = "(hidden)";
+        writeln!(f, "activity type: {:?}", self.activity_type)?;
+        writeln!(f, "details:       {}", self.details.as_deref().unwrap_or(none))?;
+        writeln!(f, "state:         {}", self.state.as_deref().unwrap_or(none))?;
+        writeln!(f, "large text:    {}", self.large_text.as_deref().unwrap_or(none))?;
+        writeln!(f, "small text:    {}", self.small_text.as_deref().unwrap_or(none))?;
+        writeln!(f, "artist image:  {}", if self.artist_image { "shown" } else { "hidden" })?;
+        writeln!(f, "timestamps:    {}", if self.timestamps { "shown" } else { "hidden" })?;
+        if self.buttons.is_empty() {
+            writeln!(f, "buttons:       {none}")?;
+        }
+        for button in &self.buttons {
+            writeln!(f, "button:        [{}] -> {}", button.label, button.url)?;
+        }
+        Ok(())
+    }
+}
+
+impl Template {
+    pub fn render(&self, placeholders: &Placeholders) -> Result<RenderedPresence, TemplateError> {
+        let mut buttons = Vec::with_capacity(self.buttons.len().min(MAX_BUTTONS));
+        for button in &self.buttons {
+            if let (Some(label), Some(url)) = (substitute(&button.label, placeholders, true)?, substitute(&button.url, placeholders, true)?) {
+                buttons.push(TemplateButton { label, url });
+            }
+        }
+        if buttons.len() > MAX_BUTTONS {
+            tracing::warn!("discord only displays {MAX_BUTTONS} buttons; ignoring the rest");
+            buttons.truncate(MAX_BUTTONS);
+        }
+
+        Ok(RenderedPresence {
+            details: render(&self.details, placeholders)?,
+            state: render(&self.state, placeholders)?,
+            large_text: render(&self.large_text, placeholders)?,
+            small_text: render(&self.small_text, placeholders)?,
+            artist_image: self.artist_image,
+            timestamps: self.timestamps,
+            activity_type: match self.activity_type {
+                TemplateActivityType::Automatic if placeholders.is_video => TemplateActivityType::Watching,
+                TemplateActivityType::Automatic => TemplateActivityType::Listening,
+                other => other,
+            },
+            buttons,
+        })
+    }
+
+    /// Whether any field or button of the template refers to the placeholder.
+    pub fn uses(&self, placeholder: &str) -> bool {
+        [&self.details, &self.state, &self.large_text, &self.small_text].into_iter()
+            .chain(self.buttons.iter().flat_map(|button| [&button.label, &button.url]))
+            .any(|field| placeholder_names(field).any(|name| name == placeholder))
+    }
+
+    /// Checks that every template only uses known placeholders and is well-formed.
+    pub fn validate(&self) -> Result<(), TemplateError> {
+        self.render(&Placeholders::default()).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn button(label: &str, url: &str) -> TemplateButton {
+        TemplateButton { label: label.to_owned(), url: url.to_owned() }
+    }
+
+    #[test]
+    fn substitutes_placeholders() {
+        let placeholders = Placeholders::sample();
+        assert_eq!(
+            substitute("{artist} - {year}", &placeholders, false).unwrap().as_deref(),
+            Some("Example Philharmonic - 1999"),
+        );
+        assert_eq!(substitute("{ genre }", &placeholders, false).unwrap().as_deref(), Some("Classical"));
+        assert_eq!(substitute("no placeholders", &placeholders, true).unwrap().as_deref(), Some("no placeholders"));
+    }
+
+    #[test]
+    fn uses_fallbacks_for_missing_values() {
+        let sample = Placeholders::sample();
+        let empty = Placeholders::default();
+        assert_eq!(substitute("{artist|Unknown}", &sample, false).unwrap().as_deref(), Some("Example Philharmonic"));
+        assert_eq!(substitute("{artist|Unknown}", &empty, false).unwrap().as_deref(), Some("Unknown"));
+        assert_eq!(substitute("{artist|}", &empty, true).unwrap().as_deref(), Some(""));
+        assert_eq!(substitute("by {artist}", &empty, false).unwrap().as_deref(), Some("by "));
+        assert_eq!(substitute("by {artist}", &empty, true).unwrap(), None);
+    }
+
+    #[test]
+    fn reports_malformed_templates() {
+        let placeholders = Placeholders::sample();
+        assert!(matches!(substitute("{artist", &placeholders, false), Err(TemplateError::Unclosed(template)) if template == "{artist"));
+        assert!(matches!(substitute("{artst}", &placeholders, false), Err(TemplateError::UnknownPlaceholder(name)) if name == "artst"));
+        assert!(matches!(substitute("{artist} {nope|x}", &placeholders, false), Err(TemplateError::UnknownPlaceholder(name)) if name == "nope"));
+    }
+
+    #[test]
+    fn blank_fields_are_hidden() {
+        let template = Template {
+            details: "{track}".to_owned(),
+            state: "  {composer}  ".to_owned(),
+            ..Template::default()
+        };
+        let rendered = template.render(&Placeholders::default()).unwrap();
+        assert_eq!(rendered.details, None);
+        assert_eq!(rendered.state, None);
+        assert_eq!(rendered.large_text, None);
+        assert_eq!(template.render(&Placeholders::sample()).unwrap().state.as_deref(), Some("  Ludwig van Beethoven  "));
+    }
+
+    #[test]
+    fn buttons_with_missing_values_are_dropped() {
+        let template = Template {
+            buttons: vec![
+                button("Listen on Apple Music", "{apple_music_url}"),
+                button("{composer|Composer}", "https://example.com/{composer}"),
+            ],
+            ..Template::default()
+        };
+        let mut placeholders = Placeholders::sample();
+        placeholders.values.remove("composer");
+        let rendered = template.render(&placeholders).unwrap();
+        assert_eq!(rendered.buttons, vec![button("Listen on Apple Music", "https://music.apple.com/us/album/1")]);
+
+        assert!(template.render(&Placeholders::default()).unwrap().buttons.is_empty());
+    }
+
+    #[test]
+    fn buttons_are_limited() {
+        let template = Template {
+            buttons: (0..MAX_BUTTONS + 2).map(|index| button(&format!("{index}"), "https://example.com")).collect(),
+            ..Template::default()
+        };
+        let rendered = template.render(&Placeholders::sample()).unwrap();
+        assert_eq!(rendered.buttons.len(), MAX_BUTTONS);
+        assert_eq!(rendered.buttons[0].label, "0");
+    }
+
+    #[test]
+    fn automatic_activity_type_depends_on_the_media() {
+        let template = Template::default();
+        let mut placeholders = Placeholders::sample();
+        assert_eq!(template.render(&placeholders).unwrap().activity_type, TemplateActivityType::Listening);
+        placeholders.is_video = true;
+        assert_eq!(template.render(&placeholders).unwrap().activity_type, TemplateActivityType::Watching);
+
+        let template = Template { activity_type: TemplateActivityType::Playing, ..Template::default() };
+        assert_eq!(template.render(&placeholders).unwrap().activity_type, TemplateActivityType::Playing);
+    }
+
+    #[test]
+    fn finds_used_placeholders() {
+        let template = Template {
+            state: "{artist|Unknown Artist}".to_owned(),
+            buttons: vec![button("Color", "https://example.com/{ dominant_color }")],
+            ..Template::default()
+        };
+        assert!(template.uses("artist"));
+        assert!(template.uses("dominant_color"));
+        assert!(!template.uses("vibrant_color"));
+        assert!(!template.uses("Unknown Artist"));
+        assert!(!Template::default().uses("dominant_color"));
+    }
+
+    #[test]
+    fn validates_every_field() {
+        assert!(Template::default().validate().is_ok());
+
+        let template = Template { large_text: "{albun}".to_owned(), ..Template::default() };
+        assert!(matches!(template.validate(), Err(TemplateError::UnknownPlaceholder(name)) if name == "albun"));
+
+        // still reported after a placeholder without a value
+        let template = Template { buttons: vec![button("{apple_music_url} {bogus}", "https://example.com")], ..Template::default() };
+        assert!(matches!(template.validate(), Err(TemplateError::UnknownPlaceholder(name)) if name == "bogus"));
+
+        let template = Template { small_text: "{artist".to_owned(), ..Template::default() };
+        assert!(matches!(template.validate(), Err(TemplateError::Unclosed(_))));
+    }
+}
@@ -2,6 +2,7 @@ use std::{fmt::Debug, sync::Arc};
 use chrono::TimeDelta;
 
 use super::{StatusBackend, TimeDeltaExtension as _};
+use crate::{corrections::TrackNames, data_fetching::components::{Component, ComponentSolicitation}, normalization::ArtistCredit};
 
 const FOUR_MINUTES: TimeDelta = TimeDelta::new(4 * 60, 0).unwrap();
 const THIRTY_SECONDS: TimeDelta = TimeDelta::new(30, 0).unwrap();
@@ -36,23 +37,53 @@ pub struct Config {
         skip_serializing_if = "is_default_client_identity"
     )]
     pub identity: ClientIdentity,
-    pub session_key: Option<lastfm::auth::SessionKey>
+    pub session_key: Option<lastfm::auth::SessionKey>,
+    /// Only the first of several artists is submitted by default, since that's how Last.fm credits tracks.
+    #[serde(default = "ArtistCredit::primary")]
+    pub artists: ArtistCredit,
 }
 
-fn clean_album(mut str: &str) -> &str {
-    for suffix in [
-        " - Single",
-        " - EP",
-    ] {
-        if str.ends_with(suffix) {
-            str = &str[..str.len() - suffix.len()];
+/// The information submitted about a track, owned so that corrections can be applied to it and failed scrobbles kept around.
+struct Submission {
+    names: TrackNames,
+    duration_in_seconds: Option<u32>,
+    track_number: Option<u32>,
+    mbid: Option<brainz::music::Id<brainz::music::Recording>>,
+}
+impl Submission {
+    fn as_heard(&self) -> lastfm::scrobble::HeardTrackInfo<'_> {
+        lastfm::scrobble::HeardTrackInfo {
+            artist: &self.names.artist,
+            track: &self.names.track,
+            album: self.names.album.as_deref(),
+            album_artist: self.names.album_artist.as_deref(),
+            duration_in_seconds: self.duration_in_seconds,
+            track_number: self.track_number,
+            mbid: self.mbid,
+        }
+    }
+}
+
+/// A scrobble which failed for a reason that may go away (the service being offline, ratelimiting, etc.), to be tried again.
+struct PendingScrobble {
+    submission: Submission,
+    timestamp: chrono::DateTime<chrono::Utc>,
+}
+impl PendingScrobble {
+    fn as_scrobble(&self) -> lastfm::scrobble::Scrobble<'_> {
+        lastfm::scrobble::Scrobble {
+            info: self.submission.as_heard(),
+            timestamp: self.timestamp,
+            chosen_by_user: None,
         }
     }
-    str
 }
 
 pub struct LastFM {
-    client: Arc<::lastfm::Client<::lastfm::auth::state::Authorized>>
+    client: Arc<::lastfm::Client<::lastfm::auth::state::Authorized>>,
+    /// Oldest first; sent along with the next scrobble.
+    pending: tokio::sync::Mutex<Vec<PendingScrobble>>,
+    artists: ArtistCredit,
 }
 impl Debug for LastFM {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -60,46 +91,87 @@ impl Debug for LastFM {
     }
 }
 impl LastFM {
-    pub fn new(identity: ClientIdentity, session_key: lastfm::auth::SessionKey) -> Self {
+    pub fn new(identity: ClientIdentity, session_key: lastfm::auth::SessionKey, artists: ArtistCredit) -> Self {
         let client = lastfm::Client::authorized(identity, session_key);
-        Self { client: Arc::new(client) }
+        Self { client: Arc::new(client), pending: Default::default(), artists }
     }
 
     /// Returns `None` if the track is missing required data (the artist or track name).
-    fn track_to_heard(track: &osa_apple_music::track::Track) -> Option<lastfm::scrobble::HeardTrackInfo<'_>> {
-        Some(lastfm::scrobble::HeardTrackInfo {
-            artist: track.artist.as_ref().map(|s| s.split(" & ").next().unwrap())?,
-            track: &track.name,
-            album: track.album.name.as_deref().map(clean_album),
-            album_artist: if track.album.artist.as_ref().is_some_and(|aa| Some(aa) != track.artist.as_ref()) {
-                // only sent if != track artist
-                Some(track.album.artist.as_ref().unwrap())
-            } else { None },
+    /// Names are normalized, then remembered corrections are applied; names Last.fm otherwise knows as misspellings are sent as their canonical spelling,
+    /// so they don't end up as separate entries.
+    async fn submission(&self, track: &osa_apple_music::track::Track, data: &crate::data_fetching::AdditionalTrackData) -> Option<Submission> {
+        let rules = crate::normalization::rules();
+        let mut original = rules.track_names(track, self.artists)?;
+        // only sent if != track artist
+        if track.album.artist == track.artist { original.album_artist = None }
+        let mut names = original.clone();
+        crate::corrections::apply(&mut names).await;
+        // looked up by the primary artist, so it doesn't apply when all of several artists are submitted
+        let primary = rules.artist(track.artist.as_deref()?, ArtistCredit::Primary);
+        if let Some(correction) = data.lastfm_correction.as_ref().filter(|_| original.artist == primary) {
+            if correction.artist_corrected && names.artist == original.artist { names.artist.clone_from(&correction.artist) }
+            if correction.track_corrected && names.track == original.track { names.track.clone_from(&correction.track) }
+        }
+
+        Some(Submission {
+            names,
             duration_in_seconds: track.duration.map(|d| d as u32),
             track_number: track.track_number.map(|n| n.get() as u32),
-            mbid: None
+            mbid: data.musicbrainz.as_ref().map(|ids| ids.recording)
         })
     }
 }
 #[async_trait::async_trait]
 impl StatusBackend for LastFM {
+    async fn get_additional_data_solicitation(&self) -> ComponentSolicitation {
+        let mut solicitation = ComponentSolicitation::default();
+        solicitation.list.insert(Component::MusicBrainzIds);
+        solicitation.list.insert(Component::LastFmCorrection);
+        solicitation
+    }
+
     #[tracing::instrument(skip(self, context), level = "debug")]
-    async fn record_as_listened(&self, context: super::BackendContext<()>) {
-        if let Some(info) = Self::track_to_heard(context.track.as_ref()) {
-            if let Err(error) = self.client.scrobble(&[lastfm::scrobble::Scrobble {
-                chosen_by_user: None,
-                timestamp: chrono::Utc::now(),
-                info
-            }]).await {
-                tracing::error!(?error, "last.fm mark-listened failure")
+    async fn record_as_listened(&self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) {
+        let Some(submission) = self.submission(context.track.as_ref(), &context.data).await else {
+            tracing::warn!("scrobble skipped; track is missing required data (artist name)");
+            return;
+        };
+
+        let mut pending = self.pending.lock().await;
+        pending.push(PendingScrobble { submission, timestamp: chrono::Utc::now() });
+        // scrobbles that are too old would only be ignored
+        let oldest = chrono::Utc::now() - lastfm::scrobble::MAX_SCROBBLE_AGE;
+        pending.retain(|scrobble| scrobble.timestamp > oldest);
+
+        while !pending.is_empty() {
+            let count = pending.len().min(lastfm::scrobble::MAX_SCROBBLES_PER_REQUEST);
+            let batch = pending[..count].iter().map(PendingScrobble::as_scrobble).collect::<Vec<_>>();
+            match self.client.scrobble(&batch).await {
+                Ok(response) => {
+                    for (scrobble, result) in batch.iter().zip(&response.results) {
+                        match result {
+                            Ok(acknowledgement) => crate::corrections::learn(&scrobble.info, acknowledgement).await,
+                            Err(error) => tracing::warn!(?error, "last.fm ignored scrobble"),
+                        }
+                    }
+                    drop(batch);
+                    pending.drain(..count);
+                },
+                Err(error) if error.is_retryable() => {
+                    tracing::warn!(?error, queued = pending.len(), "last.fm mark-listened failure; will try again with the next scrobble");
+                    break;
+                },
+                Err(error) => {
+                    tracing::error!(?error, "last.fm mark-listened failure");
+                    drop(batch);
+                    pending.drain(..count);
+                },
             }
-        } else {
-            tracing::warn!("scrobble skipped; track is missing required data (artist name)")
         }
     }
 
     /// - <https://www.last.fm/api/scrobbling#scrobble-requests>
-    async fn check_eligibility(&self, context: super::BackendContext<()>) -> bool {
+    async fn check_eligibility(&self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) -> bool {
         if let Some(duration) = context.track.duration {
             let length = TimeDelta::from_secs_f32(duration);
             let time_listened = context.listened.lock().await.total_heard();
@@ -109,11 +181,32 @@ impl StatusBackend for LastFM {
         } else { false }
     }
 
+    /// Last.fm has no notion of disliking, so only favorites are reflected (as loves).
+    /// - <https://www.last.fm/api/show/track.love>
+    #[tracing::instrument(skip(self, context), level = "debug")]
+    async fn update_feedback(&mut self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) {
+        let Some(submission) = self.submission(context.track.as_ref(), &context.data).await else {
+            tracing::warn!("last.fm love dispatch skipped; track is missing required data (artist name)");
+            return;
+        };
+        let info = submission.as_heard();
+        let result = if context.track.favorited {
+            self.client.love(info.artist, info.track).await
+        } else {
+            self.client.unlove(info.artist, info.track).await
+        };
+        if let Err(error) = result {
+            tracing::error!(?error, "last.fm love dispatch failure")
+        }
+    }
+
     #[tracing::instrument(skip(self, context), level = "debug")]
     async fn set_now_listening(&mut self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) {
-        if let Some(info) = Self::track_to_heard(context.track.as_ref()) {
-            if let Err(error) = self.client.set_now_listening(&info).await {
-                tracing::error!(?error, "last.fm now-listening dispatch failure")
+        if let Some(submission) = self.submission(context.track.as_ref(), &context.data).await {
+            let info = submission.as_heard();
+            match self.client.set_now_listening(&info).await {
+                Ok(acknowledgement) => crate::corrections::learn(&info, &acknowledgement).await,
+                Err(error) => tracing::error!(?error, "last.fm now-listening dispatch failure"),
             }
         } else {
             tracing::warn!("last.fm now-listening dispatch skipped; track is missing required data (artist name)")
@@ -2,10 +2,11 @@ use std::sync::Arc;
 use maybe_owned_string::MaybeOwnedStringDeserializeToOwned;
 
 use super::{StatusBackend, TimeDeltaExtension as _};
+use crate::{corrections::TrackNames, data_fetching::components::{Component, ComponentSolicitation}, normalization::ArtistCredit};
 
 const FOUR_MINUTES: chrono::TimeDelta = chrono::TimeDelta::new(4 * 60, 0).unwrap();
 
-use brainz::{listen::v1::submit_listens::additional_info, music::request_client::ProgramInfo};
+use brainz::{listen::v1::{feedback::FeedbackScore, submit_listens::additional_info}, music::request_client::ProgramInfo};
 
 type S = MaybeOwnedStringDeserializeToOwned<'static>;
 type P = ProgramInfo<S>;
@@ -34,10 +35,18 @@ pub struct Config {
     )]
     pub program_info: ProgramInfo<S>,
     pub user_token: Option<brainz::listen::v1::UserToken>,
+    /// Whether to submit names with the remembered corrections (learned from Last.fm, or set from the command line) applied.
+    #[serde(default)]
+    pub apply_corrections: bool,
+    /// Every credited artist is submitted by default, since ListenBrainz maps the whole credit to MusicBrainz artists.
+    #[serde(default = "ArtistCredit::all")]
+    pub artists: ArtistCredit,
 }
 
 pub struct ListenBrainz {
     client: Arc<brainz::listen::v1::Client<S>>,
+    apply_corrections: bool,
+    artists: ArtistCredit,
 }
 impl core::fmt::Debug for ListenBrainz {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
@@ -45,21 +54,47 @@ impl core::fmt::Debug for ListenBrainz {
     }
 }
 impl ListenBrainz {
-    pub fn new(program_info: ProgramInfo<MaybeOwnedStringDeserializeToOwned<'static>>, token: brainz::listen::v1::UserToken) -> Self {
-        Self { client: Arc::new(brainz::listen::v1::Client::new(program_info, Some(token))) }
+    pub fn new(program_info: ProgramInfo<MaybeOwnedStringDeserializeToOwned<'static>>, token: brainz::listen::v1::UserToken, apply_corrections: bool, artists: ArtistCredit) -> Self {
+        Self { client: Arc::new(brainz::listen::v1::Client::new(program_info, Some(token))), apply_corrections, artists }
     }
 
-    fn basic_track_metadata(track: &osa_apple_music::track::Track) -> Option<brainz::listen::v1::submit_listens::BasicTrackMetadata<'_>> {
-        Some(brainz::listen::v1::submit_listens::BasicTrackMetadata {
-            artist: track.artist.as_deref()?,
-            track: &track.name,
-            release: track.album.name.as_deref()
-        })
+    /// Favorites are loves and dislikes are hates; a track which is neither has its feedback removed.
+    pub fn feedback_score(favorited: bool, disliked: bool) -> FeedbackScore {
+        match (favorited, disliked) {
+            (true, _) => FeedbackScore::Love,
+            (false, true) => FeedbackScore::Hate,
+            (false, false) => FeedbackScore::Remove,
+        }
+    }
+
+    /// Returns `None` if the track is missing required data (the artist name).
+    async fn names(&self, track: &osa_apple_music::track::Track) -> Option<TrackNames> {
+        let mut names = crate::normalization::rules().track_names(track, self.artists)?;
+        if self.apply_corrections {
+            crate::corrections::apply(&mut names).await;
+        }
+        Some(names)
+    }
+
+    fn basic_track_metadata(names: &TrackNames) -> brainz::listen::v1::submit_listens::BasicTrackMetadata<'_> {
+        brainz::listen::v1::submit_listens::BasicTrackMetadata {
+            artist: &names.artist,
+            track: &names.track,
+            release: names.album.as_deref()
+        }
     }
 
-    fn additional_info<'a>(track: &'a osa_apple_music::track::Track, app: &'a osa_apple_music::application::ApplicationData, program: &'a brainz::music::request_client::ProgramInfo<S>) -> brainz::listen::v1::submit_listens::additional_info::AdditionalInfo<'a> {
+    fn additional_info<'a>(track: &'a osa_apple_music::track::Track, data: &crate::data_fetching::AdditionalTrackData, app: &'a osa_apple_music::application::ApplicationData, program: &'a brainz::music::request_client::ProgramInfo<S>) -> brainz::listen::v1::submit_listens::additional_info::AdditionalInfo<'a> {
         use brainz::listen::v1::submit_listens::additional_info::*;
+        let ids = data.musicbrainz.as_ref().map(|ids| BrainzIds {
+            recording: Some(ids.recording),
+            release: ids.release,
+            release_group: ids.release_group,
+            artists: Some(ids.artists.clone()),
+            ..Default::default()
+        });
         AdditionalInfo {
+            ids: ids.unwrap_or_default(),
             duration: track.duration.map(core::time::Duration::from_secs_f32),
             track_number: track.track_number.map(|n| n.get() as u32),
             submission_client: Some(program),
@@ -74,11 +109,18 @@ impl ListenBrainz {
 }
 #[async_trait::async_trait]
 impl StatusBackend for ListenBrainz {
+    async fn get_additional_data_solicitation(&self) -> ComponentSolicitation {
+        let mut solicitation = ComponentSolicitation::default();
+        solicitation.list.insert(Component::MusicBrainzIds);
+        solicitation
+    }
+
     #[tracing::instrument(skip(self, context), level = "debug")]   
-    async fn record_as_listened(&self, context: super::BackendContext<()>) {
+    async fn record_as_listened(&self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) {
         // TODO: catch network errors and add to a queue.
-        if let Some(track_data) = Self::basic_track_metadata(&context.track) {
-            let additional_info = Self::additional_info(&context.track, &context.app, self.client.get_program_info());
+        if let Some(names) = self.names(&context.track).await {
+            let track_data = Self::basic_track_metadata(&names);
+            let additional_info = Self::additional_info(&context.track, &context.data, &context.app, self.client.get_program_info());
             let started_listening_at = if let Some(at) = context.listened.lock().await.started_at() { at } else { tracing::error!("no start duration for current listening"); return };
             if let Err(error) = self.client.submit_listen(track_data, started_listening_at, Some(additional_info)).await {
                 tracing::error!(?error, "listenbrainz now-listening failure")
@@ -89,7 +131,7 @@ impl StatusBackend for ListenBrainz {
     }
 
     /// - <https://listenbrainz.readthedocs.io/en/latest/users/api/core.html#post--1-submit-listens>
-    async fn check_eligibility(&self, context: super::BackendContext<()>) -> bool {
+    async fn check_eligibility(&self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) -> bool {
         if let Some(duration) = context.track.duration {
             let length = core::time::Duration::from_secs_f32(duration);
             let time_listened = context.listened.lock().await.total_heard();
@@ -98,10 +140,24 @@ impl StatusBackend for ListenBrainz {
         } else { false }
     }
 
+    /// - <https://listenbrainz.readthedocs.io/en/latest/users/api/recordings.html#post--1-feedback-recording-feedback>
+    #[tracing::instrument(skip(self, context), level = "debug")]
+    async fn update_feedback(&mut self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) {
+        let Some(ids) = context.data.musicbrainz.as_ref() else {
+            tracing::warn!("listenbrainz feedback dispatch skipped; track has no known MusicBrainz recording");
+            return;
+        };
+        let score = Self::feedback_score(context.track.favorited, context.track.disliked);
+        if let Err(error) = self.client.submit_feedback(ids.recording, score).await {
+            tracing::error!(?error, "listenbrainz feedback failure")
+        }
+    }
+
     #[tracing::instrument(skip(self, context), level = "debug")]
     async fn set_now_listening(&mut self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) {
-        if let Some(track_data) = Self::basic_track_metadata(&context.track) {
-            let additional_info = Self::additional_info(&context.track, &context.app, self.client.get_program_info());
+        if let Some(names) = self.names(&context.track).await {
+            let track_data = Self::basic_track_metadata(&names);
+            let additional_info = Self::additional_info(&context.track, &context.data, &context.app, self.client.get_program_info());
             if let Err(error) = self.client.submit_playing_now(track_data, Some(additional_info)).await {
                 tracing::error!(?error, "listenbrainz mark-listened failure")
             }
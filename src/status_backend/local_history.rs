@@ -0,0 +1,51 @@
+use super::{StatusBackend, TimeDeltaExtension as _};
+use crate::history::store::{HistoryEntry, HistoryStore};
+
+/// Keeps a local record of every play, including ones too short to be submitted elsewhere.
+#[derive(Debug, Default)]
+pub struct LocalHistory {
+    store: HistoryStore,
+}
+impl LocalHistory {
+    pub fn new(store: HistoryStore) -> Self {
+        Self { store }
+    }
+}
+#[async_trait::async_trait]
+impl StatusBackend for LocalHistory {
+    #[tracing::instrument(skip(self, context), level = "debug")]
+    async fn record_as_listened(&self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) {
+        let listened = context.listened.lock().await;
+        let started_at = listened.contiguous.iter()
+            .map(|chunk| chunk.started_at)
+            .chain(listened.started_at())
+            .min();
+        let Some(started_at) = started_at else { tracing::error!("no start time for ended track"); return };
+
+        let entry = HistoryEntry {
+            started_at,
+            ended_at: chrono::Utc::now(),
+            persistent_id: context.track.persistent_id.clone(),
+            track: context.track.name.clone(),
+            artist: context.track.artist.clone(),
+            album: context.track.album.name.clone(),
+            album_artist: context.track.album.artist.clone(),
+            genre: context.track.genre.clone(),
+            duration: context.track.duration,
+            heard: listened.total_heard().as_secs_f32(),
+            covered: Some(listened.total_heard_unique().as_secs_f32()),
+        };
+        drop(listened);
+
+        if let Err(error) = self.store.append(&entry).await {
+            tracing::error!(?error, "failed to record play to local history")
+        }
+    }
+
+    /// Everything that was heard at all is recorded; what counts as a listen is decided when reading the history.
+    async fn check_eligibility(&self, context: super::BackendContext<crate::data_fetching::AdditionalTrackData>) -> bool {
+        !context.listened.lock().await.total_heard().is_zero()
+    }
+
+    async fn set_now_listening(&mut self, _: super::BackendContext<crate::data_fetching::AdditionalTrackData>) {}
+}
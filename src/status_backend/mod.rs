@@ -42,6 +42,7 @@ pub mod listenbrainz;
 pub mod lastfm;
 #[cfg(feature = "discord")]
 pub mod discord;
+pub mod local_history;
 
 #[derive(Debug)]
 pub struct ListenedChunk {
@@ -198,9 +199,19 @@ impl<A> Clone for BackendContext<A> {
 #[async_trait::async_trait]
 pub trait StatusBackend: core::fmt::Debug + Send + Sync {
     async fn set_now_listening(&mut self, context: BackendContext<crate::data_fetching::AdditionalTrackData>);
-    async fn record_as_listened(&self, context: BackendContext<()>);
-    async fn check_eligibility(&self, context: BackendContext<()>) -> bool;
+    async fn record_as_listened(&self, context: BackendContext<crate::data_fetching::AdditionalTrackData>);
+    async fn check_eligibility(&self, context: BackendContext<crate::data_fetching::AdditionalTrackData>) -> bool;
     async fn update_progress(&mut self, context: BackendContext<()>) {}
+    /// The player was paused on the current track.
+    async fn pause(&mut self) {}
+    /// The player resumed playing the same track it was paused on.
+    async fn resume(&mut self, context: BackendContext<()>) {}
+    /// The player stopped, and no track is current anymore.
+    async fn stop(&mut self) {}
+    /// The custom artwork of the current track was uploaded again, since its previous URL was about to expire.
+    async fn update_artwork(&mut self, url: String) {}
+    /// The current track was favorited, disliked, or had either cleared while it was playing.
+    async fn update_feedback(&mut self, context: BackendContext<crate::data_fetching::AdditionalTrackData>) {}
     async fn get_additional_data_solicitation(&self) -> ComponentSolicitation {
         ComponentSolicitation::default()
     }
@@ -212,7 +223,8 @@ pub struct StatusBackends {
     #[cfg(feature = "lastfm")]
     pub lastfm: Option<Arc<Mutex<lastfm::LastFM>>>,
     #[cfg(feature = "listenbrainz")]
-    pub listenbrainz: Option<Arc<Mutex<listenbrainz::ListenBrainz>>>
+    pub listenbrainz: Option<Arc<Mutex<listenbrainz::ListenBrainz>>>,
+    pub local_history: Option<Arc<Mutex<local_history::LocalHistory>>>
 }
 impl core::fmt::Debug for StatusBackends {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -224,6 +236,7 @@ impl core::fmt::Debug for StatusBackends {
         { if self.lastfm.is_some() { set = set.entry(&"LastFM") } }
         #[cfg(feature = "listenbrainz")]
         { if self.listenbrainz.is_some() { set = set.entry(&"ListenBrainz") } }
+        if self.local_history.is_some() { set = set.entry(&"LocalHistory") }
 
         set.finish()
     }
@@ -248,6 +261,8 @@ impl StatusBackends {
             (listenbrainz, "listenbrainz"),
         ]);
 
+        if let Some(backend) = &self.local_history { backends.push(backend.clone()) }
+
         backends
     }
 
@@ -263,7 +278,7 @@ impl StatusBackends {
 
     
     #[tracing::instrument(skip(context), level = "debug")]
-    pub async fn dispatch_track_ended(&self, context: BackendContext<()>) {
+    pub async fn dispatch_track_ended(&self, context: BackendContext<crate::data_fetching::AdditionalTrackData>) {
         let backends = self.all();
         let mut jobs = Vec::with_capacity(backends.len());
 
@@ -316,6 +331,89 @@ impl StatusBackends {
         }
     }
 
+    #[tracing::instrument(level = "debug")]
+    pub async fn dispatch_paused(&self) {
+        let backends = self.all();
+        let mut jobs = Vec::with_capacity(backends.len());
+
+        for backend in backends {
+            jobs.push(tokio::spawn(async move {
+                backend.lock().await.pause().await;
+            }));
+        }
+
+        for job in jobs {
+            job.await.unwrap();
+        }
+    }
+
+    #[tracing::instrument(skip(context), level = "debug")]
+    pub async fn dispatch_resumed(&self, context: BackendContext<()>) {
+        let backends = self.all();
+        let mut jobs = Vec::with_capacity(backends.len());
+
+        for backend in backends {
+            let context = context.clone();
+            jobs.push(tokio::spawn(async move {
+                backend.lock().await.resume(context).await;
+            }));
+        }
+
+        for job in jobs {
+            job.await.unwrap();
+        }
+    }
+
+    #[tracing::instrument(level = "debug")]
+    pub async fn dispatch_stopped(&self) {
+        let backends = self.all();
+        let mut jobs = Vec::with_capacity(backends.len());
+
+        for backend in backends {
+            jobs.push(tokio::spawn(async move {
+                backend.lock().await.stop().await;
+            }));
+        }
+
+        for job in jobs {
+            job.await.unwrap();
+        }
+    }
+
+    #[tracing::instrument(level = "debug")]
+    pub async fn dispatch_artwork_refreshed(&self, url: String) {
+        let backends = self.all();
+        let mut jobs = Vec::with_capacity(backends.len());
+
+        for backend in backends {
+            let url = url.clone();
+            jobs.push(tokio::spawn(async move {
+                backend.lock().await.update_artwork(url).await;
+            }));
+        }
+
+        for job in jobs {
+            job.await.unwrap();
+        }
+    }
+
+    #[tracing::instrument(skip(context), level = "debug", fields(track = &context.track.persistent_id))]
+    pub async fn dispatch_feedback_changed(&self, context: BackendContext<crate::data_fetching::AdditionalTrackData>) {
+        let backends = self.all();
+        let mut jobs = Vec::with_capacity(backends.len());
+
+        for backend in backends {
+            let context = context.clone();
+            jobs.push(tokio::spawn(async move {
+                backend.lock().await.update_feedback(context).await;
+            }));
+        }
+
+        for job in jobs {
+            job.await.unwrap();
+        }
+    }
+
     pub async fn new(config: &crate::config::Config<'_>) -> StatusBackends {        
         #[cfg(feature = "lastfm")]
         use crate::status_backend::lastfm::*;
@@ -331,7 +429,8 @@ impl StatusBackends {
             if config.enabled {
                 Some(Arc::new(Mutex::new(LastFM::new(
                     config.identity.clone(),
-                    config.session_key.clone().expect("no session keys")
+                    config.session_key.clone().expect("no session keys"),
+                    config.artists
                 ))))
             } else { None }
         });
@@ -341,23 +440,31 @@ impl StatusBackends {
             if config.enabled {
                 Some(Arc::new(Mutex::new(ListenBrainz::new(
                     config.program_info.clone(),
-                    config.user_token.clone().expect("no token")
+                    config.user_token.clone().expect("no token"),
+                    config.apply_corrections,
+                    config.artists
                 ))))
             } else { None }
         });
 
         #[cfg(feature = "discord")]
-        let discord = if config.backends.discord {
-            let wrapped = Arc::new(Mutex::new(DiscordPresence::new().await));
+        let discord = if config.backends.discord.enabled {
+            let wrapped = Arc::new(Mutex::new(DiscordPresence::new(&config.backends.discord).await));
+            DiscordPresence::register_self_reference(&wrapped).await;
             let weak = Arc::downgrade(&wrapped);
             DiscordPresence::enable_auto_reconnect(weak).await;
             Some(wrapped)
         } else { None };
 
+        let local_history = if config.backends.local_history {
+            Some(Arc::new(Mutex::new(local_history::LocalHistory::default())))
+        } else { None };
+
         StatusBackends {
             #[cfg(feature = "lastfm")] lastfm,
             #[cfg(feature = "discord")] discord,
-            #[cfg(feature = "listenbrainz")] listenbrainz
+            #[cfg(feature = "listenbrainz")] listenbrainz,
+            local_history
         }
     }
 }
\ No newline at end of file
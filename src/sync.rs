@@ -0,0 +1,170 @@
+//! Bringing the state of the Apple Music library over to the enabled services in bulk.
+
+use crate::{cli::HistoryService, config::Config, util::{ferror, HOME}};
+
+/// The number of pending changes shown per service when previewing a sync.
+const PREVIEW_LIMIT: usize = 20;
+
+async fn read_library_feedback() -> Vec<osa_apple_music::track::LibraryTrackFeedback> {
+    let mut session = osa_apple_music::Session::new(
+        HOME.join("Library/Application Support/am-osx-status/osa-sync-socket")
+    ).await.unwrap_or_else(|error| ferror!("failed to create `osa_apple_music` session: {error}"));
+
+    session.library_feedback().await
+        .unwrap_or_else(|error| ferror!("could not read library favorites: {error}"))
+}
+
+/// Loves favorited tracks and hates disliked ones on the given services (or every enabled one).
+/// Only tracks that are favorited or disliked are read from the library, so feedback on other tracks is left alone.
+/// Every service is synced even if another one fails, but any failure is an error once they're done.
+pub async fn feedback(config: &Config<'_>, dry_run: bool, services: &[HistoryService]) {
+    let services = crate::history::import::resolve_services(config, services);
+
+    let tracks = read_library_feedback().await;
+    println!(
+        "Found {} favorited and {} disliked tracks in the library.",
+        tracks.iter().filter(|track| track.favorited).count(),
+        tracks.iter().filter(|track| !track.favorited && track.disliked).count(),
+    );
+
+    let mut failed = 0;
+    for service in services {
+        failed += match service {
+            #[cfg(feature = "listenbrainz")]
+            HistoryService::ListenBrainz => feedback_to_listenbrainz(config, &tracks, dry_run).await,
+            #[cfg(feature = "lastfm")]
+            HistoryService::LastFM => feedback_to_lastfm(config, &tracks, dry_run).await,
+        };
+    }
+    if failed > 0 {
+        ferror!("{failed} feedback updates failed")
+    }
+}
+
+fn preview<T>(pending: &[T], describe: impl Fn(&T) -> String) {
+    for change in pending.iter().take(PREVIEW_LIMIT) {
+        println!("  {}", describe(change));
+    }
+    if pending.len() > PREVIEW_LIMIT {
+        println!("  … and {} more", pending.len() - PREVIEW_LIMIT);
+    }
+}
+
+fn artist_of(track: &osa_apple_music::track::LibraryTrackFeedback) -> &str {
+    track.artist.as_deref().unwrap_or("Unknown Artist")
+}
+
+/// Returns the number of updates that failed.
+#[cfg(feature = "listenbrainz")]
+async fn feedback_to_listenbrainz(config: &Config<'_>, tracks: &[osa_apple_music::track::LibraryTrackFeedback], dry_run: bool) -> usize {
+    use std::collections::BTreeMap;
+    use brainz::listen::v1::feedback::FeedbackScore;
+    use crate::{data_fetching::services::musicbrainz, status_backend::listenbrainz::ListenBrainz};
+
+    let (client, username) = crate::history::import::listenbrainz_client(config).await;
+    let existing = client.get_all_feedback(&username, None).await
+        .unwrap_or_else(|error| ferror!("could not retrieve existing ListenBrainz feedback: {error}"))
+        .into_iter()
+        .filter_map(|feedback| Some((feedback.recording_mbid?, feedback.score)))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut pending = Vec::new();
+    let mut unmatched = 0;
+    for track in tracks {
+        let found = musicbrainz::find_recording_of(metadata_match::Metadata {
+            title: &track.name,
+            artist: track.artist.as_deref(),
+            album: track.album.as_deref(),
+            duration: track.duration,
+        }).await;
+        let ids = match found {
+            Ok(Some(ids)) => ids,
+            Ok(None) => { unmatched += 1; continue },
+            Err(error) => {
+                tracing::warn!(?error, track = track.name, "musicbrainz search failed");
+                unmatched += 1;
+                continue;
+            }
+        };
+        let score = ListenBrainz::feedback_score(track.favorited, track.disliked);
+        if existing.get(&ids.recording) != Some(&score) {
+            pending.push((track, ids.recording, score));
+        }
+    }
+
+    println!(
+        "ListenBrainz: {} tracks need their feedback updated; {unmatched} could not be found on MusicBrainz",
+        pending.len()
+    );
+    if dry_run {
+        preview(&pending, |(track, _, score)| {
+            let verb = if *score == FeedbackScore::Love { "love" } else { "hate" };
+            format!("{verb}  {} — {}", artist_of(track), track.name)
+        });
+        return 0;
+    }
+
+    let mut failed = 0;
+    for (track, recording, score) in &pending {
+        if let Err(error) = client.submit_feedback(*recording, *score).await {
+            tracing::error!(?error, track = track.name, "listenbrainz feedback failure");
+            failed += 1;
+        }
+    }
+    println!("ListenBrainz: updated feedback on {} tracks; {failed} failed", pending.len() - failed);
+    failed
+}
+
+/// Last.fm has no notion of disliking, so favorited tracks are loved and disliked tracks that are loved are unloved.
+/// Returns the number of updates that failed.
+#[cfg(feature = "lastfm")]
+async fn feedback_to_lastfm(config: &Config<'_>, tracks: &[osa_apple_music::track::LibraryTrackFeedback], dry_run: bool) -> usize {
+    use std::collections::BTreeSet;
+    use lastfm::user::MAX_LOVED_TRACKS_PER_PAGE;
+    use crate::normalization::ArtistCredit;
+
+    let (client, username) = crate::history::import::lastfm_client(config).await;
+
+    // loves are matched by name, as that's all Last.fm identifies them by
+    let key = |artist: &str, track: &str| (artist.to_lowercase(), track.to_lowercase());
+    let mut loved = BTreeSet::new();
+    let mut page = 1;
+    loop {
+        let response = client.get_loved_tracks(&username, Some(page), Some(MAX_LOVED_TRACKS_PER_PAGE)).await
+            .unwrap_or_else(|error| ferror!("could not retrieve existing Last.fm loved tracks: {error}"));
+        loved.extend(response.tracks.iter().map(|track| key(&track.artist, &track.track)));
+        if page >= response.total_pages { break }
+        page += 1;
+    }
+
+    // named as they would be when loved as they're played
+    let credit = config.backends.lastfm.as_ref().map_or(ArtistCredit::Primary, |backend| backend.artists);
+    let pending = tracks.iter()
+        .filter_map(|track| Some((track, config.normalization.artist(track.artist.as_deref()?, credit), config.normalization.title(&track.name))))
+        .filter(|(track, artist, title)| track.favorited != loved.contains(&key(artist, title)))
+        .collect::<Vec<_>>();
+
+    println!("Last.fm: {} tracks need to be loved or unloved", pending.len());
+    if dry_run {
+        preview(&pending, |(track, artist, title)| {
+            let verb = if track.favorited { "love" } else { "unlove" };
+            format!("{verb}  {artist} — {title}")
+        });
+        return 0;
+    }
+
+    let mut failed = 0;
+    for (track, artist, title) in &pending {
+        let result = if track.favorited {
+            client.love(artist, title).await
+        } else {
+            client.unlove(artist, title).await
+        };
+        if let Err(error) = result {
+            tracing::error!(?error, track = track.name, "last.fm love failure");
+            failed += 1;
+        }
+    }
+    println!("Last.fm: updated {} tracks; {failed} failed", pending.len() - failed);
+    failed
+}